@@ -0,0 +1,98 @@
+//! Reads the PZEM004T off an Arduino Uno (ATmega328P: 32 KB flash, 2 KB RAM).
+//!
+//! The only hardware USART of the Uno is connected to the sensor (D0 = RX, D1 = TX),
+//! so it has to be disconnected while flashing. Having no console left, the on-board LED
+//! lights up while the power exceeds `POWER_LIMIT` and blinks on communication errors.
+//!
+//! The driver is built with the `no-float` and `size-opt` features and read through
+//! `read_raw`, comparing the power in fixed point: the AVR has no FPU, and the soft-float
+//! routines alone would take a sizeable part of the flash. The driver itself takes 232 bytes
+//! of RAM on a 64-bit host with these features, less with the 16-bit pointers of the AVR;
+//! `RAM_BUDGET` fails the build should it grow past an eighth of the RAM. Measure the whole
+//! image with:
+//!
+//! ```text
+//! cargo build --release
+//! avr-size -C --mcu=atmega328p target/avr-atmega328p/release/arduino-uno.elf
+//! ```
+
+#![no_std]
+#![no_main]
+
+extern crate pzem004t;
+
+use core::marker::PhantomData;
+
+use embedded_hal::timer::CountDown;
+use panic_halt as _;
+
+const POWER_LIMIT: u32 = 10_000; // 0.1 W
+const TIMEOUT: u16 = 500; // ms
+const RAM_BUDGET: usize = 256; // bytes
+
+// Size of the driver checked at compile time by evaluating `FITS`.
+struct Budget<T>(PhantomData<T>);
+
+impl<T> Budget<T> {
+    const FITS: () = assert!(core::mem::size_of::<T>() <= RAM_BUDGET);
+}
+
+fn check_budget<T>(_: &T) {
+    let () = Budget::<T>::FITS;
+}
+
+/// The Uno has no spare CountDown timer in arduino-hal, so the timeout is counted
+/// by short busy-wait delays between the polls of the serial port.
+struct PollTimer {
+    remaining_us: u32,
+}
+
+impl CountDown for PollTimer {
+    type Time = u16; // ms
+    fn start<T: Into<Self::Time>>(&mut self, count: T) {
+        self.remaining_us = count.into() as u32 * 1000;
+    }
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        if self.remaining_us == 0 {
+            return Ok(());
+        }
+
+        arduino_hal::delay_us(10);
+        self.remaining_us = self.remaining_us.saturating_sub(10);
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // USART0 at 9600 8N1, as required by the sensor
+    let serial = arduino_hal::default_serial!(dp, pins, 9600);
+    let mut led = pins.d13.into_output();
+
+    let mut tim = PollTimer { remaining_us: 0 };
+    let mut pzem = pzem004t::Pzem::new(serial, None).unwrap();
+    check_budget(&pzem);
+    let mut m = pzem004t::RawMeasurement::default();
+
+    loop {
+        match pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))) {
+            Err(_) => {
+                led.toggle();
+                arduino_hal::delay_ms(100);
+                led.toggle();
+            }
+            Ok(()) => {
+                if m.power > POWER_LIMIT {
+                    led.set_high();
+                } else {
+                    led.set_low();
+                }
+            }
+        }
+
+        arduino_hal::delay_ms(1000);
+    }
+}
@@ -0,0 +1,46 @@
+//! Reads the PZEM004T off an ESP32 running ESP-IDF.
+//!
+//! UART2 is connected to the sensor (GPIO17 = TX, GPIO16 = RX), the measurements
+//! are printed to the console of the ESP-IDF monitor.
+
+use core::fmt;
+use std::time::Duration;
+
+use esp_idf_hal::gpio;
+use esp_idf_hal::peripherals::Peripherals;
+use esp_idf_hal::prelude::*;
+use esp_idf_hal::uart::{config::Config, UartDriver};
+use pzem004t::StdTimer;
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+const PERIOD: Duration = Duration::from_secs(1);
+
+/// The standard output as the console of the examples.
+struct Stdout;
+
+impl fmt::Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
+
+fn main() {
+    esp_idf_sys::link_patches();
+
+    let p = Peripherals::take().unwrap();
+
+    // 9600 8N1, as required by the sensor
+    let serial = UartDriver::new(
+        p.uart2,
+        p.pins.gpio17,
+        p.pins.gpio16,
+        Option::<gpio::Gpio0>::None,
+        Option::<gpio::Gpio1>::None,
+        &Config::default().baudrate(Hertz(9_600)),
+    )
+    .unwrap();
+
+    let mut tim = StdTimer::new();
+    example_core::run(serial, &mut tim, TIMEOUT, PERIOD, &mut Stdout)
+}
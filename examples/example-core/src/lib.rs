@@ -0,0 +1,59 @@
+//! Application logic shared by the per-target examples: the measurement report
+//! and the polling loop. The binaries only set up the serial port, the timer
+//! and the console of their board.
+//!
+//! To add a board, create a binary crate next to the others depending on this one,
+//! and pass its peripherals to [`run`].
+
+#![no_std]
+
+use core::fmt::{self, Write};
+
+use embedded_hal::serial;
+use embedded_hal::timer::CountDown;
+use nb::block;
+use pzem004t::{Error, Measurement, Pzem};
+
+/// Writes the measurement in a human readable form, one quantity per line.
+pub fn write_measurement<W: Write>(w: &mut W, m: &Measurement) -> fmt::Result {
+    writeln!(w, "Voltage: {:.1} V", m.voltage)?;
+    writeln!(w, "Current: {:.3} A", m.current)?;
+    writeln!(w, "Power: {:.1} W", m.power)?;
+    writeln!(w, "Energy: {:.3} kWh", m.energy)?;
+    writeln!(w, "Frequency: {:.1} Hz", m.frequency)?;
+    writeln!(w, "Power factor: {:.2}", m.pf)?;
+    writeln!(w, "Alarm: {}", m.alarm)
+}
+
+/// Writes the outcome of a single read to the console.
+pub fn report<W: Write, WE, RE>(
+    w: &mut W,
+    res: Result<&Measurement, Error<WE, RE>>,
+) -> fmt::Result {
+    match res {
+        Ok(m) => write_measurement(w, m).and_then(|()| writeln!(w)),
+        Err(e) => writeln!(w, "Could not read PZEM004T: {}", e),
+    }
+}
+
+/// Reads the sensor every `period`, writing the outcomes to `console`.
+///
+/// The timer counts down both the timeout of the reads and the polling period.
+pub fn run<S, T, W>(serial: S, tim: &mut T, timeout: T::Time, period: T::Time, console: &mut W) -> !
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+    T: CountDown,
+    T::Time: Clone,
+    W: Write,
+{
+    let mut pzem = Pzem::new(serial, None).ok().unwrap();
+    let mut m = Measurement::default();
+
+    loop {
+        let res = pzem.read(&mut m, Some((&mut *tim, timeout.clone())));
+        report(console, res.map(|()| &m)).ok();
+
+        tim.start(period.clone());
+        block!(tim.wait()).ok();
+    }
+}
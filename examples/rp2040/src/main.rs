@@ -0,0 +1,82 @@
+//! Reads the PZEM004T off a Raspberry Pi Pico, using the hardware UART0 (no PIO).
+//!
+//! The measurements are printed over RTT, e.g. with `probe-rs attach`.
+//!
+//! Wiring:
+//!
+//! - Pico GP0 (UART0 TX) -> PZEM RX
+//! - Pico GP1 (UART0 RX) <- PZEM TX
+//! - Pico VBUS (5 V) -> PZEM 5V, Pico GND -> PZEM GND
+//!
+//! The GPIOs of the RP2040 are NOT 5 V tolerant. The TTL side of PZEM004T is built around
+//! optocouplers pulled up to its 5V pin, so the PZEM TX line swings up to 5 V: put a level
+//! shifter or a voltage divider (e.g. 1 kΩ in series, 2 kΩ to GND) in front of GP1.
+//! Driving the PZEM RX from the 3.3 V GP0 works, as it only has to sink the optocoupler LED current.
+//! Alternatively, powering the PZEM 5V pin from the 3.3 V rail keeps the whole link at 3.3 V,
+//! which works with most v3.0 modules.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use fugit::{MicrosDurationU64, RateExtU32};
+use rp_pico::entry;
+use rp_pico::hal::{
+    self,
+    clocks::init_clocks_and_plls,
+    gpio::FunctionUart,
+    pac,
+    uart::{DataBits, StopBits, UartConfig, UartPeripheral},
+    Clock, Sio, Timer, Watchdog,
+};
+use rtt_target::rtt_init_default;
+
+const TIMEOUT: MicrosDurationU64 = MicrosDurationU64::millis(500);
+const PERIOD: MicrosDurationU64 = MicrosDurationU64::secs(1);
+
+#[entry]
+fn main() -> ! {
+    let channels = rtt_init_default!();
+    let mut console = channels.up.0;
+
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+    let clocks = init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // UART0 at 9600 8N1, as required by the sensor
+    let uart_pins = (
+        pins.gpio0.into_function::<FunctionUart>(),
+        pins.gpio1.into_function::<FunctionUart>(),
+    );
+    let serial = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(9_600.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // The 1 MHz system timer counts down both the timeout and the polling period.
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut tim: hal::timer::CountDown = timer.count_down();
+
+    example_core::run(serial, &mut tim, TIMEOUT, PERIOD, &mut console)
+}
@@ -1,13 +1,10 @@
 #![no_std]
 #![no_main]
 
-extern crate pzem004t;
-
-use nb::block;
 use panic_halt as _;
 
 use cortex_m_rt::entry;
-use cortex_m_semihosting::hprintln;
+use cortex_m_semihosting::hio;
 use stm32f1xx_hal::{
     pac,
     prelude::*,
@@ -17,6 +14,7 @@ use stm32f1xx_hal::{
 };
 
 const TIMEOUT: Hertz = Hertz(1);
+const PERIOD: Hertz = Hertz(1);
 
 #[entry]
 fn main() -> ! {
@@ -54,24 +52,7 @@ fn main() -> ! {
     );
 
     let mut tim = Timer::syst(cp.SYST, &clocks).start_count_down(1.hz());
-    let mut pzem = pzem004t::Pzem::new(serial, None).unwrap();
-    let mut m = pzem004t::Measurement::default();
-
-    loop {
-        match pzem.read(&mut m, Some((&mut tim, TIMEOUT))) {
-            Err(e) => hprintln!("Could not read PZEM004T: {}", e).unwrap(),
-            Ok(()) => {
-                hprintln!("Voltage: {:.1} V", m.voltage).unwrap();
-                hprintln!("Current: {:.3} A", m.current).unwrap();
-                hprintln!("Power: {:.1} W", m.power).unwrap();
-                hprintln!("Energy: {:.3} kWh", m.energy).unwrap();
-                hprintln!("Frequency: {:.1} Hz", m.frequency).unwrap();
-                hprintln!("Power factor: {:.2}", m.pf).unwrap();
-                hprintln!("Alarm: {}\n", m.alarm).unwrap();
-            }
-        }
+    let mut console = hio::hstdout().unwrap();
 
-        tim.start(1.hz());
-        block!(tim.wait()).unwrap();
-    }
+    example_core::run(serial, &mut tim, TIMEOUT, PERIOD, &mut console)
 }
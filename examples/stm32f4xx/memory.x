@@ -0,0 +1,6 @@
+/* Linker script for the STM32F411CEU6 ("Black Pill") */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 512K
+  RAM : ORIGIN = 0x20000000, LENGTH = 128K
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use cortex_m_rt::entry;
+use cortex_m_semihosting::hio;
+use stm32f4xx_hal::{
+    pac, prelude::*, serial::config::Config, serial::Serial, time::Hertz, timer::Timer,
+};
+
+const TIMEOUT: Hertz = Hertz(1);
+const PERIOD: Hertz = Hertz(1);
+
+#[entry]
+fn main() -> ! {
+    // Get access to the core and the device specific peripherals
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let p = pac::Peripherals::take().unwrap();
+
+    // Freeze the configuration of all the clocks in the system and store the frequencies
+    let rcc = p.RCC.constrain();
+    let clocks = rcc.cfgr.sysclk(48.mhz()).freeze();
+
+    // USART2
+    let gpioa = p.GPIOA.split();
+    let tx = gpioa.pa2.into_alternate_af7();
+    let rx = gpioa.pa3.into_alternate_af7();
+
+    // 9600 8N1, as required by the sensor
+    let serial = Serial::usart2(
+        p.USART2,
+        (tx, rx),
+        Config::default().baudrate(9_600.bps()),
+        clocks,
+    )
+    .unwrap();
+
+    let mut tim = Timer::syst(cp.SYST, 1.hz(), clocks);
+    let mut console = hio::hstdout().unwrap();
+
+    example_core::run(serial, &mut tim, TIMEOUT, PERIOD, &mut console)
+}
@@ -0,0 +1,336 @@
+//! Command line tool for bench testing and commissioning PZEM004T energy monitors
+//! from a PC over a USB-RS485 (or USB-TTL) adapter.
+
+use std::env;
+use std::io;
+use std::process;
+use std::time::Duration;
+
+use pzem004t::prelude::*;
+use pzem004t::{
+    regs, Diagnosis, EmulatedSlave, LineConfig, LinkStatus, Parity, PzemEmulator, Reconnecting,
+    RetryPolicy,
+};
+
+const USAGE: &str = "\
+Usage: pzemctl [OPTIONS] COMMAND
+
+Options:
+    -p, --port PORT       Serial port of the adapter (default: /dev/ttyUSB0)
+    -a, --addr ADDR       Slave address (default: the general address 0xf8)
+    -t, --timeout MS      Timeout of each operation in ms (default: 500)
+
+Commands:
+    read                  Read the measurements
+    regs                  Dump the measurement registers
+    monitor               Read the measurements every second, reopening the port if lost
+    threshold [WATTS|off] Read or set the power alarm threshold, or turn the alarm off
+    addr [NEW]            Read or set the slave address
+    reset                 Reset the energy counter
+    scan [FROM-TO]        Scan the bus for slaves (default: 0x01-0xf7)
+    link                  Diagnose the serial link, trying common misconfigurations
+    bench [N]             Time N reads (default: 100) with and without draining the input
+    emulate               Emulate a sensor at ADDR (default: 0x01) with a switching 1 kW load";
+
+type Port = StdIo<Box<dyn serialport::SerialPort>>;
+
+struct Args {
+    port: String,
+    addr: Option<u8>,
+    timeout: Duration,
+    command: String,
+    arg: Option<String>,
+}
+
+fn parse_num(s: &str) -> Result<u16, String> {
+    let res = match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    res.map_err(|_| format!("Invalid number: {}", s))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+    let mut port = String::from("/dev/ttyUSB0");
+    let mut addr = None;
+    let mut timeout = Duration::from_millis(500);
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "-p" | "--port" => port = value()?,
+            "-a" | "--addr" => addr = Some(parse_num(&value()?)? as u8),
+            "-t" | "--timeout" => timeout = Duration::from_millis(parse_num(&value()?)? as u64),
+            "-h" | "--help" => return Err(String::new()),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = positional.next().ok_or_else(String::new)?;
+    let arg = positional.next();
+
+    Ok(Args {
+        port,
+        addr,
+        timeout,
+        command,
+        arg,
+    })
+}
+
+fn open(args: &Args) -> Result<Pzem<Port>, String> {
+    let port = serialport::new(&args.port, 9600)
+        .timeout(Duration::from_millis(10))
+        .open()
+        .map_err(|e| format!("Could not open {}: {}", args.port, e))?;
+
+    Pzem::new(StdIo::new(port), args.addr).map_err(describe)
+}
+
+fn emulate(args: &Args) -> Result<(), String> {
+    let port = serialport::new(&args.port, 9600)
+        .timeout(Duration::from_millis(10))
+        .open()
+        .map_err(|e| format!("Could not open {}: {}", args.port, e))?;
+
+    let addr = args.addr.unwrap_or(0x01);
+    let slave = EmulatedSlave::new(addr, RawMeasurement::default()).with_waveform(|t| {
+        // 1 kW load switched on for 10 seconds out of every 20.
+        let secs = t.as_secs();
+        let on = secs / 10 % 2 == 0;
+        RawMeasurement {
+            voltage: 2300,
+            current: if on { 4348 } else { 0 },
+            power: if on { 10_000 } else { 0 },
+            energy: ((secs / 20 * 10 + (secs % 20).min(10)) * 1000 / 3600) as u32,
+            frequency: 500,
+            pf: if on { 100 } else { 0 },
+            alarm: false,
+        }
+    });
+
+    println!("Emulating a sensor at {:#04x} on {}", addr, args.port);
+    PzemEmulator::new(port)
+        .with_slave(slave)
+        .run()
+        .map_err(|e| e.to_string())
+}
+
+fn monitor(args: &Args) -> Result<(), String> {
+    let path = args.port.clone();
+    let port = Reconnecting::new(
+        move || {
+            serialport::new(&path, 9600)
+                .timeout(Duration::from_millis(10))
+                .open()
+                .map_err(io::Error::from)
+        },
+        RetryPolicy::default(),
+    )
+    .map_err(|e| format!("Could not open {}: {}", args.port, e))?
+    .with_hook(|status| match status {
+        LinkStatus::Disconnected => eprintln!("Port lost, reopening"),
+        LinkStatus::Reconnected => eprintln!("Port reopened"),
+        LinkStatus::Connected => {}
+    });
+
+    let mut pzem = Pzem::new(StdIo::new(port), args.addr).map_err(describe)?;
+    let mut tim = StdTimer::new();
+    let mut m = Measurement::default();
+    loop {
+        match pzem.read(&mut m, Some((&mut tim, args.timeout))) {
+            Ok(()) => println!(
+                "{:.1} V, {:.3} A, {:.1} W, {:.3} kWh, {:.1} Hz, PF {:.2}",
+                m.voltage, m.current, m.power, m.energy, m.frequency, m.pf
+            ),
+            Err(e) => eprintln!("{}", describe(e)),
+        }
+
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn describe(e: Error<io::Error, io::Error>) -> String {
+    match e {
+        Error::WriteError(ref io) | Error::ReadError(ref io) => format!("{}: {}", e, io),
+        e => e.to_string(),
+    }
+}
+
+fn configure(port: &mut Box<dyn serialport::SerialPort>, config: LineConfig) -> io::Result<()> {
+    port.set_baud_rate(config.baud)?;
+    port.set_parity(match config.parity {
+        Parity::None => serialport::Parity::None,
+        Parity::Even => serialport::Parity::Even,
+        Parity::Odd => serialport::Parity::Odd,
+    })?;
+    port.set_stop_bits(match config.stop_bits {
+        2 => serialport::StopBits::Two,
+        _ => serialport::StopBits::One,
+    })?;
+
+    Ok(())
+}
+
+fn probe(
+    pzem: Pzem<Port>,
+    tim: &mut StdTimer,
+    timeout: Duration,
+) -> Result<Pzem<Port, Verified>, String> {
+    pzem.probe(Some((tim, timeout)))
+        .map_err(|(_, e)| format!("No response from the sensor: {}", describe(e)))
+}
+
+fn run(args: Args) -> Result<(), String> {
+    match args.command.as_str() {
+        "emulate" => return emulate(&args),
+        "monitor" => return monitor(&args),
+        _ => {}
+    }
+
+    let mut tim = StdTimer::new();
+    let timeout = args.timeout;
+    let mut pzem = open(&args)?;
+
+    match (args.command.as_str(), args.arg.as_deref()) {
+        ("read", None) => {
+            let mut m = Measurement::default();
+            pzem.read(&mut m, Some((&mut tim, timeout)))
+                .map_err(describe)?;
+
+            println!("Voltage: {:.1} V", m.voltage);
+            println!("Current: {:.3} A", m.current);
+            println!("Power: {:.1} W", m.power);
+            println!("Energy: {:.3} kWh", m.energy);
+            println!("Frequency: {:.1} Hz", m.frequency);
+            println!("Power factor: {:.2}", m.pf);
+            println!("Alarm: {}", m.alarm);
+        }
+        ("bench", n) => {
+            let n = n.map_or(Ok(100), parse_num)?.max(1);
+            let mut m = RawMeasurement::default();
+            for skip in [false, true] {
+                pzem = pzem.with_skip_drain(skip);
+                let start = std::time::Instant::now();
+                for _ in 0..n {
+                    pzem.read_raw(&mut m, Some((&mut tim, timeout)))
+                        .map_err(describe)?;
+                }
+                println!(
+                    "{}: {:.1} ms per read",
+                    if skip { "Skipped drain" } else { "Drain" },
+                    start.elapsed().as_secs_f64() * 1000.0 / n as f64
+                );
+            }
+        }
+        ("regs", None) => {
+            let mut m = RawMeasurement::default();
+            pzem.read_raw(&mut m, Some((&mut tim, timeout)))
+                .map_err(describe)?;
+
+            let block = m.to_registers();
+            for info in regs::MEASUREMENT.iter() {
+                let raw = info.raw(&block);
+                println!(
+                    "{:#06x} {:<10} {:>10} = {} {}",
+                    info.addr,
+                    info.name,
+                    raw,
+                    info.scale(raw),
+                    info.unit
+                );
+            }
+        }
+        ("threshold", None) => {
+            let threshold = pzem
+                .get_threshold(Some((&mut tim, timeout)))
+                .map_err(describe)?;
+            println!("{} W", threshold);
+        }
+        ("threshold", Some("off")) => {
+            probe(pzem, &mut tim, timeout)?
+                .disable_alarm(Some((&mut tim, timeout)))
+                .map_err(describe)?;
+        }
+        ("threshold", Some(watts)) => {
+            let watts = parse_num(watts)?;
+            probe(pzem, &mut tim, timeout)?
+                .set_threshold(watts, Some((&mut tim, timeout)))
+                .map_err(describe)?;
+        }
+        ("addr", None) => {
+            let addr = pzem.get_addr(Some((&mut tim, timeout))).map_err(describe)?;
+            println!("{:#04x}", addr);
+        }
+        ("addr", Some(new)) => {
+            let new = parse_num(new)? as u8;
+            probe(pzem, &mut tim, timeout)?
+                .set_addr(new, Some((&mut tim, timeout)))
+                .map_err(describe)?;
+        }
+        ("reset", None) => {
+            probe(pzem, &mut tim, timeout)?
+                .reset_energy(Some((&mut tim, timeout)))
+                .map_err(describe)?;
+        }
+        ("scan", range) => {
+            let (from, to) = match range {
+                Some(range) => {
+                    let (from, to) = range
+                        .split_once('-')
+                        .ok_or(format!("Invalid range: {}", range))?;
+                    (parse_num(from)? as u8, parse_num(to)? as u8)
+                }
+                None => (0x01, 0xf7),
+            };
+
+            for (addr, res) in pzem.scan(from..=to, Some((&mut tim, timeout))) {
+                match res {
+                    Ok(m) => println!("\r{:#04x}: {:.1} V, {:.1} W", addr, m.voltage, m.power),
+                    Err(Error::TimedOut { .. }) => eprint!("\r{:#04x}", addr),
+                    Err(e) => println!("\r{:#04x}: {}", addr, e),
+                }
+            }
+            eprintln!("\rDone");
+        }
+        ("link", None) => {
+            let results = pzem
+                .auto_probe(configure, &mut tim, timeout)
+                .map_err(|e| e.to_string())?;
+
+            for (config, diagnosis) in results.iter() {
+                println!(
+                    "{} baud, parity {:?}, {} stop bit(s): {:?}",
+                    config.baud, config.parity, config.stop_bits, diagnosis
+                );
+            }
+
+            match results.last() {
+                Some((config, Diagnosis::Healthy)) if *config == LineConfig::PZEM => {}
+                Some((_, Diagnosis::Healthy)) => {
+                    println!("The sensor answers with a nonstandard line configuration")
+                }
+                _ => return Err(String::from("No working line configuration found")),
+            }
+        }
+        _ => return Err(String::new()),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = parse_args().and_then(run) {
+        if e.is_empty() {
+            eprintln!("{}", USAGE);
+        } else {
+            eprintln!("pzemctl: {}", e);
+        }
+
+        process::exit(1);
+    }
+}
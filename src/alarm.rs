@@ -0,0 +1,84 @@
+use crate::{Measurement, MeasurementSink};
+
+/// Software hysteresis over the power (or the hardware alarm bit), so that e.g. a relay
+/// driven by the alarm doesn't chatter around the threshold.
+///
+/// The alarm goes on once the power reaches `set_at` and goes off once it drops to `clear_at`,
+/// in both cases only after `samples` consecutive measurements agree.
+///
+/// # Example
+/// ```
+/// use pzem004t::AlarmHysteresis;
+///
+/// let mut alarm = AlarmHysteresis::new(2000.0, 1800.0, 2);
+/// assert!(!alarm.update_power(2100.0)); // Not yet, one sample only
+/// assert!(alarm.update_power(2050.0));
+/// assert!(alarm.update_power(1900.0)); // Above clear_at
+/// assert!(alarm.update_power(1700.0)); // Not yet, one sample only
+/// assert!(!alarm.update_power(1750.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AlarmHysteresis {
+    /// Power in W at or above which the alarm goes on.
+    pub set_at: f32,
+    /// Power in W at or below which the alarm goes off.
+    pub clear_at: f32,
+    /// Number of consecutive samples required to change the state.
+    pub samples: u8,
+    on: bool,
+    count: u8,
+}
+
+impl AlarmHysteresis {
+    pub const fn new(set_at: f32, clear_at: f32, samples: u8) -> Self {
+        Self {
+            set_at,
+            clear_at,
+            samples,
+            on: false,
+            count: 0,
+        }
+    }
+
+    /// Returns the current state of the alarm.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Feeds the power in W, returning the new state of the alarm.
+    pub fn update_power(&mut self, power: f32) -> bool {
+        let on = if self.on {
+            power > self.clear_at
+        } else {
+            power >= self.set_at
+        };
+        self.update_alarm(on)
+    }
+
+    /// Feeds the alarm bit of the sensor, returning the new state of the alarm. Only the
+    /// number of consecutive samples applies, the power thresholds are ignored.
+    pub fn update_alarm(&mut self, alarm: bool) -> bool {
+        if alarm == self.on {
+            self.count = 0;
+        } else {
+            self.count = self.count.saturating_add(1);
+            if self.count >= self.samples {
+                self.on = alarm;
+                self.count = 0;
+            }
+        }
+
+        self.on
+    }
+
+    /// Feeds the power of the measurement, returning the new state of the alarm.
+    pub fn update(&mut self, m: &Measurement) -> bool {
+        self.update_power(m.power)
+    }
+}
+
+impl MeasurementSink for AlarmHysteresis {
+    fn push(&mut self, m: &Measurement) {
+        self.update(m);
+    }
+}
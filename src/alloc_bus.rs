@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+use hal::serial;
+
+use crate::{AddrResult, Error, Pzem, Timeout, ADDR_MAX, ADDR_MIN};
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Scans the whole range of legal slave addresses `[0x01..0xf7]`, like
+    /// [`scan_bus`](#method.scan_bus), but reporting every address which answered.
+    pub fn scan_bus_all<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Vec<AddrResult<WriteError, ReadError>> {
+        let mut results: Vec<_> = self
+            .scan(ADDR_MIN..=ADDR_MAX, &mut timeout)
+            .filter(|(_, res)| !matches!(res, Err(Error::TimedOut { .. })))
+            .collect();
+        self.mark_conflicts(&mut results, &mut timeout);
+        self.find_moved(&results);
+
+        results
+    }
+}
+
+/// Polls a set of slaves sharing a single serial bus, which may change at runtime.
+///
+/// Heap-allocated counterpart of [`Poller`](struct.Poller.html).
+///
+/// # Example
+/// ```ignore
+/// let mut poller = DynPoller::new(serial, &[]).unwrap();
+/// for (addr, _) in poller.scan(Some((&mut tim, TIMEOUT))) {
+///     poller.add(addr).unwrap();
+/// }
+/// ```
+pub struct DynPoller<Serial> {
+    pzem: Pzem<Serial>,
+    addrs: Vec<u8>,
+}
+
+impl<Serial, WriteError, ReadError> DynPoller<Serial>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Creates a new poller for the slaves at `addrs`, consuming the serial peripheral.
+    ///
+    /// Can return `Err(Error::IllegalAddress)` if any of the addresses is not in range of
+    /// legal addresses `[0x01..0xf7]`.
+    pub fn new(uart: Serial, addrs: &[u8]) -> Result<Self, Error<WriteError, ReadError>> {
+        let mut poller = Self {
+            pzem: Pzem::new(uart, None)?,
+            addrs: Vec::with_capacity(addrs.len()),
+        };
+        for &addr in addrs {
+            poller.add(addr)?;
+        }
+
+        Ok(poller)
+    }
+
+    /// Adds the slave at `addr` to the polled ones. Adding it again has no effect.
+    ///
+    /// Can return `Err(Error::IllegalAddress)` if the address is not in range of
+    /// legal addresses `[0x01..0xf7]`.
+    pub fn add(&mut self, addr: u8) -> Result<(), Error<WriteError, ReadError>> {
+        if !(ADDR_MIN..=ADDR_MAX).contains(&addr) {
+            return Err(Error::IllegalAddress);
+        }
+
+        if !self.addrs.contains(&addr) {
+            self.addrs.push(addr);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the slave at `addr`, returning `false` if it wasn't polled.
+    pub fn remove(&mut self, addr: u8) -> bool {
+        let len = self.addrs.len();
+        self.addrs.retain(|&a| a != addr);
+        self.addrs.len() != len
+    }
+
+    /// Reads the measurements off every slave in turn.
+    ///
+    /// A failing slave doesn't interrupt the cycle: each address is reported along with its own result.
+    pub fn poll<Tm: Timeout>(&mut self, mut timeout: Tm) -> Vec<AddrResult<WriteError, ReadError>> {
+        let pzem = &mut self.pzem;
+        self.addrs
+            .iter()
+            .map(|&addr| (addr, pzem.read_at(addr, &mut timeout)))
+            .collect()
+    }
+
+    /// Scans the bus for slaves, look [`Pzem::scan_bus_all`](struct.Pzem.html#method.scan_bus_all).
+    pub fn scan<Tm: Timeout>(&mut self, timeout: Tm) -> Vec<AddrResult<WriteError, ReadError>> {
+        self.pzem.scan_bus_all(timeout)
+    }
+
+    /// Returns the addresses of the polled slaves.
+    pub fn addrs(&self) -> &[u8] {
+        &self.addrs
+    }
+
+    /// Releases the underlying serial peripheral.
+    pub fn release(self) -> Serial {
+        self.pzem.release()
+    }
+}
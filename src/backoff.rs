@@ -0,0 +1,89 @@
+/// Backoff-and-retry policy for buses shared by several masters.
+///
+/// When two masters (e.g. a debugging tool and the firmware) talk on the same bus, their
+/// frames collide and the responses fail the CRC check. With a backoff policy set, the driver
+/// treats such failures as collisions: it waits for a number of *slots* and retries the exchange.
+/// One slot is one period of the timeout passed to the operation; during the wait the bus is drained.
+/// Without a timeout, the exchange is retried right away.
+///
+/// Collisions are counted in [`Stats::collisions`](struct.Stats.html#structfield.collisions).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    retries: u8,
+    slots: u8,
+    rng: Option<u16>,
+}
+
+impl Backoff {
+    /// No backoff: CRC failures are reported right away. This is the default.
+    pub const fn none() -> Self {
+        Self {
+            retries: 0,
+            slots: 0,
+            rng: None,
+        }
+    }
+
+    /// Retries up to `retries` times, always waiting `slots` slots in between.
+    ///
+    /// Choosing a different number of slots for each master sharing the bus prevents them
+    /// from colliding again.
+    pub const fn deterministic(retries: u8, slots: u8) -> Self {
+        Self {
+            retries,
+            slots,
+            rng: None,
+        }
+    }
+
+    /// Retries up to `retries` times, waiting a pseudo-random number of slots in between.
+    ///
+    /// The slot window doubles on every retry (binary exponential backoff) up to `max_slots`.
+    /// The pseudo-random sequence is initialized with `seed`, which should differ between
+    /// devices, e.g. derived from a unique chip ID.
+    pub const fn random(retries: u8, max_slots: u8, seed: u16) -> Self {
+        Self {
+            retries,
+            slots: max_slots,
+            rng: Some(if seed == 0 { 0xace1 } else { seed }),
+        }
+    }
+
+    /// Returns the maximum number of retries.
+    pub const fn max_retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// Returns the maximum number of slots waited before a retry.
+    pub const fn max_slots(&self) -> u8 {
+        self.slots
+    }
+
+    /// Returns the number of slots to wait before the retry number `attempt` (0-based),
+    /// or `None` if no more retries are allowed.
+    pub(crate) fn delay(&mut self, attempt: u8) -> Option<u8> {
+        if attempt >= self.retries {
+            return None;
+        }
+
+        match self.rng.as_mut() {
+            None => Some(self.slots),
+            Some(state) => {
+                // 16-bit xorshift
+                *state ^= *state << 7;
+                *state ^= *state >> 9;
+                *state ^= *state << 8;
+
+                let window = (2u16 << attempt.min(7)) - 1;
+                let window = window.min(self.slots as u16);
+                Some((*state % (window + 1)) as u8)
+            }
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::none()
+    }
+}
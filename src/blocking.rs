@@ -0,0 +1,100 @@
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use hal::blocking::delay::DelayUs;
+
+#[cfg(not(feature = "no-float"))]
+use crate::Measurement;
+use crate::{AsyncPort, Error, RawMeasurement, WebSerial};
+
+/// Runs the [`WebSerial`](struct.WebSerial.html) driver from the blocking code, so that the
+/// call sites of a code base moving to async may stay as they are meanwhile.
+///
+/// Each call polls the future of the driver until it completes, waiting `interval` µs with
+/// the delay between the polls. No waker is registered: the port has to make progress when
+/// polled, e.g. by checking the status of the UART, rather than wait for an interrupt.
+///
+/// # Example
+///
+/// ```ignore
+/// let pzem = WebSerial::new(AsyncUart::new(uart), None).unwrap();
+/// let mut pzem = BlockingAdapter::new(pzem, delay);
+/// let m = pzem.read()?;
+/// ```
+pub struct BlockingAdapter<P, D> {
+    driver: WebSerial<P>,
+    delay: D,
+    interval: u32,
+}
+
+impl<P: AsyncPort, D: DelayUs<u32>> BlockingAdapter<P, D> {
+    /// Wraps the driver, polling it every 100 µs.
+    pub fn new(driver: WebSerial<P>, delay: D) -> Self {
+        Self {
+            driver,
+            delay,
+            interval: 100,
+        }
+    }
+
+    /// Sets the interval between the polls in µs, e.g. about a character time of the line
+    /// (1 ms at 9600 baud) to spare the CPU.
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Reads the measurements off the sensor, look [`WebSerial::read`](struct.WebSerial.html#method.read).
+    #[cfg(not(feature = "no-float"))]
+    pub fn read(&mut self) -> Result<Measurement, Error<P::Error, P::Error>> {
+        block_on(&mut self.delay, self.interval, self.driver.read())
+    }
+
+    /// Reads the raw measurement registers off the sensor.
+    pub fn read_raw(&mut self) -> Result<RawMeasurement, Error<P::Error, P::Error>> {
+        block_on(&mut self.delay, self.interval, self.driver.read_raw())
+    }
+
+    /// Reads the power alarm threshold of the sensor in W.
+    pub fn get_threshold(&mut self) -> Result<u16, Error<P::Error, P::Error>> {
+        block_on(&mut self.delay, self.interval, self.driver.get_threshold())
+    }
+
+    /// Reads the Modbus-RTU address of the sensor.
+    pub fn get_addr(&mut self) -> Result<u16, Error<P::Error, P::Error>> {
+        block_on(&mut self.delay, self.interval, self.driver.get_addr())
+    }
+
+    /// Returns a mutable reference to the driver, e.g. to await it from the async code
+    /// already migrated.
+    pub fn driver_mut(&mut self) -> &mut WebSerial<P> {
+        &mut self.driver
+    }
+
+    /// Releases the driver and the delay.
+    pub fn release(self) -> (WebSerial<P>, D) {
+        (self.driver, self.delay)
+    }
+}
+
+fn block_on<F: Future, D: DelayUs<u32>>(delay: &mut D, interval: u32, f: F) -> F::Output {
+    let mut f = pin!(f);
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match f.as_mut().poll(&mut cx) {
+            Poll::Ready(out) => return out,
+            Poll::Pending => delay.delay_us(interval),
+        }
+    }
+}
+
+// Waker doing nothing, as the future is polled in a loop regardless.
+fn noop_waker() -> Waker {
+    const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+
+    // The functions of the vtable ignore the data pointer, hence are trivially sound.
+    unsafe { Waker::from_raw(RAW) }
+}
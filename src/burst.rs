@@ -0,0 +1,125 @@
+use hal::serial;
+use hal::timer::CountDown;
+
+use crate::power::sqrt;
+use crate::{Error, Measurement, Pzem, Timeout};
+
+/// Mean and standard deviation of a field over a burst of samples.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FieldStats {
+    pub mean: f32,
+    pub stddev: f32,
+}
+
+/// Statistics of a burst of samples taken by [`Pzem::read_burst`](struct.Pzem.html#method.read_burst).
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BurstStats {
+    /// Number of successful samples the statistics are computed from.
+    pub samples: u32,
+    /// Number of failed reads, which are skipped.
+    pub failed: u32,
+    pub voltage: FieldStats,
+    pub current: FieldStats,
+    pub power: FieldStats,
+    pub frequency: FieldStats,
+    pub pf: FieldStats,
+}
+
+// Running mean and variance, by Welford's algorithm.
+#[derive(Default, Copy, Clone)]
+struct Running {
+    mean: f32,
+    m2: f32,
+}
+
+impl Running {
+    fn push(&mut self, n: u32, x: f32) {
+        let delta = x - self.mean;
+        self.mean += delta / n as f32;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn stats(&self, n: u32) -> FieldStats {
+        FieldStats {
+            mean: self.mean,
+            stddev: match n {
+                0 | 1 => 0.0,
+                n => sqrt(self.m2 / (n - 1) as f32),
+            },
+        }
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Takes `n` consecutive samples, started every `interval`, and computes the mean and the
+    /// standard deviation of each field.
+    ///
+    /// Useful to characterize the noise of the sensor when commissioning, or when comparing
+    /// it against a reference meter. Note that the sensor refreshes the measurements about
+    /// once per second, so shorter intervals only sample the same values again.
+    ///
+    /// The failed reads are counted and skipped; the error is only returned if all of them fail.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let burst = pzem.read_burst(30, (&mut interval, 1.secs()), Some((&mut tim, TIMEOUT)))?;
+    /// println!("{:.2} ± {:.2} V", burst.voltage.mean, burst.voltage.stddev);
+    /// ```
+    pub fn read_burst<T: CountDown, Tm: Timeout>(
+        &mut self,
+        n: u32,
+        interval: (&mut T, T::Time),
+        mut timeout: Tm,
+    ) -> Result<BurstStats, Error<WriteError, ReadError>>
+    where
+        T::Time: Clone,
+    {
+        let (timer, period) = interval;
+        let mut stats = BurstStats::default();
+        let mut fields = [Running::default(); 5];
+        let mut last_err = None;
+
+        for i in 0..n {
+            timer.start(period.clone());
+
+            let mut m = Measurement::default();
+            match self.read(&mut m, &mut timeout) {
+                Ok(()) => {
+                    stats.samples += 1;
+                    let values = [m.voltage, m.current, m.power, m.frequency, m.pf];
+                    for (field, x) in fields.iter_mut().zip(values) {
+                        field.push(stats.samples, x);
+                    }
+                }
+                Err(e) => {
+                    stats.failed += 1;
+                    last_err = Some(e);
+                }
+            }
+
+            if i + 1 < n {
+                let _ = block!(timer.wait());
+            }
+        }
+
+        if stats.samples == 0 {
+            if let Some(e) = last_err {
+                return Err(e);
+            }
+        }
+
+        let [voltage, current, power, frequency, pf] = fields.map(|f| f.stats(stats.samples));
+        Ok(BurstStats {
+            voltage,
+            current,
+            power,
+            frequency,
+            pf,
+            ..stats
+        })
+    }
+}
@@ -0,0 +1,364 @@
+use core::ops::RangeInclusive;
+
+use hal::serial;
+
+use crate::{Error, Measurement, Pzem, Timeout, ADDR_DEFAULT, ADDR_MAX, ADDR_MIN};
+
+/// Per-address outcome of a bus operation: the slave address along with its own result.
+pub type AddrResult<WriteError, ReadError> =
+    (u8, Result<Measurement, Error<WriteError, ReadError>>);
+
+/// Results of a bus operation, one entry per slave address.
+pub type BusResults<WriteError, ReadError, const N: usize> =
+    heapless::Vec<AddrResult<WriteError, ReadError>, N>;
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Reads the measurements off the slave at `addr` instead of the configured one.
+    pub(crate) fn read_at<Tm: Timeout>(
+        &mut self,
+        addr: u8,
+        timeout: Tm,
+    ) -> Result<Measurement, Error<WriteError, ReadError>> {
+        let mut m = Measurement::default();
+        let old = core::mem::replace(&mut self.addr, addr);
+        let res = self.read(&mut m, timeout);
+        self.addr = old;
+
+        res.map(|()| m)
+    }
+
+    // Probes the slave at `addr` for a scan: the silent addresses are the rule rather than
+    // faults, so the errors are neither recorded into the event sink nor the debug state.
+    fn probe_at<Tm: Timeout>(
+        &mut self,
+        addr: u8,
+        timeout: Tm,
+    ) -> Result<Measurement, Error<WriteError, ReadError>> {
+        self.quiet = true;
+        let res = self.read_at(addr, timeout);
+        self.quiet = false;
+
+        res
+    }
+
+    // Sends the read request to the slave at `addr`, look `finish_at`.
+    fn start_at<Tm: Timeout>(
+        &mut self,
+        addr: u8,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let old = core::mem::replace(&mut self.addr, addr);
+        let res = self.start_read(timeout);
+        self.addr = old;
+
+        res
+    }
+
+    // Receives the measurements requested by `start_at`.
+    fn finish_at<Tm: Timeout>(
+        &mut self,
+        addr: u8,
+        timeout: Tm,
+    ) -> Result<Measurement, Error<WriteError, ReadError>> {
+        let old = core::mem::replace(&mut self.addr, addr);
+        let res = self.finish_read(timeout);
+        self.addr = old;
+
+        res.map(|raw| self.convert(&raw))
+    }
+
+    /// Scans the whole range of legal slave addresses `[0x01..0xf7]`.
+    ///
+    /// Every address which answered in any way is reported along with its own result:
+    /// a slave responding with a corrupted frame shows up with e.g. `Err(Error::CrcMismatch)`
+    /// rather than aborting the scan. Silent addresses are skipped. At most `N` addresses are
+    /// reported; the scan stops once the results are full.
+    ///
+    /// An address whose responses keep failing the CRC check, while other addresses answer
+    /// cleanly, is reported with `Err(Error::ProbableAddressConflict)`.
+    ///
+    /// The timeout is reused for every probed address, hence a short one is recommended.
+    /// The errors of the probes aren't recorded into the attached [`EventLog`](struct.EventLog.html)
+    /// nor the [`DebugState`](struct.DebugState.html), which keep the faults of the regular reads.
+    ///
+    /// Finding the sensor on another address is reported to the hook attached by
+    /// [`with_addr_hook`](#method.with_addr_hook).
+    pub fn scan_bus<Tm: Timeout, const N: usize>(
+        &mut self,
+        timeout: Tm,
+    ) -> BusResults<WriteError, ReadError, N> {
+        self.scan_bus_range(ADDR_MIN..=ADDR_MAX, timeout)
+    }
+
+    /// Scans the slave addresses in `addrs` like [`scan_bus`](#method.scan_bus), e.g. the few
+    /// addresses assigned to the sensors of an installation. Addresses outside of the legal
+    /// range `[0x01..0xf7]` are skipped.
+    pub fn scan_bus_range<Tm: Timeout, const N: usize>(
+        &mut self,
+        addrs: RangeInclusive<u8>,
+        mut timeout: Tm,
+    ) -> BusResults<WriteError, ReadError, N> {
+        let mut results = heapless::Vec::new();
+        for (addr, res) in self.scan(addrs, &mut timeout).stop_after(N) {
+            if !matches!(res, Err(Error::TimedOut { .. })) {
+                let _ = results.push((addr, res));
+            }
+        }
+
+        self.mark_conflicts(&mut results, &mut timeout);
+        self.find_moved(&results);
+
+        results
+    }
+
+    /// Scans the slave addresses in `addrs` one at a time, look [`ScanIter`](struct.ScanIter.html).
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut scan = pzem.scan(0x01..=0xf7, Some((&mut tim, 50.millis())));
+    /// while let Some((addr, res)) = scan.next() {
+    ///     progress.set(addr);
+    ///     if let Ok(m) = res {
+    ///         hprintln!("{:#04x}: {:.1} W", addr, m.power).unwrap();
+    ///     }
+    ///     if button.is_pressed() {
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    pub fn scan<Tm: Timeout>(
+        &mut self,
+        addrs: RangeInclusive<u8>,
+        timeout: Tm,
+    ) -> ScanIter<'_, Serial, State, Tm> {
+        let (start, end) = addrs.into_inner();
+        ScanIter {
+            pzem: self,
+            addrs: start.max(ADDR_MIN)..=end.min(ADDR_MAX),
+            timeout,
+            found: 0,
+            limit: usize::MAX,
+        }
+    }
+
+    // Reports the sensor as moved if its address is silent while a single other slave answers.
+    pub(crate) fn find_moved(&self, results: &[AddrResult<WriteError, ReadError>]) {
+        match results {
+            [(addr, _)] if self.addr != ADDR_DEFAULT && *addr != self.addr => {
+                self.addr_changed(self.addr, *addr)
+            }
+            _ => {}
+        }
+    }
+
+    // Reports the addresses garbled while others answer cleanly as the probable conflicts,
+    // as a line problem would garble all of them.
+    pub(crate) fn mark_conflicts<Tm: Timeout>(
+        &mut self,
+        results: &mut [AddrResult<WriteError, ReadError>],
+        mut timeout: Tm,
+    ) {
+        if !results.iter().any(|(_, res)| res.is_ok()) {
+            return;
+        }
+
+        for (addr, res) in results.iter_mut() {
+            if matches!(res, Err(Error::CrcMismatch))
+                && matches!(self.conflict_at(*addr, &mut timeout), Ok(true))
+            {
+                *res = Err(Error::ProbableAddressConflict);
+            }
+        }
+    }
+}
+
+/// Scan of a range of slave addresses, probing a single address per call to `next`, so
+/// that the progress can be shown and the scan abandoned at any point.
+///
+/// Yields every address probed, the silent ones with `Err(Error::TimedOut { .. })`; the errors
+/// of the probes aren't recorded, as by `scan_bus`. Unlike
+/// [`Pzem::scan_bus`](struct.Pzem.html#method.scan_bus), neither marks the probable address
+/// conflicts nor reports the moved sensor.
+pub struct ScanIter<'a, Serial, State, Tm> {
+    pzem: &'a mut Pzem<Serial, State>,
+    addrs: RangeInclusive<u8>,
+    timeout: Tm,
+    found: usize,
+    limit: usize,
+}
+
+impl<Serial, State, Tm> ScanIter<'_, Serial, State, Tm> {
+    /// Ends the scan once `n` addresses have answered.
+    pub fn stop_after(mut self, n: usize) -> Self {
+        self.limit = n;
+        self
+    }
+
+    /// Returns the number of addresses which answered so far.
+    pub fn found(&self) -> usize {
+        self.found
+    }
+}
+
+impl<Serial, State, Tm, WriteError, ReadError> Iterator for ScanIter<'_, Serial, State, Tm>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+    Tm: Timeout,
+{
+    type Item = AddrResult<WriteError, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.found >= self.limit {
+            return None;
+        }
+
+        let addr = self.addrs.next()?;
+        let res = self.pzem.probe_at(addr, &mut self.timeout);
+        if !matches!(res, Err(Error::TimedOut { .. })) {
+            self.found += 1;
+        }
+
+        Some((addr, res))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.found >= self.limit {
+            (0, Some(0))
+        } else {
+            (0, self.addrs.size_hint().1)
+        }
+    }
+}
+
+/// Polls a fixed set of slaves sharing a single serial bus.
+///
+/// Like the driver it wraps, `Send` but not `Sync`: it belongs to the single task polling the bus.
+///
+/// # Example
+/// ```ignore
+/// let mut poller = Poller::<_, 4>::new(serial, &[0x01, 0x02, 0x03]).unwrap();
+/// for (addr, res) in poller.poll(Some((&mut tim, TIMEOUT))) {
+///     match res {
+///         Ok(m) => hprintln!("{:#04x}: {:.1} W", addr, m.power).unwrap(),
+///         Err(e) => hprintln!("{:#04x}: {}", addr, e).unwrap(),
+///     }
+/// }
+/// ```
+pub struct Poller<Serial, const N: usize> {
+    pzem: Pzem<Serial>,
+    addrs: heapless::Vec<u8, N>,
+}
+
+impl<Serial, WriteError, ReadError, const N: usize> Poller<Serial, N>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Creates a new poller for the slaves at `addrs`, consuming the serial peripheral.
+    ///
+    /// Can return `Err(Error::IllegalAddress)` if any of the addresses is not in range of
+    /// legal addresses `[0x01..0xf7]`, or `Err(Error::TooManyAddresses)` if there are more
+    /// than `N` of them.
+    pub fn new(uart: Serial, addrs: &[u8]) -> Result<Self, Error<WriteError, ReadError>> {
+        if addrs.iter().any(|a| !(ADDR_MIN..=ADDR_MAX).contains(a)) {
+            return Err(Error::IllegalAddress);
+        }
+
+        let addrs = heapless::Vec::from_slice(addrs).map_err(|_| Error::TooManyAddresses)?;
+        let pzem = Pzem::new(uart, None)?;
+
+        Ok(Self { pzem, addrs })
+    }
+
+    /// Reads the measurements off every slave in turn.
+    ///
+    /// A failing slave doesn't interrupt the cycle: each address is reported along with its own result.
+    pub fn poll<Tm: Timeout>(&mut self, mut timeout: Tm) -> BusResults<WriteError, ReadError, N> {
+        let mut results = heapless::Vec::new();
+        for &addr in self.addrs.iter() {
+            let res = self.pzem.read_at(addr, &mut timeout);
+            let _ = results.push((addr, res));
+        }
+
+        results
+    }
+
+    /// Returns the addresses of the polled slaves.
+    pub fn addrs(&self) -> &[u8] {
+        &self.addrs
+    }
+
+    /// Releases the underlying serial peripheral.
+    pub fn release(self) -> Serial {
+        self.pzem.release()
+    }
+}
+
+/// Polls the slaves of two serial buses at once, alternating the transactions between them.
+///
+/// The request to the next slave of one bus is sent while the response of the other bus is
+/// still on the way, so that a poll cycle takes about as long as the cycle of the longer
+/// [`Poller`](struct.Poller.html) alone, instead of the sum of both. Hence the receivers must
+/// buffer the incoming bytes while the other bus is being served, e.g. by an interrupt-driven
+/// [`RingBufferRx`](struct.RingBufferRx.html): a bare hardware receiver would overrun.
+///
+/// Collisions aren't retried, as the other bus can't wait for the backoff.
+///
+/// # Example
+/// ```ignore
+/// let a = Poller::<_, 4>::new(uart1, &[0x01, 0x02]).unwrap();
+/// let b = Poller::<_, 4>::new(uart2, &[0x01, 0x02, 0x03]).unwrap();
+/// let mut scheduler = InterleavedScheduler::new(a, b);
+/// let (a, b) = scheduler.poll(Some((&mut tim, TIMEOUT)));
+/// ```
+pub struct InterleavedScheduler<A, B, const N: usize> {
+    a: Poller<A, N>,
+    b: Poller<B, N>,
+}
+
+impl<A, B, WA, RA, WB, RB, const N: usize> InterleavedScheduler<A, B, N>
+where
+    A: serial::Write<u8, Error = WA> + serial::Read<u8, Error = RA>,
+    B: serial::Write<u8, Error = WB> + serial::Read<u8, Error = RB>,
+{
+    pub fn new(a: Poller<A, N>, b: Poller<B, N>) -> Self {
+        Self { a, b }
+    }
+
+    /// Reads the measurements off every slave of both buses.
+    ///
+    /// Each address is reported along with its own result, separately for each bus.
+    #[allow(clippy::type_complexity)]
+    pub fn poll<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> (BusResults<WA, RA, N>, BusResults<WB, RB, N>) {
+        let (mut ra, mut rb) = (heapless::Vec::new(), heapless::Vec::new());
+        let (pa, pb) = (&mut self.a.pzem, &mut self.b.pzem);
+
+        for i in 0..self.a.addrs.len().max(self.b.addrs.len()) {
+            let (a, b) = (self.a.addrs.get(i).copied(), self.b.addrs.get(i).copied());
+
+            let sa = a.map(|addr| pa.start_at(addr, &mut timeout));
+            let sb = b.map(|addr| pb.start_at(addr, &mut timeout));
+
+            if let (Some(addr), Some(res)) = (a, sa) {
+                let res = res.and_then(|()| pa.finish_at(addr, &mut timeout));
+                let _ = ra.push((addr, res));
+            }
+            if let (Some(addr), Some(res)) = (b, sb) {
+                let res = res.and_then(|()| pb.finish_at(addr, &mut timeout));
+                let _ = rb.push((addr, res));
+            }
+        }
+
+        (ra, rb)
+    }
+
+    /// Releases both pollers.
+    pub fn release(self) -> (Poller<A, N>, Poller<B, N>) {
+        (self.a, self.b)
+    }
+}
@@ -0,0 +1,87 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use hal::timer::CountDown;
+
+/// Flag cancelling the transaction in flight, attached by [`Pzem::with_cancel`](struct.Pzem.html#method.with_cancel).
+///
+/// Cancelling from an interrupt handler or another thread makes the driver abandon the
+/// waiting at once, discard the bytes received so far and fail the operation with
+/// `Err(Error::Cancelled)`, leaving it ready for the next one. The flag is cleared at the
+/// start of every transaction, hence cancelling while idle has no effect.
+///
+/// # Example
+///
+/// ```ignore
+/// static CANCEL: Cancel = Cancel::new();
+///
+/// #[interrupt]
+/// fn EXTI0() {
+///     // Abort button
+///     CANCEL.cancel();
+/// }
+///
+/// let mut pzem = Pzem::new(serial, None).unwrap().with_cancel(&CANCEL);
+/// match pzem.read(&mut m, NoTimeout) {
+///     Err(Error::Cancelled) => display.show("Aborted"),
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Cancel {
+    flag: AtomicBool,
+}
+
+impl Cancel {
+    pub const fn new() -> Self {
+        Self {
+            flag: AtomicBool::new(false),
+        }
+    }
+
+    /// Cancels the transaction in flight.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if the transaction in flight has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.flag.store(false, Ordering::Release);
+    }
+}
+
+// Timer expiring early once the transaction is cancelled, or never without a timer.
+pub(crate) struct Cancellable<'a, T> {
+    timer: Option<&'a mut T>,
+    cancel: Option<&'a Cancel>,
+}
+
+impl<'a, T> Cancellable<'a, T> {
+    pub(crate) fn new(timer: Option<&'a mut T>, cancel: Option<&'a Cancel>) -> Self {
+        Self { timer, cancel }
+    }
+}
+
+impl<T: CountDown> CountDown for Cancellable<'_, T> {
+    type Time = T::Time;
+
+    fn start<U: Into<T::Time>>(&mut self, count: U) {
+        if let Some(timer) = self.timer.as_deref_mut() {
+            timer.start(count);
+        }
+    }
+
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        if self.cancel.is_some_and(Cancel::is_cancelled) {
+            return Ok(());
+        }
+
+        match self.timer.as_deref_mut() {
+            Some(timer) => timer.wait(),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{HOLDING_REG_MAX, READ_REG_MAX, REG_COUNT};
+
+/// Provider of the 16-bit MODBUS cyclic redundancy check.
+///
+/// Implementations must be `Sync`, so that the driver may be moved across tasks.
+///
+/// The driver uses the software implementation [`SoftwareCrc`](struct.SoftwareCrc.html) by default.
+/// Targets with a hardware CRC unit (e.g. the STM32 CRC peripheral configured for the
+/// MODBUS polynomial `0x8005`, reflected, with initial value `0xffff`) may implement this trait
+/// and plug it in with [`Pzem::with_crc`](struct.Pzem.html#method.with_crc).
+///
+/// # Example
+/// ```ignore
+/// struct HwCrc;
+///
+/// impl CrcProvider for HwCrc {
+///     fn crc(&self, bytes: &[u8]) -> u16 {
+///         cortex_m::interrupt::free(|cs| CRC.borrow(cs).borrow_mut().as_mut().unwrap().calculate(bytes))
+///     }
+/// }
+///
+/// static HW_CRC: HwCrc = HwCrc;
+/// let pzem = Pzem::new(serial, None).unwrap().with_crc(&HW_CRC);
+/// ```
+pub trait CrcProvider: Sync {
+    /// Computes the MODBUS CRC of `bytes`.
+    fn crc(&self, bytes: &[u8]) -> u16;
+}
+
+/// Software table-driven implementation of the MODBUS CRC.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SoftwareCrc;
+
+impl CrcProvider for SoftwareCrc {
+    fn crc(&self, bytes: &[u8]) -> u16 {
+        crc16::State::<crc16::MODBUS>::calculate(bytes)
+    }
+}
+
+/// Computes the MODBUS CRC of `bytes` bit by bit, in const contexts.
+///
+/// Matches [`SoftwareCrc`](struct.SoftwareCrc.html), but is considerably slower at run time;
+/// meant for the golden frames of the tests, CRC'd at compile time.
+///
+/// ```
+/// use pzem004t::{modbus_crc, CrcProvider, SoftwareCrc};
+///
+/// // Request reading the measurements off the slave 0x01.
+/// const CRC: u16 = modbus_crc(&[0x01, 0x04, 0x00, 0x00, 0x00, 0x0a]);
+/// const REQUEST: [u8; 8] = [0x01, 0x04, 0x00, 0x00, 0x00, 0x0a, CRC as u8, (CRC >> 8) as u8];
+///
+/// assert_eq!(REQUEST[6..], [0x70, 0x0d]);
+/// assert_eq!(CRC, SoftwareCrc.crc(&REQUEST[..6]));
+/// ```
+pub const fn modbus_crc(bytes: &[u8]) -> u16 {
+    let mut crc = 0xffff;
+    let mut i = 0;
+    while i < bytes.len() {
+        crc ^= bytes[i] as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        i += 1;
+    }
+
+    crc
+}
+
+// Frame lengths, including the 2 CRC bytes.
+pub(crate) const REQ_LEN: usize = 8; // Addr + function + register/param (2) + count/value (2)
+pub(crate) const RESET_LEN: usize = 4; // Addr + function
+pub(crate) const READ_RESP_LEN: usize = 3 + 2 * REG_COUNT as usize + 2; // Addr + function + byte count + registers
+pub(crate) const READ_RESP_MAX: usize = 3 + 2 * READ_REG_MAX + 2; // Likewise, with the registers of the clones
+pub(crate) const PARAM_RESP_LEN: usize = 7; // Addr + function + byte count + 1 register
+pub(crate) const WRITE_RESP_LEN: usize = REQ_LEN; // Echo of the request
+pub(crate) const WRITE_MULTI_REQ_LEN: usize = 11; // Addr + function + register (2) + count (2) + byte count + value (2)
+pub(crate) const WRITE_MULTI_RESP_LEN: usize = 8; // Addr + function + register (2) + count (2)
+pub(crate) const EXCEPTION_LEN: usize = 5; // Addr + function | 0x80 + exception code
+pub(crate) const HOLDING_RESP_MAX: usize = 3 + 2 * HOLDING_REG_MAX + 2; // Addr + function + byte count + registers
+
+// Buffer receiving the read responses, its length checked at compile time by evaluating
+// `FITS`.
+pub(crate) struct ReadBuf<const N: usize>;
+
+impl<const N: usize> ReadBuf<N> {
+    pub(crate) const FITS: () = assert!(
+        N >= READ_RESP_LEN,
+        "the frame must hold at least READ_FRAME_LEN bytes"
+    );
+}
+
+// Number of the holding registers read at once, checked at compile time by evaluating
+// `VALID`.
+pub(crate) struct HoldingCount<const N: usize>;
+
+impl<const N: usize> HoldingCount<N> {
+    pub(crate) const VALID: () = assert!(
+        N > 0 && N <= HOLDING_REG_MAX,
+        "between 1 and HOLDING_REG_MAX registers may be read"
+    );
+}
+
+// Consistency of the frame layout, checked at compile time.
+const _: () = {
+    // The registers are decoded from fixed-size arrays.
+    assert!(REG_COUNT == 10);
+    assert!(READ_RESP_LEN == 25);
+    assert!(READ_REG_MAX >= REG_COUNT as usize && READ_RESP_MAX == 29);
+
+    // The byte count fields are a single byte, and so are the counts of `read_blocking`.
+    assert!(2 * REG_COUNT as usize <= u8::MAX as usize);
+    assert!(HOLDING_RESP_MAX <= u8::MAX as usize);
+
+    // The header is read before telling the exception responses from the regular ones.
+    assert!(EXCEPTION_LEN == 2 + 1 + 2);
+    assert!(RESET_LEN > 2 && PARAM_RESP_LEN > 2 && WRITE_MULTI_RESP_LEN > 2);
+
+    // Single register responses and requests.
+    assert!(PARAM_RESP_LEN == 3 + 2 + 2);
+    assert!(REQ_LEN == 2 + 2 + 2 + 2);
+    assert!(WRITE_MULTI_REQ_LEN == WRITE_MULTI_RESP_LEN + 1 + 2);
+
+    // The parameter writes are answered with the echo of the request.
+    assert!(WRITE_RESP_LEN == REQ_LEN);
+
+    // The check value of the MODBUS CRC.
+    assert!(modbus_crc(b"123456789") == 0x4b37);
+};
+
+// 16-bit cyclic redundancy check (CRC), transmitted low byte first.
+pub(crate) fn crc_write<const N: usize>(crc: &dyn CrcProvider, buf: &mut [u8; N]) {
+    let crc = crc.crc(&buf[..N - 2]);
+
+    buf[N - 2] = (crc >> 0) as u8;
+    buf[N - 1] = (crc >> 8) as u8;
+}
+
+pub(crate) fn crc_check<const N: usize>(crc: &dyn CrcProvider, buf: &[u8; N]) -> bool {
+    let crc = crc.crc(&buf[..N - 2]);
+
+    (crc >> 0) as u8 == buf[N - 2] && (crc >> 8) as u8 == buf[N - 1]
+}
+
+// Likewise, for the frames sized by their byte count; too short to hold a CRC, `buf` fails.
+pub(crate) fn crc_check_slice(crc: &dyn CrcProvider, buf: &[u8]) -> bool {
+    match buf {
+        [data @ .., lo, hi] => {
+            let crc = crc.crc(data);
+            (crc >> 0) as u8 == *lo && (crc >> 8) as u8 == *hi
+        }
+        _ => false,
+    }
+}
+
+// Extracts the measurement registers from the response: two bytes each, high byte first.
+// The registers past the first 10, sent by some clones, are ignored.
+pub(crate) fn registers(buf: &[u8]) -> [u16; REG_COUNT as usize] {
+    let mut regs = [0u16; REG_COUNT as usize];
+    for (i, reg) in regs.iter_mut().enumerate() {
+        *reg = u16::from_be_bytes([buf[3 + 2 * i], buf[4 + 2 * i]]);
+    }
+
+    regs
+}
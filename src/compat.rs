@@ -0,0 +1,119 @@
+use hal::serial;
+
+/// Byte-oriented serial port with slice-based reads and writes, as exposed by most HALs
+/// not implementing the embedded-hal 0.2 serial traits.
+///
+/// Implement it for the UART driver of the HAL and wrap the driver in [`Compat`](struct.Compat.html)
+/// to use it with [`Pzem`](struct.Pzem.html).
+///
+/// # Examples
+///
+/// esp-idf-hal UART driver:
+///
+/// ```ignore
+/// struct EspUart<'d>(esp_idf_hal::uart::UartDriver<'d>);
+///
+/// impl ByteStream for EspUart<'_> {
+///     type Error = esp_idf_sys::EspError;
+///     fn read_available(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+///         self.0.read(buf, esp_idf_hal::delay::NON_BLOCK)
+///     }
+///     fn write_bytes(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+///         self.0.write(buf)
+///     }
+///     fn flush_bytes(&mut self) -> Result<(), Self::Error> {
+///         self.0.wait_tx_done(esp_idf_hal::delay::BLOCK)
+///     }
+/// }
+///
+/// let pzem = Pzem::new(Compat::new(EspUart(uart)), None).unwrap();
+/// ```
+///
+/// embassy blocking UART, with the receiver buffered by a ring buffer:
+///
+/// ```ignore
+/// struct EmbassyUart<'d> {
+///     tx: embassy_stm32::usart::UartTx<'d, Blocking>,
+///     rx: embassy_stm32::usart::RingBufferedUartRx<'d>,
+/// }
+///
+/// impl ByteStream for EmbassyUart<'_> {
+///     type Error = embassy_stm32::usart::Error;
+///     fn read_available(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+///         match embedded_io::ReadReady::read_ready(&mut self.rx)? {
+///             true => embedded_io::Read::read(&mut self.rx, buf),
+///             false => Ok(0),
+///         }
+///     }
+///     fn write_bytes(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+///         self.tx.blocking_write(buf).map(|()| buf.len())
+///     }
+///     fn flush_bytes(&mut self) -> Result<(), Self::Error> {
+///         self.tx.blocking_flush()
+///     }
+/// }
+/// ```
+pub trait ByteStream {
+    type Error;
+
+    /// Reads the bytes already received into `buf` without blocking,
+    /// returning their number, which is zero if none are available.
+    fn read_available(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Writes the bytes from `buf`, returning the number of bytes accepted.
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Blocks until the written bytes have been transmitted.
+    fn flush_bytes(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Adapter implementing the embedded-hal serial traits over a [`ByteStream`](trait.ByteStream.html).
+#[derive(Debug)]
+pub struct Compat<T> {
+    inner: T,
+}
+
+impl<T> Compat<T> {
+    /// Wraps the byte stream.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Releases the underlying stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ByteStream> serial::Read<u8> for Compat<T> {
+    type Error = T::Error;
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let mut b = [0u8];
+        match self.inner.read_available(&mut b) {
+            Ok(0) => Err(nb::Error::WouldBlock),
+            Ok(_) => Ok(b[0]),
+            Err(e) => Err(nb::Error::Other(e)),
+        }
+    }
+}
+
+impl<T: ByteStream> serial::Write<u8> for Compat<T> {
+    type Error = T::Error;
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match self.inner.write_bytes(&[word]) {
+            Ok(0) => Err(nb::Error::WouldBlock),
+            Ok(_) => Ok(()),
+            Err(e) => Err(nb::Error::Other(e)),
+        }
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.inner.flush_bytes().map_err(nb::Error::Other)
+    }
+}
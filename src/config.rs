@@ -0,0 +1,122 @@
+use hal::serial;
+
+use crate::{Error, Pzem, Timeout, Verified};
+
+/// Parameters of the sensor to be changed at once by [`Pzem::apply`](struct.Pzem.html#method.apply).
+///
+/// Parameters set to `None` are left untouched.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub addr: Option<u8>,
+    pub threshold: Option<u16>,
+}
+
+/// Step of [`Pzem::apply`](struct.Pzem.html#method.apply).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ApplyStep {
+    /// Reading the current threshold, to be restored on failure.
+    ReadCurrent,
+    /// Writing the new threshold.
+    Threshold,
+    /// Reading back the new threshold.
+    VerifyThreshold,
+    /// Writing the new address.
+    Addr,
+    /// Reading back the new address.
+    VerifyAddr,
+}
+
+/// Error of [`Pzem::apply`](struct.Pzem.html#method.apply), reporting which step failed.
+#[derive(Debug, Clone)]
+pub struct ApplyError<WriteError, ReadError> {
+    /// The step which failed.
+    pub step: ApplyStep,
+    /// The error of the failed step.
+    pub error: Error<WriteError, ReadError>,
+    /// Whether the previously written threshold has been successfully restored.
+    pub rolled_back: bool,
+}
+
+impl<Serial, WriteError, ReadError> Pzem<Serial, Verified>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Changes the threshold and the address of the sensor in a single verified sequence.
+    ///
+    /// The threshold is written first and read back, then the address is written and read back
+    /// on the new address. If any step fails after the threshold has been changed but before
+    /// the address has, the previous threshold is restored. The error reports the failed step
+    /// and whether the rollback succeeded.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let config = Config { addr: Some(0x10), threshold: Some(2300) };
+    /// if let Err(e) = pzem.apply(config, Some((&mut tim, TIMEOUT))) {
+    ///     hprintln!("Commissioning failed at {:?}: {}", e.step, e.error).unwrap();
+    /// }
+    /// ```
+    pub fn apply<Tm: Timeout>(
+        &mut self,
+        config: Config,
+        mut timeout: Tm,
+    ) -> Result<(), ApplyError<WriteError, ReadError>> {
+        let fail = |step, error| ApplyError {
+            step,
+            error,
+            rolled_back: false,
+        };
+
+        let mut old_threshold = None;
+        if let Some(threshold) = config.threshold {
+            let old = self
+                .get_threshold(&mut timeout)
+                .map_err(|e| fail(ApplyStep::ReadCurrent, e))?;
+
+            if old != threshold {
+                old_threshold = Some(old);
+
+                let res = self
+                    .set_threshold(threshold, &mut timeout)
+                    .map_err(|e| (ApplyStep::Threshold, e))
+                    .and_then(|()| match self.get_threshold(&mut timeout) {
+                        Ok(t) if t == threshold => Ok(()),
+                        Ok(_) => Err((ApplyStep::VerifyThreshold, Error::WriteVerificationFailed)),
+                        Err(e) => Err((ApplyStep::VerifyThreshold, e)),
+                    });
+
+                if let Err((step, error)) = res {
+                    let rolled_back = self.set_threshold(old, &mut timeout).is_ok();
+                    return Err(ApplyError {
+                        step,
+                        error,
+                        rolled_back,
+                    });
+                }
+            }
+        }
+
+        if let Some(addr) = config.addr {
+            if let Err(error) = self.set_addr(addr, &mut timeout) {
+                let rolled_back = match old_threshold {
+                    Some(old) => self.set_threshold(old, &mut timeout).is_ok(),
+                    None => false,
+                };
+
+                // The address is read back by set_addr itself.
+                let step = match error {
+                    Error::AddrChangeFailed(_) => ApplyStep::VerifyAddr,
+                    _ => ApplyStep::Addr,
+                };
+
+                return Err(ApplyError {
+                    step,
+                    error,
+                    rolled_back,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use hal::serial;
+
+use crate::{Error, Pzem, RawMeasurement, Timeout};
+
+// Number of reads garbled in a row taken for an address conflict.
+const CONFLICT_READS: usize = 3;
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    // Tells whether the slave at `addr` is likely shared by several sensors.
+    //
+    // The sensors sharing an address answer at once, so the headers of the colliding responses
+    // agree while the measurements don't: every read fails the CRC check. The parameter reads
+    // are no use here, as identical responses collide harmlessly.
+    pub(crate) fn conflict_at<Tm: Timeout>(
+        &mut self,
+        addr: u8,
+        mut timeout: Tm,
+    ) -> Result<bool, Error<WriteError, ReadError>> {
+        let old = core::mem::replace(&mut self.addr, addr);
+        let mut res = Ok(true);
+        for _ in 0..CONFLICT_READS {
+            match self.read_raw(&mut RawMeasurement::default(), &mut timeout) {
+                Err(Error::CrcMismatch) => continue,
+                Err(Error::WriteError(e)) => res = Err(Error::WriteError(e)),
+                Err(Error::ReadError(e)) => res = Err(Error::ReadError(e)),
+                _ => res = Ok(false),
+            }
+            break;
+        }
+        self.addr = old;
+
+        res
+    }
+}
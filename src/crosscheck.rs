@@ -0,0 +1,97 @@
+use crate::Measurement;
+
+/// Field of a measurement, as reported by [`Measurement::cross_check`](struct.Measurement.html#method.cross_check).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Field {
+    Voltage,
+    Current,
+    Power,
+    Energy,
+    Frequency,
+    Pf,
+}
+
+/// Values decoded from the measurement registers by the `pzemac` component of ESPHome and by
+/// the PZEM-AC driver of Tasmota, which use the same formulas.
+///
+/// Note that these firmwares report the energy in Wh, rather than kWh.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct EsphomeReference {
+    pub voltage: f32,
+    pub current: f32,
+    pub power: f32,
+    /// Energy in Wh.
+    pub energy: f32,
+    pub frequency: f32,
+    pub pf: f32,
+}
+
+impl EsphomeReference {
+    /// Decodes the block of the 10 measurement registers, e.g. from
+    /// [`RawMeasurement::to_registers`](struct.RawMeasurement.html#method.to_registers).
+    pub fn from_registers(regs: &[u16; 10]) -> Self {
+        // pzem_get_32bit(): low word first.
+        let u32_at = |i: usize| ((regs[i + 1] as u32) << 16) | regs[i] as u32;
+
+        Self {
+            voltage: regs[0] as f32 / 10.0,
+            current: u32_at(1) as f32 / 1000.0,
+            power: u32_at(3) as f32 / 10.0,
+            energy: u32_at(5) as f32,
+            frequency: regs[7] as f32 / 10.0,
+            pf: regs[8] as f32 / 100.0,
+        }
+    }
+}
+
+/// Value of a field differing from the reference.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub field: Field,
+    /// Value of the measurement, in the units of the reference.
+    pub value: f32,
+    pub reference: f32,
+}
+
+impl Measurement {
+    /// Compares the measurement against the values ESPHome and Tasmota decode from the same
+    /// registers, returning the fields which differ by more than the rounding error.
+    ///
+    /// A debugging aid for the users migrating from these firmwares and seeing other values:
+    /// the discrepancies point at a nonstandard [`Scaling`](struct.Scaling.html) or at a
+    /// mix-up of the energy units, while none at all point at the comparison itself.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut raw = RawMeasurement::default();
+    /// pzem.read_raw(&mut raw, Some((&mut tim, TIMEOUT)))?;
+    /// let m = scaling.apply(&raw);
+    /// for d in m.cross_check(&raw.to_registers()) {
+    ///     println!("{:?}: {} here, {} in ESPHome", d.field, d.value, d.reference);
+    /// }
+    /// ```
+    pub fn cross_check(&self, regs: &[u16; 10]) -> heapless::Vec<Discrepancy, 6> {
+        let r = EsphomeReference::from_registers(regs);
+        let fields = [
+            (Field::Voltage, self.voltage, r.voltage),
+            (Field::Current, self.current, r.current),
+            (Field::Power, self.power, r.power),
+            (Field::Energy, self.energy * 1000.0, r.energy),
+            (Field::Frequency, self.frequency, r.frequency),
+            (Field::Pf, self.pf, r.pf),
+        ];
+
+        fields
+            .iter()
+            .filter(|(_, value, reference)| {
+                (value - reference).abs() > reference.abs().max(1.0) * 1e-5
+            })
+            .map(|&(field, value, reference)| Discrepancy {
+                field,
+                value,
+                reference,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,40 @@
+use core::fmt::{Display, Write};
+
+use crate::Measurement;
+
+impl Measurement {
+    /// Returns the header line matching the records written by
+    /// [`write_csv`](#method.write_csv), terminated with a newline.
+    pub const fn csv_header() -> &'static str {
+        "timestamp,voltage,current,power,energy,frequency,pf,alarm\n"
+    }
+
+    /// Writes the measurement as a single CSV record, terminated with a newline.
+    ///
+    /// The `timestamp` is written as the first column using its `Display` implementation,
+    /// the remaining columns follow the order of [`csv_header`](#method.csv_header),
+    /// with the precision of the sensor registers. The alarm status is written as `0` or `1`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut line = heapless::String::<96>::new();
+    /// m.write_csv(&mut line, rtc.unix_time()).unwrap();
+    /// // 1589932800,230.1,1.234,283.9,12.345,50.0,0.99,0
+    /// file.write(line.as_bytes()).unwrap();
+    /// ```
+    pub fn write_csv<W: Write, Ts: Display>(&self, w: &mut W, timestamp: Ts) -> core::fmt::Result {
+        writeln!(
+            w,
+            "{},{:.1},{:.3},{:.1},{:.3},{:.1},{:.2},{}",
+            timestamp,
+            self.voltage,
+            self.current,
+            self.power,
+            self.energy,
+            self.frequency,
+            self.pf,
+            self.alarm as u8
+        )
+    }
+}
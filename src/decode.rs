@@ -0,0 +1,231 @@
+//! Decoding rules of the measurement registers.
+//!
+//! These are the same functions the driver uses internally, exposed for users doing raw register
+//! reads (partial reads, custom commands), so that their results stay consistent with the driver.
+//!
+//! The measurement registers, in order:
+//!
+//! | Register | Quantity           | Resolution |
+//! |----------|--------------------|------------|
+//! | `0x0000` | Voltage            | 0.1 V      |
+//! | `0x0001` | Current, low word  | 0.001 A    |
+//! | `0x0002` | Current, high word |            |
+//! | `0x0003` | Power, low word    | 0.1 W      |
+//! | `0x0004` | Power, high word   |            |
+//! | `0x0005` | Energy, low word   | 1 Wh       |
+//! | `0x0006` | Energy, high word  |            |
+//! | `0x0007` | Frequency          | 0.1 Hz     |
+//! | `0x0008` | Power factor       | 0.01       |
+//! | `0x0009` | Alarm status       | `0xffff` when on |
+//!
+//! The same map is available as data in the [`regs`](../regs/index.html) module.
+//!
+//! The floating point conversions are not available with the `no-float` feature;
+//! look [`RawMeasurement`](../struct.RawMeasurement.html) instead.
+
+#[cfg(not(feature = "size-opt"))]
+use core::fmt::{Display, Formatter};
+
+use crate::codec::{crc_check_slice, registers, SoftwareCrc};
+#[cfg(not(feature = "no-float"))]
+use crate::regs;
+#[cfg(not(feature = "no-float"))]
+use crate::Measurement;
+use crate::{RawMeasurement, CMD_READ, READ_REG_MAX, REG_COUNT};
+
+/// Reasons a response frame fails to decode into the measurements, after it has made it
+/// through the serial link intact.
+///
+/// Returned by [`decode_response`](fn.decode_response.html), and by the driver wrapped in
+/// [`Error::Decode`](../enum.Error.html#variant.Decode).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The frame is not as long as its byte count tells.
+    BadLength,
+    /// The byte count of the frame doesn't match the 10 measurement registers, nor the extra
+    /// ones of the clones, up to [`READ_REG_MAX`](../constant.READ_REG_MAX.html).
+    BadByteCount,
+    /// A register holds a value out of the range of the sensor, e.g. a power factor above 1.
+    ImplausibleScale,
+}
+
+#[cfg(not(feature = "size-opt"))]
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        match self {
+            DecodeError::BadLength => write!(f, "bad frame length"),
+            DecodeError::BadByteCount => write!(f, "bad byte count"),
+            DecodeError::ImplausibleScale => write!(f, "register out of range"),
+        }
+    }
+}
+
+/// Output type decoded directly from the block of the 10 measurement registers,
+/// read by [`Pzem::read_as`](../struct.Pzem.html#method.read_as).
+///
+/// Allows defining custom outputs, e.g. integers in other units or only some of the fields,
+/// without the conversions of [`Measurement`](../struct.Measurement.html).
+///
+/// # Example
+///
+/// ```
+/// use pzem004t::decode::{decode_u32_registers, FromRegisters};
+///
+/// /// Voltage in mV and power in mW.
+/// struct Milli {
+///     voltage: u32,
+///     power: u32,
+/// }
+///
+/// impl FromRegisters for Milli {
+///     fn from_registers(regs: &[u16; 10]) -> Self {
+///         Self {
+///             voltage: regs[0] as u32 * 100,
+///             power: decode_u32_registers(regs[4], regs[3]) * 100,
+///         }
+///     }
+/// }
+///
+/// let m = Milli::from_registers(&[2301, 0, 0, 2839, 0, 0, 0, 500, 99, 0]);
+/// assert_eq!((m.voltage, m.power), (230_100, 283_900));
+/// ```
+pub trait FromRegisters {
+    /// Decodes the registers in the order of the table above.
+    fn from_registers(regs: &[u16; 10]) -> Self;
+}
+
+impl FromRegisters for RawMeasurement {
+    fn from_registers(regs: &[u16; 10]) -> Self {
+        RawMeasurement::from_registers(regs)
+    }
+}
+
+/// Decodes with the datasheet scaling, regardless of [`Pzem::with_scaling`](../struct.Pzem.html#method.with_scaling).
+#[cfg(not(feature = "no-float"))]
+impl FromRegisters for Measurement {
+    fn from_registers(regs: &[u16; 10]) -> Self {
+        decode_registers(regs)
+    }
+}
+
+/// Combines two 16-bit registers into a 32-bit value.
+///
+/// Note that PZEM004T transmits the low word first, i.e. at the lower register address.
+///
+/// ```
+/// assert_eq!(pzem004t::decode::decode_u32_registers(0x0001, 0x86a0), 100_000);
+/// ```
+pub const fn decode_u32_registers(hi_word: u16, lo_word: u16) -> u32 {
+    ((hi_word as u32) << 16) | lo_word as u32
+}
+
+/// Converts the raw voltage register into V.
+#[cfg(not(feature = "no-float"))]
+pub fn voltage(raw: u16) -> f32 {
+    regs::VOLTAGE.scale(raw as u32)
+}
+
+/// Converts the raw current registers into A.
+#[cfg(not(feature = "no-float"))]
+pub fn current(raw: u32) -> f32 {
+    regs::CURRENT.scale(raw)
+}
+
+/// Converts the raw power registers into W.
+#[cfg(not(feature = "no-float"))]
+pub fn power(raw: u32) -> f32 {
+    regs::POWER.scale(raw)
+}
+
+/// Converts the raw energy registers (Wh) into kWh.
+#[cfg(not(feature = "no-float"))]
+pub fn energy(raw: u32) -> f32 {
+    regs::ENERGY.scale(raw)
+}
+
+/// Converts the raw frequency register into Hz.
+#[cfg(not(feature = "no-float"))]
+pub fn frequency(raw: u16) -> f32 {
+    regs::FREQUENCY.scale(raw as u32)
+}
+
+/// Converts the raw power factor register.
+#[cfg(not(feature = "no-float"))]
+pub fn pf(raw: u16) -> f32 {
+    regs::PF.scale(raw as u32)
+}
+
+/// Converts the raw alarm status register.
+#[cfg(not(feature = "no-float"))]
+pub fn alarm(raw: u16) -> bool {
+    raw != 0
+}
+
+/// Decodes the whole block of the 10 measurement registers.
+#[cfg(not(feature = "no-float"))]
+pub fn decode_registers(regs: &[u16; 10]) -> Measurement {
+    RawMeasurement::from_registers(regs).into()
+}
+
+/// Decodes a response frame to the measurement read, as returned by
+/// [`Pzem::read_with_raw`](../struct.Pzem.html#method.read_with_raw).
+///
+/// The frame takes the first `5 + frame[2]` bytes of `frame`, the rest is ignored.
+///
+/// Returns `None` if the frame is not a valid response, e.g. it fails the CRC check.
+/// Look [`decode_response`](fn.decode_response.html) for the reason of the failure.
+pub fn decode_frame(frame: &[u8]) -> Option<RawMeasurement> {
+    let len = 3 + *frame.get(2)? as usize + 2;
+    if frame[1] != CMD_READ || len > frame.len() || !crc_check_slice(&SoftwareCrc, &frame[..len]) {
+        return None;
+    }
+
+    parse(&frame[..len]).ok()
+}
+
+/// Decodes a response frame to the measurement read, whose CRC has already been checked.
+///
+/// Unlike [`decode_frame`](fn.decode_frame.html), also rejects the registers out of the range
+/// of the sensor: the voltage above 300 V, the frequency above 70 Hz or the power factor above 1.
+///
+/// ```
+/// use pzem004t::decode::{decode_response, DecodeError};
+///
+/// let mut frame = [0u8; 25];
+/// frame[..3].copy_from_slice(&[0x01, 0x04, 20]);
+/// frame[3..5].copy_from_slice(&2301u16.to_be_bytes());
+/// assert_eq!(decode_response(&frame).unwrap().voltage, 2301);
+///
+/// assert_eq!(decode_response(&frame[..23]), Err(DecodeError::BadLength));
+/// frame[19..21].copy_from_slice(&101u16.to_be_bytes());
+/// assert_eq!(decode_response(&frame), Err(DecodeError::ImplausibleScale));
+/// ```
+pub fn decode_response(frame: &[u8]) -> Result<RawMeasurement, DecodeError> {
+    let m = parse(frame)?;
+
+    if m.voltage > 3000 || m.frequency > 700 || m.pf > 100 {
+        return Err(DecodeError::ImplausibleScale);
+    }
+
+    Ok(m)
+}
+
+// Whether `count` is the byte count of a measurement response: whole registers, from the
+// ones of a genuine sensor up to the extra ones of the clones.
+pub(crate) fn valid_count(count: usize) -> bool {
+    count & 1 == 0 && (2 * REG_COUNT as usize..=2 * READ_REG_MAX).contains(&count)
+}
+
+// Decodes the registers of the response, checking only its structure: the driver reports
+// whatever the sensor measured.
+pub(crate) fn parse(frame: &[u8]) -> Result<RawMeasurement, DecodeError> {
+    let count = *frame.get(2).ok_or(DecodeError::BadLength)? as usize;
+    if !valid_count(count) {
+        return Err(DecodeError::BadByteCount);
+    }
+    if frame.len() != 3 + count + 2 {
+        return Err(DecodeError::BadLength);
+    }
+
+    Ok(RawMeasurement::from_registers(&registers(frame)))
+}
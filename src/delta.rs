@@ -0,0 +1,56 @@
+use crate::Measurement;
+
+/// Per-field differences between two measurements, produced by [`Measurement::diff`](struct.Measurement.html#method.diff).
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MeasurementDelta {
+    /// Voltage change in V.
+    pub voltage: f32,
+    /// Current change in A.
+    pub current: f32,
+    /// Power change in W.
+    pub power: f32,
+    /// Energy consumed in between, in Wh. Negative if the counter has been reset or rolled over.
+    pub energy_wh: f32,
+    /// Frequency change in Hz.
+    pub frequency: f32,
+    /// Power factor change.
+    pub pf: f32,
+    /// Whether the alarm status has changed.
+    pub alarm_changed: bool,
+}
+
+impl MeasurementDelta {
+    /// Returns the rate of power change in W/s, given the time `dt` in seconds between the measurements.
+    pub fn power_ramp(&self, dt: f32) -> f32 {
+        self.power / dt
+    }
+
+    /// Returns the average power in W over the time `dt` in seconds between the measurements,
+    /// computed from the energy consumed.
+    ///
+    /// The energy counter has a resolution of 1 Wh, hence it is only meaningful over longer periods.
+    pub fn average_power(&self, dt: f32) -> f32 {
+        self.energy_wh * 3600.0 / dt
+    }
+}
+
+impl Measurement {
+    /// Computes the differences of this measurement from the previous one `prev`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let delta = m.diff(&prev);
+    /// hprintln!("{:.0} Wh consumed, power ramp {:.1} W/s", delta.energy_wh, delta.power_ramp(60.0)).unwrap();
+    /// ```
+    pub fn diff(&self, prev: &Measurement) -> MeasurementDelta {
+        MeasurementDelta {
+            voltage: self.voltage - prev.voltage,
+            current: self.current - prev.current,
+            power: self.power - prev.power,
+            energy_wh: (self.energy - prev.energy) * 1000.0,
+            frequency: self.frequency - prev.frequency,
+            pf: self.pf - prev.pf,
+            alarm_changed: self.alarm != prev.alarm,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use hal::serial;
+use hal::timer::CountDown;
+
+use crate::io::{Drain, ReadBlocking, WriteBlocking};
+use crate::{Error, NoTimeout, Operation, Pzem, RawMeasurement, Timeout, ADDR_DEFAULT};
+
+// Sent through the loopback by `self_test`: alternating bits and both levels held for a byte.
+// Not a request any sensor on the line would answer.
+const LOOPBACK_PATTERN: [u8; 4] = [0x55, 0xaa, 0x00, 0xff];
+
+/// Diagnosis of the serial link reported by [`Pzem::link_doctor`](struct.Pzem.html#method.link_doctor).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// All the probes succeeded.
+    Healthy,
+    /// Nothing has been received at all. Check the wiring (TX and RX swapped?), the power
+    /// supply of the sensor (the mains side must be connected) and the slave address.
+    NoResponse,
+    /// Only bytes not forming a response have been received. Usually caused by a wrong baud
+    /// rate (PZEM004T uses 9600 baud) or by noise on the line.
+    GarbageOnly,
+    /// The responses have been received, but failed the CRC check. Usually caused by a
+    /// parity or stop-bit mismatch (PZEM004T uses 8N1), or by marginal signal levels.
+    CrcOnlyFailures,
+}
+
+/// Results of the probes run by [`Pzem::link_doctor`](struct.Pzem.html#method.link_doctor).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LinkReport {
+    pub diagnosis: Diagnosis,
+    /// Number of bytes received while no request was pending.
+    pub idle_bytes: u32,
+    /// Number of probes answered with a valid response.
+    pub responses: u8,
+    /// Number of probes answered with a response failing the CRC check.
+    pub crc_failures: u8,
+    /// Number of probes answered with an unexpected response.
+    pub garbage: u8,
+    /// Number of probes which timed out.
+    pub timeouts: u8,
+}
+
+impl LinkReport {
+    fn new() -> Self {
+        Self {
+            diagnosis: Diagnosis::NoResponse,
+            idle_bytes: 0,
+            responses: 0,
+            crc_failures: 0,
+            garbage: 0,
+            timeouts: 0,
+        }
+    }
+
+    // Counts the outcome of a probe, passing the errors of the serial peripheral through.
+    fn tally<WriteError, ReadError>(
+        &mut self,
+        res: Result<(), Error<WriteError, ReadError>>,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        match res {
+            Ok(()) | Err(Error::Exception(_)) => self.responses += 1,
+            Err(Error::CrcMismatch) => self.crc_failures += 1,
+            Err(Error::TimedOut { .. }) => self.timeouts += 1,
+            Err(e @ Error::WriteError(_)) | Err(e @ Error::ReadError(_)) => return Err(e),
+            Err(_) => self.garbage += 1,
+        }
+
+        Ok(())
+    }
+
+    fn diagnose(&mut self, probes: u8) {
+        self.diagnosis = if self.responses == probes {
+            Diagnosis::Healthy
+        } else if self.crc_failures > 0 {
+            Diagnosis::CrcOnlyFailures
+        } else if self.garbage > 0 || self.idle_bytes > 0 {
+            Diagnosis::GarbageOnly
+        } else {
+            Diagnosis::NoResponse
+        };
+    }
+}
+
+/// Results of [`Pzem::self_test`](struct.Pzem.html#method.self_test).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Whether the test pattern came back intact through the loopback, `None` if not checked.
+    pub loopback: Option<bool>,
+    /// Diagnosis of the parameter read.
+    pub diagnosis: Diagnosis,
+    /// Address parameter read off the sensor, `None` if it didn't answer.
+    pub echoed_addr: Option<u16>,
+}
+
+impl SelfTestReport {
+    /// Returns `true` if the UART passed the loopback check, if run, and the sensor answered.
+    pub fn passed(&self) -> bool {
+        self.loopback != Some(false) && self.diagnosis == Diagnosis::Healthy
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Runs a sequence of probes to tell the wrong serial configuration from the wiring faults.
+    ///
+    /// First listens to the line for a period of the read timeout without sending anything,
+    /// then reads the measurements and the address parameter of the sensor. The timeouts
+    /// should be generous, e.g. a second, so that a slow response isn't mistaken for none.
+    ///
+    /// Only the errors of the serial peripheral are returned as `Err`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let report = pzem.link_doctor(Some((&mut tim, 1.hz()))).unwrap();
+    /// if report.diagnosis != Diagnosis::Healthy {
+    ///     hprintln!("PZEM004T link: {:?}", report).unwrap();
+    /// }
+    /// ```
+    pub fn link_doctor<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<LinkReport, Error<WriteError, ReadError>> {
+        let mut report = LinkReport::new();
+
+        // Nothing should be received while no request is pending.
+        if let Some((timer, time)) = timeout.get(Operation::Read) {
+            timer.start(time);
+            while timer.wait().is_err() {
+                let (n, _) = self
+                    .uart
+                    .drain(Some(&mut *timer), None)
+                    .map_err(Error::ReadError)?;
+                report.idle_bytes = report.idle_bytes.wrapping_add(n);
+            }
+        }
+
+        self.run_probes(&mut report, &mut timeout)?;
+
+        Ok(report)
+    }
+
+    /// Checks that the sensor answers the requests, without the idle listening of
+    /// [`link_doctor`](#method.link_doctor).
+    ///
+    /// Returns the diagnosis, which is `Diagnosis::Healthy` if the link works. Only the errors
+    /// of the serial peripheral are returned as `Err`.
+    pub fn verify_link<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<Diagnosis, Error<WriteError, ReadError>> {
+        let mut report = LinkReport::new();
+        self.run_probes(&mut report, &mut timeout)?;
+
+        Ok(report.diagnosis)
+    }
+
+    /// First-run diagnostic of the product: checks the UART, then that the sensor answers.
+    ///
+    /// If `loopback` is given, it is called with `true` to loop the transmitter of the UART
+    /// back to its receiver (e.g. the loopback mode of the peripheral, or an analog switch on
+    /// the board), and with `false` to restore the connection to the sensor. A short test
+    /// pattern is sent in between, and must be received back within the parameter read timeout.
+    /// Without a timeout, a broken loopback waits indefinitely.
+    ///
+    /// Then reads the address parameter of the sensor, a harmless request which any sensor
+    /// answers. The echoed address is expected to be the configured one, unless the general
+    /// address is used; a mismatch is reported as `Diagnosis::GarbageOnly`.
+    ///
+    /// Only the errors of the serial peripheral are returned as `Err`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let report = pzem
+    ///     .self_test(Some(&mut |on| uart.set_loopback(on)), Some((&mut tim, 1.hz())))
+    ///     .unwrap();
+    /// if !report.passed() {
+    ///     display.show_error(report.diagnosis);
+    /// }
+    /// ```
+    pub fn self_test<Tm: Timeout>(
+        &mut self,
+        loopback: Option<&mut dyn FnMut(bool)>,
+        mut timeout: Tm,
+    ) -> Result<SelfTestReport, Error<WriteError, ReadError>> {
+        let loopback = match loopback {
+            Some(hint) => {
+                hint(true);
+                let res = self.echo_pattern(timeout.get(Operation::ReadParam));
+                hint(false);
+                Some(res?)
+            }
+            None => None,
+        };
+
+        let mut report = LinkReport::new();
+        let res = self.get_addr(&mut timeout);
+        let echoed_addr = res.as_ref().ok().copied();
+        match echoed_addr {
+            Some(addr) if self.addr != ADDR_DEFAULT && addr != self.addr as u16 => {
+                report.garbage += 1
+            }
+            _ => report.tally(res.map(|_| ()))?,
+        }
+        report.diagnose(1);
+
+        Ok(SelfTestReport {
+            loopback,
+            diagnosis: report.diagnosis,
+            echoed_addr,
+        })
+    }
+
+    // Sends the test pattern, returning whether it has been received back.
+    fn echo_pattern<T: CountDown>(
+        &mut self,
+        timeout: Option<(&mut T, T::Time)>,
+    ) -> Result<bool, Error<WriteError, ReadError>> {
+        self.uart
+            .drain::<NoTimeout>(None, self.drain_cap())
+            .map_err(Error::ReadError)?;
+
+        let mut timer = timeout.map(|(timer, time)| {
+            timer.start(time);
+            timer
+        });
+        if !self
+            .uart
+            .write_blocking(timer.as_deref_mut(), &LOOPBACK_PATTERN, self.flush)
+            .map_err(Error::WriteError)?
+        {
+            return Ok(false);
+        }
+
+        let mut echo = [0u8; LOOPBACK_PATTERN.len()];
+        let n = self
+            .uart
+            .read_blocking(timer, &mut echo)
+            .map_err(Error::ReadError)?;
+
+        Ok(n as usize == echo.len() && echo == LOOPBACK_PATTERN)
+    }
+
+    // Reads the measurements and the address, classifying the outcomes into the report.
+    fn run_probes<Tm: Timeout>(
+        &mut self,
+        report: &mut LinkReport,
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut m = RawMeasurement::default();
+        let results = [
+            self.read_raw(&mut m, &mut timeout),
+            self.get_addr(&mut timeout).map(|_| ()),
+        ];
+        let probes = results.len() as u8;
+
+        for res in results {
+            report.tally(res)?;
+        }
+        report.diagnose(probes);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use fugit::{Duration, Rate};
+use hal::timer::CountDown;
+
+use crate::{Operation, Timeout, Timeouts};
+
+/// `Time` unit of a timer a [`fugit`](https://crates.io/crates/fugit) duration can be converted into.
+///
+/// Implemented for the fugit durations, counted in ticks of any period, and for the fugit
+/// rates, the timer then expiring at the frequency of the inverse of the duration. The
+/// durations not representable in the unit saturate to the longest timeout it can express.
+///
+/// Implement it for the `Time` unit of a HAL not using fugit:
+///
+/// ```ignore
+/// impl FromDuration for Hertz {
+///     fn from_duration<const NOM: u32, const DENOM: u32>(d: Duration<u32, NOM, DENOM>) -> Self {
+///         let us = d.convert::<1, 1_000_000>().ticks().max(1);
+///         Hertz((1_000_000 / us).max(1))
+///     }
+/// }
+/// ```
+pub trait FromDuration {
+    fn from_duration<const NOM: u32, const DENOM: u32>(d: Duration<u32, NOM, DENOM>) -> Self;
+}
+
+impl<const O_NOM: u32, const O_DENOM: u32> FromDuration for Duration<u32, O_NOM, O_DENOM> {
+    fn from_duration<const NOM: u32, const DENOM: u32>(d: Duration<u32, NOM, DENOM>) -> Self {
+        d.const_try_into()
+            .unwrap_or_else(|| Self::from_ticks(u32::MAX))
+    }
+}
+
+impl<const O_NOM: u32, const O_DENOM: u32> FromDuration for Rate<u32, O_NOM, O_DENOM> {
+    fn from_duration<const NOM: u32, const DENOM: u32>(d: Duration<u32, NOM, DENOM>) -> Self {
+        match d.try_into_rate::<O_NOM, O_DENOM>() {
+            Some(rate) if rate.raw() > 0 => rate,
+            Some(_) => Self::from_raw(1),
+            None => Self::from_raw(u32::MAX), // zero duration
+        }
+    }
+}
+
+/// A single timeout given as a fugit duration, e.g. `(&mut tim, 200.millis())`.
+impl<T: CountDown, const NOM: u32, const DENOM: u32> Timeout for (&mut T, Duration<u32, NOM, DENOM>)
+where
+    T::Time: FromDuration,
+{
+    type Timer = T;
+    fn get(&mut self, _op: Operation) -> Option<(&mut T, T::Time)> {
+        Some((&mut *self.0, T::Time::from_duration(self.1)))
+    }
+}
+
+impl<const NOM: u32, const DENOM: u32> Timeouts<Duration<u32, NOM, DENOM>> {
+    /// Converts the timeouts into the `Time` unit of the timer.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let timeouts = Timeouts {
+    ///     read: 100.millis(),
+    ///     read_param: 100.millis(),
+    ///     write_param: 500.millis(),
+    ///     reset: 1.secs(),
+    /// };
+    /// let mut session = Session::new(&mut tim, timeouts.into_time());
+    /// ```
+    pub fn into_time<Time: FromDuration>(self) -> Timeouts<Time> {
+        Timeouts {
+            read: Time::from_duration(self.read),
+            read_param: Time::from_duration(self.read_param),
+            write_param: Time::from_duration(self.write_param),
+            reset: Time::from_duration(self.reset),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use hal::timer::CountDown;
+
+/// Object-safe counterpart of `CountDown`, implemented for every `CountDown` timer.
+///
+/// Look [`DynCountDown`](struct.DynCountDown.html).
+pub trait ErasedCountDown<Time> {
+    fn start_erased(&mut self, count: Time);
+    fn wait_erased(&mut self) -> nb::Result<(), void::Void>;
+}
+
+impl<T: CountDown> ErasedCountDown<T::Time> for T {
+    fn start_erased(&mut self, count: T::Time) {
+        self.start(count)
+    }
+
+    fn wait_erased(&mut self) -> nb::Result<(), void::Void> {
+        self.wait()
+    }
+}
+
+/// Type-erased timer, so that the code passing the timeouts to the driver doesn't have
+/// to be generic over the timer type.
+///
+/// Any timers counting in the same `Time` unit can be used interchangeably, e.g. SysTick
+/// and a general purpose timer of the same HAL.
+///
+/// # Example
+/// ```ignore
+/// fn poll(pzem: &mut Pzem<Serial1>, tim: &mut DynCountDown<Hertz>) {
+///     pzem.read(&mut m, Some((tim, TIMEOUT))).ok();
+/// }
+///
+/// poll(&mut pzem, &mut DynCountDown::new(&mut syst));
+/// poll(&mut pzem, &mut DynCountDown::new(&mut tim2));
+/// ```
+pub struct DynCountDown<'a, Time> {
+    inner: &'a mut dyn ErasedCountDown<Time>,
+}
+
+impl<'a, Time> DynCountDown<'a, Time> {
+    pub fn new<T: CountDown<Time = Time>>(timer: &'a mut T) -> Self {
+        Self { inner: timer }
+    }
+}
+
+impl<Time> CountDown for DynCountDown<'_, Time> {
+    type Time = Time;
+    fn start<T: Into<Self::Time>>(&mut self, count: T) {
+        self.inner.start_erased(count.into())
+    }
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        self.inner.wait_erased()
+    }
+}
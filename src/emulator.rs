@@ -0,0 +1,377 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::codec::{REQ_LEN, WRITE_MULTI_REQ_LEN};
+use crate::{
+    CrcProvider, RawMeasurement, SoftwareCrc, ADDR_DEFAULT, ADDR_MAX, ADDR_MIN, CMD_READ,
+    CMD_READ_PARAM, CMD_RESET, CMD_WRITE_MULTI, CMD_WRITE_PARAM, EXCEPTION_FLAG, PARAM_ADDR,
+    PARAM_THRESHOLD, REG_COUNT,
+};
+
+const EXC_ILLEGAL_FUNCTION: u8 = 0x01;
+const EXC_ILLEGAL_ADDRESS: u8 = 0x02;
+const EXC_ILLEGAL_DATA: u8 = 0x03;
+
+type Waveform = Box<dyn FnMut(Duration) -> RawMeasurement + Send>;
+
+/// Slave emulated by a [`PzemEmulator`](struct.PzemEmulator.html).
+///
+/// The measurements are produced by the waveform, a function of the time elapsed since the
+/// emulator was created. The energy counter is reset relative to the waveform, and the alarm
+/// status follows the power threshold, as on the sensor.
+pub struct EmulatedSlave {
+    addr: u8,
+    threshold: u16,
+    energy_offset: u32,
+    waveform: Waveform,
+}
+
+impl EmulatedSlave {
+    /// Creates the slave at `addr`, measuring a constant `m`.
+    pub fn new(addr: u8, m: RawMeasurement) -> Self {
+        Self {
+            addr,
+            threshold: 2300,
+            energy_offset: 0,
+            waveform: Box::new(move |_| m),
+        }
+    }
+
+    /// Sets the waveform producing the measurements.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // 100 W load switched on and off every 10 seconds, accumulating energy.
+    /// let slave = EmulatedSlave::new(0x01, RawMeasurement::default()).with_waveform(|t| {
+    ///     let on = t.as_secs() / 10 % 2 == 0;
+    ///     RawMeasurement {
+    ///         voltage: 2300,
+    ///         current: if on { 435 } else { 0 },
+    ///         power: if on { 1000 } else { 0 },
+    ///         energy: (t.as_secs() / 2 * 100 / 3600) as u32,
+    ///         frequency: 500,
+    ///         pf: if on { 100 } else { 0 },
+    ///         alarm: false,
+    ///     }
+    /// });
+    /// ```
+    pub fn with_waveform(
+        mut self,
+        waveform: impl FnMut(Duration) -> RawMeasurement + Send + 'static,
+    ) -> Self {
+        self.waveform = Box::new(waveform);
+        self
+    }
+
+    /// Sets the power alarm threshold in W.
+    pub fn with_threshold(mut self, threshold: u16) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the current slave address, which the master may have changed.
+    pub fn addr(&self) -> u8 {
+        self.addr
+    }
+
+    /// Returns the current power alarm threshold in W.
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    fn measure(&mut self, t: Duration) -> RawMeasurement {
+        let mut m = (self.waveform)(t);
+        if m.energy < self.energy_offset {
+            self.energy_offset = 0;
+        }
+        m.energy -= self.energy_offset;
+        m.alarm = m.power >= self.threshold as u32 * 10;
+        m
+    }
+}
+
+/// Faults injected by a [`PzemEmulator`](struct.PzemEmulator.html) into its responses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Faults {
+    /// Drops on average one response byte in `n`.
+    pub drop_one_in: Option<u32>,
+    /// Delays every response.
+    pub delay: Duration,
+    /// Seed of the pseudo-random generator, making the faults reproducible.
+    pub seed: u32,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            drop_one_in: None,
+            delay: Duration::ZERO,
+            seed: 0x2545_f491,
+        }
+    }
+}
+
+/// Emulator of one or more PZEM004T slaves, answering the requests received on any
+/// `std::io::Read + Write` stream, e.g. a serial port or a PTY.
+///
+/// Allows testing the whole gateway software without the mains wiring. As with
+/// [`StdIo`](struct.StdIo.html), the stream should be either non-blocking or have a read timeout set.
+///
+/// The requests addressed to the general address `0xf8` are answered by the first slave.
+/// Requests failing the CRC check are ignored, as by the sensor.
+///
+/// Up to `N` bytes of the requests are buffered, 128 unless created by
+/// [`with_buffer`](#method.with_buffer). The bytes received into a full buffer are dropped,
+/// as by the UART of the sensor, and counted, look [`overflows`](#method.overflows).
+///
+/// # Example
+///
+/// ```ignore
+/// let port = serialport::new("/dev/pts/3", 9600).timeout(Duration::from_millis(10)).open()?;
+/// let mut emulator = PzemEmulator::new(port)
+///     .with_slave(EmulatedSlave::new(0x01, load))
+///     .with_faults(Faults { delay: Duration::from_millis(30), ..Faults::default() });
+/// emulator.run()?;
+/// ```
+pub struct PzemEmulator<T, const N: usize = 128> {
+    stream: T,
+    slaves: Vec<EmulatedSlave>,
+    faults: Faults,
+    rng: u32,
+    start: Instant,
+    req: heapless::Vec<u8, N>,
+    overflows: u32,
+}
+
+impl<T: io::Read + io::Write> PzemEmulator<T> {
+    /// Creates the emulator with no slaves, serving the requests received on `stream`.
+    pub fn new(stream: T) -> Self {
+        Self::with_buffer(stream)
+    }
+}
+
+impl<T: io::Read + io::Write, const N: usize> PzemEmulator<T, N> {
+    /// Creates the emulator with no slaves, buffering up to `N` bytes of the requests received
+    /// on `stream`: `PzemEmulator::<_, 32>::with_buffer(stream)`.
+    ///
+    /// # Panics
+    ///
+    /// If `N` can't hold the longest request, 11 bytes.
+    pub fn with_buffer(stream: T) -> Self {
+        assert!(N >= WRITE_MULTI_REQ_LEN);
+
+        let faults = Faults::default();
+        Self {
+            stream,
+            slaves: Vec::new(),
+            faults,
+            rng: faults.seed,
+            start: Instant::now(),
+            req: heapless::Vec::new(),
+            overflows: 0,
+        }
+    }
+
+    /// Adds the emulated slave.
+    pub fn with_slave(mut self, slave: EmulatedSlave) -> Self {
+        self.slaves.push(slave);
+        self
+    }
+
+    /// Sets the faults injected into the responses.
+    pub fn with_faults(mut self, faults: Faults) -> Self {
+        self.faults = faults;
+        self.rng = faults.seed | 1;
+        self
+    }
+
+    /// Returns the emulated slaves.
+    pub fn slaves(&self) -> &[EmulatedSlave] {
+        &self.slaves
+    }
+
+    /// Returns the number of bytes dropped as the buffer was full.
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// Releases the underlying stream.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+
+    /// Serves the requests until the stream fails or ends.
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            self.poll()?;
+        }
+    }
+
+    /// Reads the bytes available on the stream, answering the complete requests.
+    ///
+    /// Returns the number of requests answered; the end of the stream fails with
+    /// `ErrorKind::UnexpectedEof`.
+    pub fn poll(&mut self) -> io::Result<usize> {
+        let mut buf = [0u8; 64];
+        let n = match self.stream.read(&mut buf) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                0
+            }
+            Err(e) => return Err(e),
+        };
+        for &b in &buf[..n] {
+            if self.req.push(b).is_err() {
+                self.overflows = self.overflows.wrapping_add(1);
+            }
+        }
+
+        let mut answered = 0;
+        while let Some(len) = request_len(&self.req) {
+            if self.req.len() < len {
+                break;
+            }
+
+            if !crc_valid(&self.req[..len]) {
+                // Resynchronize on the next byte.
+                self.req.remove(0);
+                continue;
+            }
+            let mut req = [0u8; WRITE_MULTI_REQ_LEN];
+            req[..len].copy_from_slice(&self.req[..len]);
+            let req = &req[..len];
+            self.req.rotate_left(len);
+            self.req.truncate(self.req.len() - len);
+
+            if let Some(resp) = self.respond(req) {
+                self.send(resp)?;
+                answered += 1;
+            }
+        }
+
+        Ok(answered)
+    }
+
+    fn respond(&mut self, req: &[u8]) -> Option<Vec<u8>> {
+        let t = self.start.elapsed();
+        let slave = match req[0] {
+            ADDR_DEFAULT => self.slaves.first_mut()?,
+            addr => self.slaves.iter_mut().find(|s| s.addr == addr)?,
+        };
+
+        let reg = u16::from_be_bytes([req[2], req[3]]);
+        let mut resp = vec![req[0], req[1]];
+        let res = match req[1] {
+            CMD_READ => {
+                let count = u16::from_be_bytes([req[4], req[5]]);
+                let regs = slave.measure(t).to_registers();
+                read_regs(&mut resp, &regs, reg, count)
+            }
+            CMD_READ_PARAM => {
+                let count = u16::from_be_bytes([req[4], req[5]]);
+                let regs = [0, slave.threshold, slave.addr as u16];
+                match reg {
+                    0 => Err(EXC_ILLEGAL_ADDRESS),
+                    _ => read_regs(&mut resp, &regs, reg, count),
+                }
+            }
+            CMD_WRITE_PARAM | CMD_WRITE_MULTI => {
+                let value = match req[1] {
+                    CMD_WRITE_PARAM => u16::from_be_bytes([req[4], req[5]]),
+                    _ => u16::from_be_bytes([req[7], req[8]]),
+                };
+                let res = match reg {
+                    PARAM_THRESHOLD => {
+                        slave.threshold = value;
+                        Ok(())
+                    }
+                    PARAM_ADDR if (ADDR_MIN as u16..=ADDR_MAX as u16).contains(&value) => {
+                        slave.addr = value as u8;
+                        Ok(())
+                    }
+                    PARAM_ADDR => Err(EXC_ILLEGAL_DATA),
+                    _ => Err(EXC_ILLEGAL_ADDRESS),
+                };
+                res.map(|()| resp.extend_from_slice(&req[2..6]))
+            }
+            CMD_RESET => {
+                slave.energy_offset = (slave.waveform)(t).energy;
+                Ok(())
+            }
+            _ => Err(EXC_ILLEGAL_FUNCTION),
+        };
+
+        if let Err(code) = res {
+            resp.truncate(1);
+            resp.extend_from_slice(&[req[1] | EXCEPTION_FLAG, code]);
+        }
+
+        let crc = SoftwareCrc.crc(&resp);
+        resp.extend_from_slice(&crc.to_le_bytes());
+        Some(resp)
+    }
+
+    fn send(&mut self, mut resp: Vec<u8>) -> io::Result<()> {
+        if let Some(n) = self.faults.drop_one_in.filter(|&n| n > 0) {
+            let rng = &mut self.rng;
+            resp.retain(|_| xorshift(rng) % n != 0);
+        }
+
+        if !self.faults.delay.is_zero() {
+            thread::sleep(self.faults.delay);
+        }
+
+        self.stream.write_all(&resp)?;
+        self.stream.flush()
+    }
+}
+
+// Length of the request starting the buffer, or `None` if it isn't known yet.
+fn request_len(req: &[u8]) -> Option<usize> {
+    match *req.get(1)? {
+        CMD_RESET => Some(4),
+        CMD_WRITE_MULTI => Some(WRITE_MULTI_REQ_LEN),
+        _ => Some(REQ_LEN),
+    }
+}
+
+fn crc_valid(frame: &[u8]) -> bool {
+    let (data, crc) = frame.split_at(frame.len() - 2);
+    SoftwareCrc.crc(data) == u16::from_le_bytes([crc[0], crc[1]])
+}
+
+fn read_regs(resp: &mut Vec<u8>, regs: &[u16], start: u16, count: u16) -> Result<(), u8> {
+    let (start, end) = (start as usize, start as usize + count as usize);
+    if count == 0 || count > REG_COUNT || end > regs.len() {
+        return Err(EXC_ILLEGAL_ADDRESS);
+    }
+
+    resp.push(2 * count as u8);
+    for reg in &regs[start..end] {
+        resp.extend_from_slice(&reg.to_be_bytes());
+    }
+
+    Ok(())
+}
+
+fn xorshift(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
@@ -0,0 +1,189 @@
+use crate::{Measurement, MeasurementSink};
+
+/// Largest value of the energy counter in kWh, after which it rolls over to 0.
+pub const ENERGY_MAX: f32 = 9999.999;
+
+// Largest value of the energy counter in Wh, the resolution of the sensor.
+const ENERGY_MAX_WH: u32 = 9_999_999;
+
+// Converts the energy in kWh to whole Wh, the counter being exact in `f32` below 16777 kWh.
+fn to_wh(energy: f32) -> u64 {
+    (energy.max(0.0) * 1000.0 + 0.5) as u64
+}
+
+fn to_kwh(wh: u64) -> f32 {
+    wh as f32 / 1000.0
+}
+
+impl Measurement {
+    /// Estimates the number of hours until the energy counter rolls over, given the
+    /// average power consumption `avg_power` in W.
+    ///
+    /// Returns `None` if no power is being consumed, hence the counter never rolls over.
+    pub fn hours_to_rollover(&self, avg_power: f32) -> Option<f32> {
+        if avg_power <= 0.0 {
+            return None;
+        }
+
+        let remaining = (ENERGY_MAX - self.energy).max(0.0);
+        Some(remaining / (avg_power / 1000.0))
+    }
+
+    /// Returns `true` if the energy counter is projected to roll over within `threshold` hours
+    /// at the average power consumption `avg_power` in W, or has already reached its maximum.
+    ///
+    /// Maintenance firmware may use this to book-keep the counted energy and schedule
+    /// [`reset_energy`](struct.Pzem.html#method.reset_energy) proactively. The average over
+    /// e.g. the last day is the meaningful figure: the instantaneous power would defer the
+    /// reset whenever a sample falls on an idle period.
+    pub fn needs_reset_soon(&self, avg_power: f32, threshold: f32) -> bool {
+        match self.hours_to_rollover(avg_power) {
+            Some(hours) => hours <= threshold,
+            None => self.energy >= ENERGY_MAX,
+        }
+    }
+}
+
+/// Calendar date, as told by an [`Rtc`](trait.Rtc.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    /// Month, `1..=12`.
+    pub month: u8,
+    /// Day of the month, `1..=31`.
+    pub day: u8,
+}
+
+impl Date {
+    // Number of days since 1970-01-01 in the proleptic Gregorian calendar.
+    fn days(&self) -> i32 {
+        let (m, d) = (self.month as i32, self.day as i32);
+        let y = self.year as i32 - (m <= 2) as i32;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+}
+
+/// Real-time clock telling the local date, used by [`DailyEnergy`](struct.DailyEnergy.html).
+///
+/// Implemented for the closures returning the date.
+pub trait Rtc {
+    fn date(&mut self) -> Date;
+}
+
+impl<F: FnMut() -> Date> Rtc for F {
+    fn date(&mut self) -> Date {
+        self()
+    }
+}
+
+/// Energy consumed today, yesterday and in total, split at midnight by the date of an [`Rtc`](trait.Rtc.html).
+///
+/// The energy is accumulated from the differences of the counter of the sensor, so that the
+/// resets and the rollovers of the counter don't affect the figures. The sums are kept in
+/// whole Wh, the resolution of the counter, so that the total doesn't drift with rounding
+/// however long it runs. The energy consumed
+/// between the last measurement before midnight and the first one after is counted to the new day.
+///
+/// # Example
+/// ```ignore
+/// let mut daily = DailyEnergy::new(|| {
+///     let dt = rtc.get_datetime();
+///     Date { year: dt.year, month: dt.month, day: dt.day }
+/// })
+/// .with_total(eeprom.load_total());
+///
+/// loop {
+///     pzem.read_into(&mut daily, Some((&mut tim, TIMEOUT))).ok();
+///     display.show(daily.today(), daily.yesterday(), daily.total());
+///     // ...
+/// }
+/// ```
+pub struct DailyEnergy<R> {
+    rtc: R,
+    date: Option<Date>,
+    counter: Option<u32>,
+    // Energy in Wh.
+    today: u64,
+    yesterday: u64,
+    total: u64,
+}
+
+impl<R: Rtc> DailyEnergy<R> {
+    /// Creates the book-keeping starting from zero, telling the days apart by the date of `rtc`.
+    pub fn new(rtc: R) -> Self {
+        Self {
+            rtc,
+            date: None,
+            counter: None,
+            today: 0,
+            yesterday: 0,
+            total: 0,
+        }
+    }
+
+    /// Starts the total from `total` in kWh, e.g. restored from the non-volatile memory.
+    pub fn with_total(mut self, total: f32) -> Self {
+        self.total = to_wh(total);
+        self
+    }
+
+    /// Returns the energy consumed today in kWh.
+    pub fn today(&self) -> f32 {
+        to_kwh(self.today)
+    }
+
+    /// Returns the energy consumed yesterday in kWh, zero if nothing was measured.
+    pub fn yesterday(&self) -> f32 {
+        to_kwh(self.yesterday)
+    }
+
+    /// Returns the total energy consumed in kWh.
+    pub fn total(&self) -> f32 {
+        to_kwh(self.total)
+    }
+
+    /// Feeds the energy counter in kWh.
+    pub fn update_energy(&mut self, energy: f32) {
+        let date = self.rtc.date();
+        match self.date {
+            Some(prev) if prev != date => {
+                self.yesterday = match date.days() - prev.days() {
+                    1 => self.today,
+                    _ => 0,
+                };
+                self.today = 0;
+            }
+            _ => {}
+        }
+        self.date = Some(date);
+
+        let energy = to_wh(energy).min(ENERGY_MAX_WH as u64) as u32;
+        let consumed = match self.counter {
+            Some(prev) if energy >= prev => energy - prev,
+            // Rolled over just before reaching the maximum.
+            Some(prev) if prev > ENERGY_MAX_WH - 1000 => ENERGY_MAX_WH - prev + energy,
+            // Reset.
+            Some(_) => energy,
+            None => 0,
+        };
+        self.counter = Some(energy);
+
+        self.today += consumed as u64;
+        self.total += consumed as u64;
+    }
+
+    /// Feeds the energy counter of the measurement.
+    pub fn update(&mut self, m: &Measurement) {
+        self.update_energy(m.energy)
+    }
+}
+
+impl<R: Rtc> MeasurementSink for DailyEnergy<R> {
+    fn push(&mut self, m: &Measurement) {
+        self.update(m);
+    }
+}
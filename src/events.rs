@@ -0,0 +1,193 @@
+use crate::{Error, Exception};
+use heapless::HistoryBuffer;
+
+/// Event recorded by the driver into an attached [`EventSink`](trait.EventSink.html).
+///
+/// The variants named after an [`Error`](enum.Error.html) record the operations failed with it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    TimedOut,
+    CrcMismatch,
+    PzemError,
+    IllegalAddress,
+    LineNoise,
+    Exception(Exception),
+    WriteVerificationFailed,
+    ResetFailed,
+    Implausible,
+    BusBusy,
+    AddrChangeFailed,
+    InvalidThreshold,
+    ProbableAddressConflict,
+    Cancelled,
+    Decode,
+    InvalidCommand,
+    TooManyAddresses,
+    /// An operation failed with the error of the serial peripheral.
+    SerialError,
+    /// The alarm status of the sensor went on.
+    AlarmRaised,
+    /// The alarm status of the sensor went off.
+    AlarmCleared,
+    /// The energy counter was reset.
+    EnergyReset,
+}
+
+impl<WriteError, ReadError> From<&Error<WriteError, ReadError>> for Event {
+    fn from(e: &Error<WriteError, ReadError>) -> Self {
+        match e {
+            Error::TimedOut { .. } => Event::TimedOut,
+            Error::CrcMismatch => Event::CrcMismatch,
+            Error::PzemError => Event::PzemError,
+            Error::IllegalAddress => Event::IllegalAddress,
+            Error::LineNoise => Event::LineNoise,
+            Error::Exception(e) => Event::Exception(*e),
+            Error::WriteVerificationFailed => Event::WriteVerificationFailed,
+            Error::ResetFailed => Event::ResetFailed,
+            Error::Implausible => Event::Implausible,
+            Error::BusBusy => Event::BusBusy,
+            Error::AddrChangeFailed(_) => Event::AddrChangeFailed,
+            Error::InvalidThreshold(_) => Event::InvalidThreshold,
+            Error::ProbableAddressConflict => Event::ProbableAddressConflict,
+            Error::Cancelled => Event::Cancelled,
+            Error::Decode(_) => Event::Decode,
+            Error::InvalidCommand => Event::InvalidCommand,
+            Error::TooManyAddresses => Event::TooManyAddresses,
+            Error::WriteError(_) | Error::ReadError(_) => Event::SerialError,
+        }
+    }
+}
+
+/// Event along with the tick it was recorded at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EventRecord {
+    /// Value of the clock passed to [`Pzem::with_event_log`](struct.Pzem.html#method.with_event_log).
+    pub tick: u32,
+    pub event: Event,
+}
+
+/// Destination of the events recorded by the driver.
+///
+/// Look [`EventLog`](struct.EventLog.html).
+pub trait EventSink: Send {
+    fn record(&mut self, record: EventRecord);
+
+    /// Returns the `i`-th retained event, counting from the oldest one.
+    ///
+    /// Sinks which don't retain the events (e.g. forwarding them elsewhere) may keep the default.
+    fn get(&self, _i: usize) -> Option<EventRecord> {
+        None
+    }
+
+    /// Discards the retained events.
+    fn clear(&mut self) {}
+}
+
+impl dyn EventSink + '_ {
+    /// Iterates over the retained events, from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = EventRecord> + '_ {
+        (0..).map_while(move |i| self.get(i))
+    }
+}
+
+/// Log of the `N` most recent events, overwriting the oldest ones.
+///
+/// # Example
+///
+/// ```ignore
+/// let log: &'static mut EventLog<16> = cortex_m::singleton!(: EventLog<16> = EventLog::new()).unwrap();
+/// let mut pzem = Pzem::new(serial, None).unwrap().with_event_log(log, ticks);
+///
+/// // ...
+///
+/// if let Some(log) = pzem.event_log() {
+///     for record in log.iter() {
+///         println!("{}: {:?}", record.tick, record.event);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct EventLog<const N: usize> {
+    buf: HistoryBuffer<EventRecord, N>,
+}
+
+impl<const N: usize> EventLog<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: HistoryBuffer::new(),
+        }
+    }
+
+    /// Returns the number of events in the log.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the most recent event.
+    pub fn last(&self) -> Option<&EventRecord> {
+        self.buf.recent()
+    }
+
+    /// Iterates over the events, from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = &EventRecord> {
+        self.buf.oldest_ordered()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl<const N: usize> EventSink for EventLog<N> {
+    fn record(&mut self, record: EventRecord) {
+        self.buf.write(record);
+    }
+
+    fn get(&self, i: usize) -> Option<EventRecord> {
+        self.buf.oldest_ordered().nth(i).copied()
+    }
+
+    fn clear(&mut self) {
+        EventLog::clear(self)
+    }
+}
+
+// Event sink attached to the driver.
+pub(crate) struct EventHook {
+    pub(crate) sink: &'static mut dyn EventSink,
+    clock: fn() -> u32,
+    alarm: Option<bool>,
+}
+
+impl EventHook {
+    pub(crate) fn new(sink: &'static mut dyn EventSink, clock: fn() -> u32) -> Self {
+        Self {
+            sink,
+            clock,
+            alarm: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, event: Event) {
+        self.sink.record(EventRecord {
+            tick: (self.clock)(),
+            event,
+        });
+    }
+
+    // Records the transition of the alarm status, if any.
+    pub(crate) fn alarm(&mut self, alarm: bool) {
+        if self.alarm.is_some_and(|prev| prev != alarm) {
+            self.record(if alarm {
+                Event::AlarmRaised
+            } else {
+                Event::AlarmCleared
+            });
+        }
+        self.alarm = Some(alarm);
+    }
+}
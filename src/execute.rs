@@ -0,0 +1,169 @@
+use hal::serial;
+
+use crate::codec::{crc_write, HOLDING_RESP_MAX, REQ_LEN, RESET_LEN, WRITE_RESP_LEN};
+use crate::events::Event;
+use crate::{
+    Error, Operation, Pzem, ThresholdError, Timeout, Verified, CMD_READ, CMD_READ_PARAM, CMD_RESET,
+    CMD_WRITE_PARAM, HOLDING_REG_MAX, PARAM_ADDR, PARAM_THRESHOLD, THRESHOLD_MAX,
+};
+
+/// Single Modbus-RTU transaction performed by [`Pzem::execute`](struct.Pzem.html#method.execute).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Reads `count` measurement (input) registers starting at `start`, function `0x04`.
+    ReadInput { start: u16, count: u16 },
+    /// Reads `count` parameter (holding) registers starting at `start`, function `0x03`.
+    ReadHolding { start: u16, count: u16 },
+    /// Writes `value` into the parameter register `reg`, function `0x06`. The address register
+    /// is refused: change it with [`Pzem::set_addr`](struct.Pzem.html#method.set_addr), which
+    /// keeps the driver in sync with the sensor.
+    WriteHolding { reg: u16, value: u16 },
+    /// Resets the energy counter, function `0x42`.
+    ResetEnergy,
+}
+
+/// Buffer receiving the response frame of [`Pzem::execute`](struct.Pzem.html#method.execute).
+#[derive(Debug, Clone)]
+pub struct ResponseBuf {
+    buf: [u8; HOLDING_RESP_MAX],
+    len: usize,
+}
+
+impl ResponseBuf {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; HOLDING_RESP_MAX],
+            len: 0,
+        }
+    }
+
+    /// Returns the whole response frame, from the slave address to the CRC, or an empty slice
+    /// if no response has been received.
+    pub fn frame(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns the payload of the response: the register bytes of a read, the register and the
+    /// value echoed by a write, nothing for the reset.
+    pub fn data(&self) -> &[u8] {
+        match self.frame() {
+            [_, CMD_READ | CMD_READ_PARAM, _, data @ .., _, _] => data,
+            [_, _, data @ .., _, _] => data,
+            _ => &[],
+        }
+    }
+
+    /// Iterates over the registers of a read response.
+    pub fn registers(&self) -> impl Iterator<Item = u16> + '_ {
+        self.data()
+            .chunks_exact(2)
+            .map(|reg| u16::from_be_bytes([reg[0], reg[1]]))
+    }
+}
+
+impl Default for ResponseBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Serial, WriteError, ReadError> Pzem<Serial, Verified>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Performs a single transaction, storing the response frame into `resp`.
+    ///
+    /// Meant for the registers and the function codes the driver has no dedicated method for:
+    /// the request goes through the same retries, CRC and exception checks as the rest of the
+    /// driver. Commands the sensor can't answer within `resp` (no registers, more than
+    /// [`HOLDING_REG_MAX`](constant.HOLDING_REG_MAX.html), past the end of the address space)
+    /// or which would put the driver out of sync with the sensor are refused with
+    /// `Err(Error::InvalidCommand)` without touching the bus; the alarm threshold written is
+    /// checked like by [`set_threshold`](#method.set_threshold).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut resp = ResponseBuf::new();
+    /// pzem.execute(Command::ReadHolding { start: 0x0003, count: 2 }, &mut resp, Some((&mut tim, 2.hz())))?;
+    /// for reg in resp.registers() {
+    ///     // ...
+    /// }
+    /// ```
+    pub fn execute<Tm: Timeout>(
+        &mut self,
+        command: Command,
+        resp: &mut ResponseBuf,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        resp.len = 0;
+
+        let (function, op, reg, word) = match command {
+            Command::ReadInput { start, count } => (CMD_READ, Operation::Read, start, count),
+            Command::ReadHolding { start, count } => {
+                (CMD_READ_PARAM, Operation::ReadParam, start, count)
+            }
+            Command::WriteHolding { reg, value } => {
+                (CMD_WRITE_PARAM, Operation::WriteParam, reg, value)
+            }
+            Command::ResetEnergy => {
+                let mut buf: [u8; RESET_LEN] = [self.addr, CMD_RESET, 0, 0];
+                crc_write(self.crc, &mut buf);
+
+                self.communicate(Operation::Reset, &buf, &mut resp.buf[..RESET_LEN], timeout)?;
+                resp.len = RESET_LEN;
+                self.record(Event::EnergyReset);
+                return Ok(());
+            }
+        };
+
+        let len = match command {
+            Command::WriteHolding {
+                reg: PARAM_THRESHOLD,
+                value: 0,
+            } => return Err(Error::InvalidThreshold(ThresholdError::Zero)),
+            Command::WriteHolding {
+                reg: PARAM_THRESHOLD,
+                value,
+            } if value > THRESHOLD_MAX => {
+                return Err(Error::InvalidThreshold(ThresholdError::AboveMax))
+            }
+            Command::WriteHolding { reg, .. } if reg != PARAM_ADDR => Some(WRITE_RESP_LEN),
+            Command::WriteHolding { .. } => None,
+            _ if word == 0 || word as usize > HOLDING_REG_MAX => None,
+            _ if reg.checked_add(word - 1).is_none() => None,
+            _ => Some(3 + 2 * word as usize + 2),
+        };
+        let len = match len {
+            Some(len) => len,
+            None => {
+                log_warn!("PZEM004T {:#04x}: refused {:?}", self.addr, command);
+                return Err(Error::InvalidCommand);
+            }
+        };
+
+        let mut buf: [u8; REQ_LEN] = [
+            self.addr,         // Slave address
+            function,          // Function code
+            (reg >> 8) as u8,  // Register address high byte
+            (reg >> 0) as u8,  // Register address low byte
+            (word >> 8) as u8, // Number of registers or value high byte
+            (word >> 0) as u8, // Number of registers or value low byte
+            0,                 // CRC
+            0,                 // CRC
+        ];
+
+        crc_write(self.crc, &mut buf);
+
+        self.communicate(op, &buf, &mut resp.buf[..len], timeout)?;
+        resp.len = len;
+
+        if function == CMD_WRITE_PARAM {
+            self.check_echo(&buf[2..6], &resp.buf[2..6])
+        } else if resp.buf[2] as usize != 2 * word as usize {
+            Err(Error::PzemError)
+        } else {
+            Ok(())
+        }
+    }
+}
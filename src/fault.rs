@@ -0,0 +1,185 @@
+use hal::serial;
+use heapless::{Deque, Vec};
+
+// Longest frame kept for duplication.
+const FRAME_MAX: usize = 64;
+
+/// Rates of the faults injected by a [`FaultInjector`](struct.FaultInjector.html),
+/// each given as one in `n` on average; `None` disables the fault.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FaultRates {
+    /// Drops the received bytes.
+    pub drop_one_in: Option<u32>,
+    /// Flips a bit of the received bytes, failing the CRC check of the frame.
+    pub corrupt_one_in: Option<u32>,
+    /// Receives the frames twice, the copy following the original once the line goes idle.
+    pub duplicate_one_in: Option<u32>,
+    /// Delays the responses by [`delay_polls`](#structfield.delay_polls).
+    pub delay_one_in: Option<u32>,
+    /// Number of reads answered with `WouldBlock` before a delayed response.
+    pub delay_polls: u32,
+    /// Seed of the pseudo-random generator, making the faults reproducible.
+    pub seed: u32,
+}
+
+impl Default for FaultRates {
+    fn default() -> Self {
+        Self {
+            drop_one_in: None,
+            corrupt_one_in: None,
+            duplicate_one_in: None,
+            delay_one_in: None,
+            delay_polls: 0,
+            seed: 0x2545_f491,
+        }
+    }
+}
+
+/// Numbers of the faults injected so far.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FaultCounts {
+    pub dropped: u32,
+    pub corrupted: u32,
+    pub duplicated: u32,
+    pub delayed: u32,
+}
+
+/// Serial wrapper injecting faults into the received bytes, to test the retry and
+/// resynchronization logic reproducibly.
+///
+/// The faults are drawn from a pseudo-random generator seeded by [`FaultRates::seed`](struct.FaultRates.html#structfield.seed),
+/// so that a failing run can be replayed. Only available with the `test-support` feature.
+///
+/// # Example
+///
+/// ```ignore
+/// let rates = FaultRates { corrupt_one_in: Some(200), duplicate_one_in: Some(10), ..FaultRates::default() };
+/// let mut pzem = Pzem::new(FaultInjector::new(serial, rates), None).unwrap();
+/// for _ in 0..1000 {
+///     if pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).is_ok() {
+///         assert_eq!(m, expected);
+///     }
+/// }
+/// ```
+pub struct FaultInjector<S> {
+    inner: S,
+    rates: FaultRates,
+    rng: u32,
+    counts: FaultCounts,
+    frame: Vec<u8, FRAME_MAX>,
+    replay: Deque<u8, FRAME_MAX>,
+    delay: u32,
+    writing: bool,
+}
+
+impl<S> FaultInjector<S> {
+    /// Wraps the serial peripheral.
+    pub fn new(inner: S, rates: FaultRates) -> Self {
+        Self {
+            inner,
+            rates,
+            rng: rates.seed | 1,
+            counts: FaultCounts::default(),
+            frame: Vec::new(),
+            replay: Deque::new(),
+            delay: 0,
+            writing: false,
+        }
+    }
+
+    /// Returns the numbers of the faults injected so far.
+    pub fn counts(&self) -> &FaultCounts {
+        &self.counts
+    }
+
+    /// Returns a mutable reference to the underlying serial peripheral.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Releases the underlying serial peripheral.
+    pub fn release(self) -> S {
+        self.inner
+    }
+
+    fn next(&mut self) -> u32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng
+    }
+
+    fn roll(&mut self, one_in: Option<u32>) -> bool {
+        match one_in {
+            Some(n) if n > 0 => self.next() % n == 0,
+            _ => false,
+        }
+    }
+}
+
+impl<S: serial::Read<u8>> serial::Read<u8> for FaultInjector<S> {
+    type Error = S::Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.writing = false;
+        if self.delay > 0 {
+            self.delay -= 1;
+            return Err(nb::Error::WouldBlock);
+        }
+
+        if let Some(b) = self.replay.pop_front() {
+            return Ok(b);
+        }
+
+        loop {
+            let mut b = match self.inner.read() {
+                Err(nb::Error::WouldBlock) => {
+                    // The line went idle, ending the frame.
+                    if !self.frame.is_empty() && self.roll(self.rates.duplicate_one_in) {
+                        self.counts.duplicated = self.counts.duplicated.wrapping_add(1);
+                        for &b in self.frame.iter() {
+                            let _ = self.replay.push_back(b);
+                        }
+                    }
+                    self.frame.clear();
+                    return Err(nb::Error::WouldBlock);
+                }
+                res => res?,
+            };
+            let _ = self.frame.push(b);
+
+            if self.roll(self.rates.drop_one_in) {
+                self.counts.dropped = self.counts.dropped.wrapping_add(1);
+                continue;
+            }
+
+            if self.roll(self.rates.corrupt_one_in) {
+                self.counts.corrupted = self.counts.corrupted.wrapping_add(1);
+                b ^= 1 << (self.next() % 8);
+            }
+
+            return Ok(b);
+        }
+    }
+}
+
+impl<S: serial::Write<u8>> serial::Write<u8> for FaultInjector<S> {
+    type Error = S::Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        if !self.writing {
+            // Start of a request: decide on the delay of its response.
+            self.writing = true;
+            if self.roll(self.rates.delay_one_in) {
+                self.counts.delayed = self.counts.delayed.wrapping_add(1);
+                self.delay = self.rates.delay_polls;
+            }
+        }
+
+        self.inner.write(word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
@@ -3,25 +3,64 @@ use hal::timer;
 
 pub trait WriteBlocking {
     type Error;
-    fn write_blocking(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+    // Writes and, if `flush` is set, flushes `buf`, unless the already started `timer`
+    // expires first. Returns whether the whole buffer has been transmitted.
+    fn write_blocking<T: timer::CountDown>(
+        &mut self,
+        timer: Option<&mut T>,
+        buf: &[u8],
+        flush: bool,
+    ) -> Result<bool, Self::Error>;
 }
 
 impl<Uart: serial::Write<u8>> WriteBlocking for Uart {
     type Error = Uart::Error;
-    fn write_blocking(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
-        for i in 0..buf.len() {
-            block!(self.write(buf[i]))?;
+    fn write_blocking<T: timer::CountDown>(
+        &mut self,
+        mut timer: Option<&mut T>,
+        buf: &[u8],
+        flush: bool,
+    ) -> Result<bool, Self::Error> {
+        for &b in buf {
+            if !poll(timer.as_deref_mut(), || self.write(b))? {
+                return Ok(false);
+            }
+        }
+
+        if !flush {
+            return Ok(true);
+        }
+
+        poll(timer, || self.flush())
+    }
+}
+
+// Polls `f` until it completes, or until the already started `timer` expires.
+fn poll<T: timer::CountDown, E>(
+    mut timer: Option<&mut T>,
+    mut f: impl FnMut() -> nb::Result<(), E>,
+) -> Result<bool, E> {
+    loop {
+        match f() {
+            Ok(()) => return Ok(true),
+            Err(nb::Error::Other(e)) => return Err(e),
+            Err(nb::Error::WouldBlock) => {}
         }
 
-        Ok(())
+        if let Some(timer) = timer.as_deref_mut() {
+            if timer.wait().is_ok() {
+                return Ok(false); // timeout!
+            }
+        }
     }
 }
 
 pub trait ReadBlocking {
     type Error;
+    // Reads until `buf` is full, or until the already started `timer` expires.
     fn read_blocking<T: timer::CountDown>(
         &mut self,
-        timeout: Option<(&mut T, T::Time)>,
+        timer: Option<&mut T>,
         buf: &mut [u8],
     ) -> Result<u8, Self::Error>;
 }
@@ -30,14 +69,11 @@ impl<Uart: serial::Read<u8>> ReadBlocking for Uart {
     type Error = Uart::Error;
     fn read_blocking<T: timer::CountDown>(
         &mut self,
-        timeout: Option<(&mut T, T::Time)>,
+        timer: Option<&mut T>,
         buf: &mut [u8],
     ) -> Result<u8, Self::Error> {
         let mut i = 0;
-        if timeout.is_some() {
-            let (timer, timeout) = timeout.unwrap();
-            timer.start(timeout);
-
+        if let Some(timer) = timer {
             while i < buf.len() {
                 match timer.wait() {
                     Err(nb::Error::WouldBlock) => match self.read() {
@@ -49,7 +85,7 @@ impl<Uart: serial::Read<u8>> ReadBlocking for Uart {
                         }
                     },
                     // NOTE: the error type for wait() is Void.
-                    Err(nb::Error::Other(_)) => unreachable!(),
+                    Err(nb::Error::Other(e)) => void::unreachable(e),
                     Ok(()) => break, // timeout!
                 }
             }
@@ -66,17 +102,39 @@ impl<Uart: serial::Read<u8>> ReadBlocking for Uart {
 
 pub trait Drain {
     type Error;
-    fn drain(&mut self) -> Result<(), Self::Error>;
+    // Discards the input queue until it is empty, `max` bytes have been discarded,
+    // or the already started `timer` expires. Returns the number of discarded bytes
+    // and whether the queue has been emptied.
+    fn drain<T: timer::CountDown>(
+        &mut self,
+        timer: Option<&mut T>,
+        max: Option<u32>,
+    ) -> Result<(u32, bool), Self::Error>;
 }
 
 impl<Uart: serial::Read<u8>> Drain for Uart {
     type Error = Uart::Error;
-    fn drain(&mut self) -> Result<(), Self::Error> {
+    fn drain<T: timer::CountDown>(
+        &mut self,
+        mut timer: Option<&mut T>,
+        max: Option<u32>,
+    ) -> Result<(u32, bool), Self::Error> {
+        let mut n: u32 = 0;
         loop {
             match self.read() {
-                Err(nb::Error::WouldBlock) => return Ok(()),
+                Err(nb::Error::WouldBlock) => return Ok((n, true)),
                 Err(nb::Error::Other(e)) => return Err(e),
-                Ok(_) => continue,
+                Ok(_) => n = n.wrapping_add(1),
+            }
+
+            if max.is_some_and(|max| n >= max) {
+                return Ok((n, false));
+            }
+
+            if let Some(timer) = timer.as_deref_mut() {
+                if timer.wait().is_ok() {
+                    return Ok((n, false)); // timeout!
+                }
             }
         }
     }
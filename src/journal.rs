@@ -0,0 +1,248 @@
+use core::fmt::Write;
+
+use crate::codec::HOLDING_RESP_MAX;
+
+/// Direction of a frame on the bus, as seen from the driver.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// Request sent to the sensor.
+    Tx,
+    /// Bytes received from the sensor, possibly a truncated or corrupted response.
+    Rx,
+}
+
+/// Frame passed to a [`Journal`](trait.Journal.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// Value of the clock passed to [`Pzem::with_journal`](struct.Pzem.html#method.with_journal)
+    /// when the first byte of the frame was sent or received.
+    pub tick: u32,
+    pub direction: Direction,
+    pub bytes: &'a [u8],
+}
+
+/// Destination of every frame sent and received by the driver, e.g. a capture file on an SD
+/// card or an RTT channel, for offline analysis of the bus.
+///
+/// Look [`VcdWriter`](struct.VcdWriter.html) for the captures loadable into sigrok/PulseView.
+pub trait Journal: Send {
+    fn record(&mut self, frame: Frame<'_>);
+}
+
+// Journal attached to the driver, collecting the received bytes into a frame.
+pub(crate) struct JournalHook {
+    sink: &'static mut dyn Journal,
+    clock: fn() -> u32,
+    rx: heapless::Vec<u8, HOLDING_RESP_MAX>,
+    rx_tick: u32,
+}
+
+impl JournalHook {
+    pub(crate) fn new(sink: &'static mut dyn Journal, clock: fn() -> u32) -> Self {
+        Self {
+            sink,
+            clock,
+            rx: heapless::Vec::new(),
+            rx_tick: 0,
+        }
+    }
+
+    pub(crate) fn sent(&mut self, bytes: &[u8]) {
+        self.sink.record(Frame {
+            tick: (self.clock)(),
+            direction: Direction::Tx,
+            bytes,
+        });
+    }
+
+    pub(crate) fn received(&mut self, bytes: &[u8]) {
+        if self.rx.is_empty() && !bytes.is_empty() {
+            self.rx_tick = (self.clock)();
+        }
+        // The responses fit, except for the garbage of a broken exchange.
+        let _ = self.rx.extend_from_slice(bytes);
+    }
+
+    // Records the bytes received since the last call as a single frame.
+    pub(crate) fn end_rx(&mut self) {
+        if !self.rx.is_empty() {
+            self.sink.record(Frame {
+                tick: self.rx_tick,
+                direction: Direction::Rx,
+                bytes: &self.rx,
+            });
+            self.rx.clear();
+        }
+    }
+}
+
+/// Journal writing the frames as the UART waveforms of a Value Change Dump, the format
+/// imported by sigrok (`sigrok-cli -I vcd -P uart:baudrate=9600`) and PulseView.
+///
+/// The requests are dumped on the `tx` wire and the responses on the `rx` wire, 8N1 at the
+/// given baud rate. The frames start at their ticks, converted to µs with `tick_hz`, or right
+/// after the previous frame if it hasn't ended yet. As `record` can't fail, the formatting
+/// errors (e.g. a full buffer) are remembered and reported by [`take_error`](#method.take_error).
+///
+/// # Example
+/// ```ignore
+/// static mut VCD: Option<VcdWriter<SdFile>> = None;
+///
+/// let vcd = unsafe { VCD.insert(VcdWriter::new(SdFile::create("bus.vcd"), 1000, 9600)) };
+/// let mut pzem = Pzem::new(serial, None).unwrap().with_journal(vcd, millis);
+/// ```
+pub struct VcdWriter<W> {
+    w: W,
+    tick_hz: u32,
+    baud: u32,
+    header: bool,
+    // Time of the last value change and the end of the last frame, in µs.
+    stamp: u64,
+    end: u64,
+    error: bool,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Creates the writer; the header is written along with the first frame.
+    pub fn new(w: W, tick_hz: u32, baud: u32) -> Self {
+        Self {
+            w,
+            tick_hz,
+            baud,
+            header: false,
+            stamp: 0,
+            end: 0,
+            error: false,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Releases the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Returns `Err` if writing any frame failed since the last call.
+    pub fn take_error(&mut self) -> core::fmt::Result {
+        if core::mem::take(&mut self.error) {
+            Err(core::fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_frame(&mut self, frame: Frame<'_>) -> core::fmt::Result {
+        if !self.header {
+            self.w.write_str(
+                "$timescale 1 us $end\n\
+                 $scope module pzem004t $end\n\
+                 $var wire 1 t tx $end\n\
+                 $var wire 1 r rx $end\n\
+                 $upscope $end\n\
+                 $enddefinitions $end\n\
+                 #0\n1t\n1r\n",
+            )?;
+            self.header = true;
+        }
+
+        let id = match frame.direction {
+            Direction::Tx => 't',
+            Direction::Rx => 'r',
+        };
+        let tick = frame.tick as u64 * 1_000_000 / self.tick_hz.max(1) as u64;
+        let start = tick.max(self.end);
+        let baud = self.baud.max(1) as u64;
+        let bit = |k: u64| start + k * 1_000_000 / baud;
+
+        // Start bit, 8 data bits from the least significant, stop bit.
+        let mut level = 1;
+        let mut k = 0;
+        for &b in frame.bytes {
+            let bits = 1u16 << 9 | (b as u16) << 1;
+            for i in 0..10 {
+                let next = (bits >> i) as u8 & 1;
+                if next != level {
+                    let time = bit(k);
+                    if time != self.stamp {
+                        writeln!(self.w, "#{}", time)?;
+                        self.stamp = time;
+                    }
+                    writeln!(self.w, "{}{}", next, id)?;
+                    level = next;
+                }
+                k += 1;
+            }
+        }
+        self.end = bit(k);
+
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Journal for VcdWriter<W> {
+    fn record(&mut self, frame: Frame<'_>) {
+        if self.write_frame(frame).is_err() {
+            self.error = true;
+        }
+    }
+}
+
+/// Journal writing the frames as a text capture, a line per frame with the tick, the
+/// direction and the bytes in hex, e.g. `1200 tx 01 04 00 00 00 0a 70 0d`.
+///
+/// Small enough to be pasted into a bug report, the capture is played back by
+/// [`Replay`](struct.Replay.html) (`test-support` feature). As `record` can't fail, the
+/// formatting errors are remembered and reported by [`take_error`](#method.take_error).
+pub struct CaptureWriter<W> {
+    w: W,
+    error: bool,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(w: W) -> Self {
+        Self { w, error: false }
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Releases the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Returns `Err` if writing any frame failed since the last call.
+    pub fn take_error(&mut self) -> core::fmt::Result {
+        if core::mem::take(&mut self.error) {
+            Err(core::fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write_frame(&mut self, frame: Frame<'_>) -> core::fmt::Result {
+        let dir = match frame.direction {
+            Direction::Tx => "tx",
+            Direction::Rx => "rx",
+        };
+        write!(self.w, "{} {}", frame.tick, dir)?;
+        for b in frame.bytes {
+            write!(self.w, " {:02x}", b)?;
+        }
+        self.w.write_char('\n')
+    }
+}
+
+impl<W: Write + Send> Journal for CaptureWriter<W> {
+    fn record(&mut self, frame: Frame<'_>) {
+        if self.write_frame(frame).is_err() {
+            self.error = true;
+        }
+    }
+}
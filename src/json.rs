@@ -0,0 +1,62 @@
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::Measurement;
+
+// Number with the given count of decimals, `null` if not finite, which JSON can't represent.
+struct Number(f32, usize);
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0.is_finite() {
+            true => write!(f, "{:.*}", self.1, self.0),
+            false => write!(f, "null"),
+        }
+    }
+}
+
+impl Measurement {
+    /// Serializes the measurement into `s` as a compact JSON object with a fixed schema.
+    ///
+    /// The values which aren't finite, e.g. the power factor of a scaled measurement, are
+    /// written as `null`. The previous contents of `s` are cleared. Returns
+    /// `Err(core::fmt::Error)` if the object doesn't fit into the string capacity; 128 bytes
+    /// are sufficient for the values measured by the sensor, not necessarily for the scaled
+    /// or corrected ones.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut s = heapless::String::<128>::new();
+    /// m.to_json(&mut s).unwrap();
+    /// // {"voltage":230.1,"current":1.234,"power":283.9,"energy":12.345,"frequency":50.0,"pf":0.99,"alarm":false}
+    /// mqtt.publish("pzem/state", s.as_bytes());
+    /// ```
+    pub fn to_json<const N: usize>(&self, s: &mut heapless::String<N>) -> fmt::Result {
+        s.clear();
+        self.write_json(s)
+    }
+
+    fn write_json<W: Write>(&self, w: &mut W) -> fmt::Result {
+        write!(
+            w,
+            "{{\"voltage\":{},\"current\":{},\"power\":{},\"energy\":{},\
+             \"frequency\":{},\"pf\":{},\"alarm\":{}}}",
+            Number(self.voltage, 1),
+            Number(self.current, 3),
+            Number(self.power, 1),
+            Number(self.energy, 3),
+            Number(self.frequency, 1),
+            Number(self.pf, 2),
+            self.alarm
+        )
+    }
+
+    /// Serializes the measurement into a newly allocated string, look [`to_json`](#method.to_json).
+    #[cfg(feature = "alloc")]
+    pub fn to_json_string(&self) -> alloc::string::String {
+        let mut s = alloc::string::String::new();
+        // Writing into a `String` can't fail.
+        let _ = self.write_json(&mut s);
+        s
+    }
+}
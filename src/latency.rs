@@ -0,0 +1,52 @@
+use crate::codec::{REQ_LEN, RESET_LEN, WRITE_MULTI_REQ_LEN};
+use crate::{Backoff, Operation};
+
+/// Returns the time in microseconds it takes to transmit `bytes` at `baud` with 8N1 framing
+/// (10 bits per byte), rounded up.
+pub const fn frame_time_us(bytes: u32, baud: u32) -> u32 {
+    let bits = bytes as u64 * 10 * 1_000_000;
+    let us = bits.div_ceil(baud as u64);
+    if us > u32::MAX as u64 {
+        u32::MAX
+    } else {
+        us as u32
+    }
+}
+
+/// Returns the worst-case duration in microseconds a single operation `op` may block for,
+/// given the baud rate, the timeout of the operation and the backoff policy.
+///
+/// Every attempt may spend up to the timeout draining the input queue, then transmits the
+/// request and waits up to the timeout for the response. Each retry of the backoff policy
+/// adds the maximum number of slots (periods of the timeout) and another attempt. The result
+/// saturates at `u32::MAX`.
+///
+/// Operations without a timeout ([`NoTimeout`](struct.NoTimeout.html)) are unbounded.
+/// The methods performing several operations add up accordingly: e.g. `set_addr` reads the
+/// address back, and `WriteMode::Auto` may repeat the write.
+///
+/// # Example
+/// ```
+/// use pzem004t::{worst_case_us, Backoff, Operation};
+///
+/// const READ_US: u32 = worst_case_us(Operation::Read, 9600, 100_000, Backoff::none());
+/// assert!(READ_US <= 250_000);
+/// ```
+pub const fn worst_case_us(op: Operation, baud: u32, timeout_us: u32, backoff: Backoff) -> u32 {
+    let req_len = match op {
+        Operation::Read | Operation::ReadParam => REQ_LEN,
+        Operation::WriteParam => WRITE_MULTI_REQ_LEN,
+        Operation::Reset => RESET_LEN,
+    };
+
+    let attempt = 2 * timeout_us as u64 + frame_time_us(req_len as u32, baud) as u64;
+    let retries = backoff.max_retries() as u64;
+    let slots = retries * backoff.max_slots() as u64;
+
+    let us = (retries + 1) * attempt + slots * timeout_us as u64;
+    if us > u32::MAX as u64 {
+        u32::MAX
+    } else {
+        us as u32
+    }
+}
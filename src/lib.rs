@@ -11,27 +11,78 @@
 //!
 //! ## Read the measurements off the sensor every second
 //!
-//!     let mut pzem = pzem004t::Pzem::new(serial, None).unwrap();
-//!     let mut m = pzem004t::Measurement::default();
-//!     loop {
-//!         match pzem.read(&mut m, Some((&mut tim, TIMEOUT))) {
-//!             Err(e) => println!("Could not read PZEM004T: {}", e);
-//!             Ok(()) => {
-//!                 println!("Voltage: {:.1} V", m.voltage);
-//!                 println!("Current: {:.3} A", m.current);
-//!                 println!("Power: {:.1} W", m.power);
-//!                 println!("Energy: {:.3} kWh", m.energy);
-//!                 println!("Frequency: {:.1} Hz", m.frequency);
-//!                 println!("Power factor: {:.2}", m.pf);
-//!                 println!("Alarm: {}\n", m.alarm);
-//!             }
+//! ```ignore
+//! let mut pzem = pzem004t::Pzem::new(serial, None).unwrap();
+//! let mut m = pzem004t::Measurement::default();
+//! loop {
+//!     match pzem.read(&mut m, Some((&mut tim, TIMEOUT))) {
+//!         Err(e) => println!("Could not read PZEM004T: {}", e),
+//!         Ok(()) => {
+//!             println!("Voltage: {:.1} V", m.voltage);
+//!             println!("Current: {:.3} A", m.current);
+//!             println!("Power: {:.1} W", m.power);
+//!             println!("Energy: {:.3} kWh", m.energy);
+//!             println!("Frequency: {:.1} Hz", m.frequency);
+//!             println!("Power factor: {:.2}", m.pf);
+//!             println!("Alarm: {}\n", m.alarm);
 //!         }
-//!
-//!         tim.start(1.hz());
-//!         block!(tim.wait()).unwrap();
 //!     }
+//!
+//!     tim.start(1.hz());
+//!     block!(tim.wait()).unwrap();
+//! }
+//! ```
+//!
+//! # Cargo features
+//!
+//! - `log`: emit debug-level logs of the decoded measurements and warn-level logs on
+//!   protocol errors through the [`log`](https://crates.io/crates/log) crate.
+//! - `critical-section`: implements [`BusMutex`](trait.BusMutex.html) for the mutex of the
+//!   [`critical-section`](https://crates.io/crates/critical-section) crate, to share the driver
+//!   among tasks with [`SharedBus`](struct.SharedBus.html).
+//! - `fugit`: accepts the [`fugit`](https://crates.io/crates/fugit) durations as the timeouts,
+//!   `pzem.read(&mut m, (&mut tim, 200.millis()))`, converted into the `Time` unit of the
+//!   timer, look [`FromDuration`](trait.FromDuration.html).
+//! - `unchecked-frames`: provides [`CrcMode::Unchecked`](enum.CrcMode.html#variant.Unchecked),
+//!   accepting the frames failing the CRC check for the bench bring-up. Never enable it in production.
+//! - `json`: provides [`Measurement::to_json`](struct.Measurement.html#method.to_json),
+//!   serializing the measurement into a `heapless::String` without allocating.
+//! - `mqtt`: provides [`Measurement::to_mqtt_topics`](struct.Measurement.html#method.to_mqtt_topics),
+//!   emitting per-field topic/payload pairs in the ESPHome/Tasmota layout without allocating.
+//! - `no-float`: removes all the floating point code, including [`Measurement`](struct.Measurement.html)
+//!   and everything built upon it (bus polling, JSON and CSV formatting, sensor traits, validation),
+//!   leaving the raw integer API ([`read_raw`](struct.Pzem.html#method.read_raw),
+//!   [`RawMeasurement`](struct.RawMeasurement.html)). This avoids linking the soft-float routines
+//!   on targets without an FPU.
+//! - `size-opt`: minimizes the code footprint for the flash-constrained targets. Guaranteed
+//!   to remove exactly the following, and nothing else:
+//!   - the `Display` impls of [`Error`](enum.Error.html) and [`Exception`](enum.Exception.html),
+//!     along with their message strings; format them with `Debug` if needed;
+//!   - the CSV formatting of the measurements, [`Measurement::write_csv`](struct.Measurement.html#method.write_csv)
+//!     and `CsvWriter`; the formats requested explicitly by `json` and `mqtt` stay;
+//!   - the communication statistics: `Stats`, `PhaseTimes`, [`Pzem::stats`](struct.Pzem.html#method.stats),
+//!     `reset_stats`, `with_phase_clock` and [`DeviceSnapshot::stats`](struct.DeviceSnapshot.html#structfield.stats).
+//!     The event log is unaffected.
+//! - `alloc`: for targets with a heap but no operating system, provides the conveniences
+//!   built upon `Vec` and `String`: [`DynPoller`](struct.DynPoller.html) over a set of slaves
+//!   changing at runtime, [`scan_bus_all`](struct.Pzem.html#method.scan_bus_all) and, along
+//!   with `json`, [`Measurement::to_json_string`](struct.Measurement.html#method.to_json_string).
+//! - `test-support`: provides the [`test_vectors`](test_vectors/index.html) module with the
+//!   canonical frames of the protocol, to validate the alternate transports against, and the
+//!   [`FaultInjector`](struct.FaultInjector.html) for the robustness tests, and the
+//!   [`Replay`](struct.Replay.html) of the captures written by [`CaptureWriter`](struct.CaptureWriter.html).
+//! - `std`: implies `alloc`; links the standard library and provides the [`StdIo`](struct.StdIo.html) adapter
+//!   over any `std::io::Read + Write` stream, along with the [`StdTimer`](struct.StdTimer.html),
+//!   the [`Reconnecting`](struct.Reconnecting.html) stream reopened on errors and the
+//!   [`PzemEmulator`](struct.PzemEmulator.html) of the sensor.
+//! - `async`: provides the [`WebSerial`](struct.WebSerial.html) driver awaiting an
+//!   [`AsyncPort`](trait.AsyncPort.html), along with the [`BlockingAdapter`](struct.BlockingAdapter.html)
+//!   running it from the blocking code.
+//! - `wasm`: implies `std` and `async`, for the browsers talking to the sensor through the
+//!   Web Serial API.
 
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::identity_op)]
 
 extern crate crc16;
 extern crate embedded_hal as hal;
@@ -39,16 +90,225 @@ extern crate embedded_hal as hal;
 #[macro_use(block)]
 extern crate nb;
 
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+
 mod io;
 use io::*;
 
+pub mod decode;
+pub mod regs;
+pub use decode::{DecodeError, FromRegisters};
+pub mod prelude;
+#[cfg(feature = "test-support")]
+pub mod test_vectors;
+
+#[cfg(feature = "test-support")]
+mod fault;
+#[cfg(feature = "test-support")]
+pub use fault::{FaultCounts, FaultInjector, FaultRates};
+
+#[cfg(feature = "test-support")]
+mod replay;
+#[cfg(feature = "test-support")]
+pub use replay::{Replay, ReplayError, ReplayTimer};
+
+mod raw;
+pub use raw::RawMeasurement;
+
+mod codec;
+use codec::*;
+pub use codec::{modbus_crc, CrcProvider, SoftwareCrc};
+
 mod no_timeout;
 pub use no_timeout::NoTimeout;
 
-use core::fmt::Display;
-use core::fmt::Formatter;
+mod dyn_count_down;
+pub use dyn_count_down::{DynCountDown, ErasedCountDown};
+
+mod timeout;
+pub use timeout::{Operation, Session, Timeout, Timeouts};
+
+mod tick_timeout;
+pub use tick_timeout::{AdaptiveTimeout, TickTimeout, TickTimer};
+
+#[cfg(feature = "fugit")]
+mod duration;
+#[cfg(feature = "fugit")]
+pub use duration::FromDuration;
+
+#[cfg(not(any(feature = "no-float", feature = "size-opt")))]
+mod csv;
+
+mod config;
+pub use config::{ApplyError, ApplyStep, Config};
+
+#[cfg(not(feature = "no-float"))]
+mod delta;
+#[cfg(not(feature = "no-float"))]
+pub use delta::MeasurementDelta;
+
+#[cfg(not(feature = "no-float"))]
+mod energy;
+#[cfg(not(feature = "no-float"))]
+pub use energy::{DailyEnergy, Date, Rtc, ENERGY_MAX};
+
+mod backoff;
+pub use backoff::Backoff;
+
+mod latency;
+pub use latency::{frame_time_us, worst_case_us};
+
+mod stats;
+use stats::PhaseTimer;
+#[cfg(not(feature = "size-opt"))]
+pub use stats::{PhaseTimes, Stats};
+
+mod doctor;
+pub use doctor::{Diagnosis, LinkReport, SelfTestReport};
+
+mod ready;
+pub use ready::{READY_ATTEMPTS, READY_INTERVAL};
+
+mod cancel;
+pub use cancel::Cancel;
+use cancel::Cancellable;
+
+mod conflict;
+
+mod snapshot;
+pub use snapshot::{DebugState, DeviceParams, DeviceSnapshot};
+
+mod execute;
+pub use execute::{Command, ResponseBuf};
+
+mod outcome;
+use outcome::ExchangeInfo;
+pub use outcome::ReadOutcome;
+
+mod events;
+use events::EventHook;
+pub use events::{Event, EventLog, EventRecord, EventSink};
+
+mod journal;
+use journal::JournalHook;
+pub use journal::{CaptureWriter, Direction, Frame, Journal, VcdWriter};
+
+#[cfg(not(feature = "no-float"))]
+mod bus;
+#[cfg(not(feature = "no-float"))]
+pub use bus::{AddrResult, BusResults, InterleavedScheduler, Poller, ScanIter};
+
+#[cfg(all(feature = "alloc", not(feature = "no-float")))]
+mod alloc_bus;
+#[cfg(all(feature = "alloc", not(feature = "no-float")))]
+pub use alloc_bus::DynPoller;
+
+#[cfg(all(feature = "json", not(feature = "no-float")))]
+mod json;
+
+#[cfg(all(feature = "mqtt", not(feature = "no-float")))]
+mod mqtt;
+
+#[cfg(not(feature = "no-float"))]
+mod sensor;
+#[cfg(not(feature = "no-float"))]
+pub use sensor::{EnergyMeter, PowerReading, PowerSensor, Sensor};
+
+#[cfg(not(feature = "no-float"))]
+mod scaling;
+#[cfg(not(feature = "no-float"))]
+pub use scaling::{Correction, Scaling};
+
+#[cfg(not(feature = "no-float"))]
+mod burst;
+#[cfg(not(feature = "no-float"))]
+pub use burst::{BurstStats, FieldStats};
+
+#[cfg(not(feature = "no-float"))]
+mod crosscheck;
+#[cfg(not(feature = "no-float"))]
+pub use crosscheck::{Discrepancy, EsphomeReference, Field};
+
+#[cfg(not(feature = "no-float"))]
+mod power;
+#[cfg(not(feature = "no-float"))]
+pub use power::CURRENT_MIN;
+
+#[cfg(not(feature = "no-float"))]
+mod alarm;
+#[cfg(not(feature = "no-float"))]
+pub use alarm::AlarmHysteresis;
+
+#[cfg(not(feature = "no-float"))]
+mod quality;
+#[cfg(not(feature = "no-float"))]
+pub use quality::{EventDetector, FrequencyMonitor, VoltageEvent};
+
+#[cfg(not(feature = "no-float"))]
+mod sink;
+#[cfg(not(any(feature = "no-float", feature = "size-opt")))]
+pub use sink::CsvWriter;
+#[cfg(not(feature = "no-float"))]
+pub use sink::{Aggregator, Broadcast, History, MeasurementSink, Subscription};
+
+#[cfg(not(feature = "no-float"))]
+mod validate;
+#[cfg(not(feature = "no-float"))]
+pub use validate::Validator;
+
+mod sniffer;
+pub use sniffer::{Sniffed, Sniffer};
+
+mod compat;
+pub use compat::{ByteStream, Compat};
+
+mod serial_ref;
+pub use serial_ref::SerialRef;
+
+mod shared;
+pub use shared::{BusMutex, SharedBus};
+
+mod ring_buffer;
+pub use ring_buffer::{Consumer, Producer, RingBuffer, RingBufferRx};
+
+#[cfg(feature = "std")]
+mod std_io;
+#[cfg(feature = "std")]
+pub use std_io::{LineConfig, Parity, StdIo, StdTimer};
+
+#[cfg(feature = "std")]
+mod reconnect;
+#[cfg(feature = "std")]
+pub use reconnect::{LinkStatus, Reconnecting, RetryPolicy};
+
+#[cfg(feature = "std")]
+mod emulator;
+#[cfg(feature = "std")]
+pub use emulator::{EmulatedSlave, Faults, PzemEmulator};
+
+#[cfg(feature = "async")]
+mod web_serial;
+#[cfg(feature = "async")]
+pub use web_serial::{AsyncPort, WebSerial};
+
+#[cfg(feature = "async")]
+mod blocking;
+#[cfg(feature = "async")]
+pub use blocking::BlockingAdapter;
+
+#[cfg(not(feature = "size-opt"))]
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
 use hal::serial;
 use hal::timer;
+use hal::timer::CountDown;
 
 const ADDR_DEFAULT: u8 = 0xf8; // Universal address for single-slave environment
 const ADDR_MIN: u8 = 0x01;
@@ -59,75 +319,244 @@ const CMD_RESET: u8 = 0x42; // Reset the energy counter
 
 const CMD_READ_PARAM: u8 = 0x03; // Read the slave parameters
 const CMD_WRITE_PARAM: u8 = 0x06; // Write the slave parameters
+const CMD_WRITE_MULTI: u8 = 0x10; // Write the slave parameters, multiple registers variant
+
+const EXCEPTION_FLAG: u8 = 0x80; // Set in the function code of the exception responses
+
+const PARAM_THRESHOLD: u16 = regs::THRESHOLD.addr;
+const PARAM_ADDR: u16 = regs::ADDRESS.addr;
+
+/// Number of the measurement registers read by the driver, from the voltage to the alarm
+/// status: the count field of the read request.
+pub const REG_COUNT: u16 = regs::ALARM.addr + regs::ALARM.size as u16;
 
-const PARAM_THRESHOLD: u16 = 0x0001; // Power alarm threshold
-const PARAM_ADDR: u16 = 0x0002; // Modbus-RTU address
+/// Length of the requests reading the measurements or a parameter and writing a parameter,
+/// from the slave address to the CRC. The energy reset is shorter, 4 bytes.
+pub const REQUEST_LEN: usize = codec::REQ_LEN;
 
-const REG_COUNT: u16 = 10; // 10 registers in total
+/// Length of the response frame to the measurement read of a genuine sensor; size the
+/// buffers receiving the clones too for [`READ_FRAME_MAX`](constant.READ_FRAME_MAX.html).
+pub const READ_RESPONSE_LEN: usize = codec::READ_RESP_LEN;
+
+/// Length of the response frame to a parameter read (the alarm threshold or the address).
+pub const PARAM_RESPONSE_LEN: usize = codec::PARAM_RESP_LEN;
+
+/// Length of the response frame to the measurement read, look [`Pzem::read_with_raw`](struct.Pzem.html#method.read_with_raw).
+/// Same as [`READ_RESPONSE_LEN`](constant.READ_RESPONSE_LEN.html).
+pub const READ_FRAME_LEN: usize = READ_RESPONSE_LEN;
+
+/// Maximum number of the measurement registers accepted in the response. Some clone firmwares
+/// answer with 11 or 12 registers instead of 10; the extra ones are ignored.
+pub const READ_REG_MAX: usize = 12;
+
+/// Length of the longest response frame to the measurement read accepted, from the clones
+/// answering [`READ_REG_MAX`](constant.READ_REG_MAX.html) registers.
+pub const READ_FRAME_MAX: usize = codec::READ_RESP_MAX;
+
+/// Maximum number of holding registers fetched by [`Pzem::read_holding_registers`](struct.Pzem.html#method.read_holding_registers).
+pub const HOLDING_REG_MAX: usize = 16;
+
+/// Highest power alarm threshold in W accepted by [`Pzem::set_threshold`](struct.Pzem.html#method.set_threshold):
+/// the full scale of the 100 A model and the factory default.
+///
+/// The power can't reach it in practice, hence it is the value written by
+/// [`Pzem::disable_alarm`](struct.Pzem.html#method.disable_alarm).
+pub const THRESHOLD_MAX: u16 = 23_000;
+
+/// Clamps the power alarm threshold in W into the range accepted by
+/// [`Pzem::set_threshold`](struct.Pzem.html#method.set_threshold), `1..=THRESHOLD_MAX`.
+///
+/// ```
+/// use pzem004t::{clamp_threshold, THRESHOLD_MAX};
+///
+/// assert_eq!(clamp_threshold(0), 1);
+/// assert_eq!(clamp_threshold(2300), 2300);
+/// assert_eq!(clamp_threshold(50_000), THRESHOLD_MAX);
+/// ```
+pub const fn clamp_threshold(watts: u32) -> u16 {
+    match watts {
+        0 => 1,
+        w if w > THRESHOLD_MAX as u32 => THRESHOLD_MAX,
+        w => w as u16,
+    }
+}
+
+/// Power alarm threshold refused by [`Pzem::set_threshold`](struct.Pzem.html#method.set_threshold).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThresholdError {
+    /// Zero, which some firmware versions treat as the alarm always on. Use
+    /// [`Pzem::disable_alarm`](struct.Pzem.html#method.disable_alarm) to turn the alarm off.
+    Zero,
+    /// Above [`THRESHOLD_MAX`](constant.THRESHOLD_MAX.html).
+    AboveMax,
+}
+
+/// Exception codes the sensor may respond with instead of the regular response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Exception {
+    IllegalFunction,
+    IllegalAddress,
+    IllegalData,
+    SlaveError,
+    Other(u8),
+}
+
+impl From<u8> for Exception {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => Exception::IllegalFunction,
+            0x02 => Exception::IllegalAddress,
+            0x03 => Exception::IllegalData,
+            0x04 => Exception::SlaveError,
+            code => Exception::Other(code),
+        }
+    }
+}
+
+#[cfg(not(feature = "size-opt"))]
+impl Display for Exception {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        match self {
+            Exception::IllegalFunction => write!(f, "illegal function"),
+            Exception::IllegalAddress => write!(f, "illegal address"),
+            Exception::IllegalData => write!(f, "illegal data"),
+            Exception::SlaveError => write!(f, "slave error"),
+            Exception::Other(code) => write!(f, "exception code {:#04x}", code),
+        }
+    }
+}
+
+/// Function used for writing the parameters of the sensor.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Write Single Register (`0x06`), as documented for PZEM004T. This is the default.
+    #[default]
+    Single,
+    /// Write Multiple Registers (`0x10`), required by some clones.
+    Multiple,
+    /// Write Single Register, falling back to Write Multiple Registers for good
+    /// if the sensor responds with the illegal function exception.
+    Auto,
+}
+
+/// Handling of the response frames failing the CRC check.
+#[derive(Debug, Default, Copy, Clone)]
+pub enum CrcMode {
+    /// Corrupted frames are dropped silently. This is the default.
+    #[default]
+    Strict,
+    /// Corrupted frames are passed to the callback before the operation
+    /// fails with `Err(Error::CrcMismatch)`, e.g. for logging the bus corruption.
+    Lenient(fn(&[u8])),
+    /// Corrupted frames are passed to the callback and then accepted as if they were valid,
+    /// still counted in [`Stats::crc_errors`](struct.Stats.html#structfield.crc_errors).
+    ///
+    /// Meant for the bench bring-up only, when the data can be seen arriving but marginal signal
+    /// integrity fails every read: the measurements decoded from these frames may be garbage.
+    /// Requires the `unchecked-frames` feature.
+    #[cfg(feature = "unchecked-frames")]
+    Unchecked(fn(&[u8])),
+}
+
+/// Likely address of the sensor after a failed [`Pzem::set_addr`](struct.Pzem.html#method.set_addr).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddrState {
+    /// The sensor still answers on the old address, which the driver has reverted to.
+    Unchanged,
+    /// The sensor answers neither on the old nor on the new address.
+    Unknown,
+}
+
+/// Change of the sensor address reported to the hook attached by
+/// [`Pzem::with_addr_hook`](struct.Pzem.html#method.with_addr_hook).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AddrChange {
+    pub old: u8,
+    pub new: u8,
+}
 
 /// Errors which can occur when attempting to communicate with PZEM004T sensor.
+///
+/// `Display` is implemented regardless of the serial error types, many of which implement
+/// `Debug` only: the errors of the serial peripheral are described by a generic message,
+/// match on `WriteError` and `ReadError` for the details.
 #[derive(Debug, Clone)]
 pub enum Error<WriteError, ReadError> {
-    TimedOut,
+    /// The request or the response didn't make it in time. The number of response bytes
+    /// received tells a silent sensor (none) from a truncated frame.
+    TimedOut {
+        received: u8,
+    },
     CrcMismatch,
     PzemError,
     IllegalAddress,
+    LineNoise,
+    Exception(Exception),
+    WriteVerificationFailed,
+    ResetFailed,
+    Implausible,
+    BusBusy,
+    AddrChangeFailed(AddrState),
+    InvalidThreshold(ThresholdError),
+    /// The responses keep failing the CRC check in the way of several sensors answering
+    /// at the same address.
+    ProbableAddressConflict,
+    /// The transaction has been cancelled, look [`Cancel`](struct.Cancel.html).
+    Cancelled,
+    /// The response passed the CRC check, but doesn't decode into the measurements.
+    Decode(DecodeError),
+    /// The command passed to [`Pzem::execute`](struct.Pzem.html#method.execute) has been refused.
+    InvalidCommand,
+    /// More slave addresses than the capacity of the [`Poller`](struct.Poller.html).
+    TooManyAddresses,
     WriteError(WriteError),
     ReadError(ReadError),
 }
 
-impl<WriteError: Display, ReadError: Display> Display for Error<WriteError, ReadError> {
+#[cfg(not(feature = "size-opt"))]
+impl<WriteError, ReadError> Display for Error<WriteError, ReadError> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
         match self {
-            Error::TimedOut => write!(f, "Communication timed out"),
+            Error::TimedOut { received: 0 } => write!(f, "Communication timed out"),
+            Error::TimedOut { received } => {
+                write!(f, "Communication timed out, {} bytes received", received)
+            }
             Error::CrcMismatch => write!(f, "CRC doesn't match"),
             Error::PzemError => write!(f, "Internal PZEM004T error"),
             Error::IllegalAddress => write!(f, "Illegal address"),
-            Error::WriteError(e) => write!(f, "Could not write: {}", e),
-            Error::ReadError(e) => write!(f, "Could not read: {}", e),
+            Error::LineNoise => write!(f, "Line noise detected"),
+            Error::Exception(e) => write!(f, "PZEM004T responded with {}", e),
+            Error::WriteVerificationFailed => write!(f, "Written value doesn't match"),
+            Error::ResetFailed => write!(f, "Energy counter didn't reset"),
+            Error::Implausible => write!(f, "Implausible measurement"),
+            Error::BusBusy => write!(f, "Bus is busy"),
+            Error::AddrChangeFailed(AddrState::Unchanged) => {
+                write!(f, "Address change failed, sensor on the old address")
+            }
+            Error::AddrChangeFailed(AddrState::Unknown) => {
+                write!(f, "Address change failed, sensor address unknown")
+            }
+            Error::InvalidThreshold(ThresholdError::Zero) => {
+                write!(f, "Zero alarm threshold, disable the alarm instead")
+            }
+            Error::InvalidThreshold(ThresholdError::AboveMax) => {
+                write!(f, "Alarm threshold above {} W", THRESHOLD_MAX)
+            }
+            Error::ProbableAddressConflict => {
+                write!(f, "Several sensors probably share the address")
+            }
+            Error::Cancelled => write!(f, "Transaction cancelled"),
+            Error::Decode(e) => write!(f, "Undecodable response, {}", e),
+            Error::InvalidCommand => write!(f, "Invalid command"),
+            Error::TooManyAddresses => write!(f, "Too many slave addresses"),
+            Error::WriteError(_) => write!(f, "Could not write to the serial port"),
+            Error::ReadError(_) => write!(f, "Could not read from the serial port"),
         }
     }
 }
 
-// 16-bit cyclic redundancy check (CRC).
-fn crc_write(buf: &mut [u8]) {
-    let n = buf.len();
-    let crc = u16::to_be(crc16::State::<crc16::MODBUS>::calculate(&mut buf[0..n - 2]));
-
-    buf[n - 2] = (crc >> 8) as u8;
-    buf[n - 1] = (crc >> 0) as u8;
-}
-
-fn crc_check(buf: &[u8]) -> bool {
-    let n = buf.len();
-    let crc = u16::from_be(crc16::State::<crc16::MODBUS>::calculate(&buf[0..n - 2]));
-
-    (crc >> 8) as u8 == buf[n - 2] && crc as u8 == buf[n - 1]
-}
-
-fn result_convert(buf: &[u8; 25], m: &mut Measurement) {
-    m.voltage = (((buf[3] as u16) << 8) | buf[4] as u16) as f32 / 10.0;
-    m.current = (((buf[5] as u32) << 8)
-        | ((buf[6] as u32) << 0)
-        | ((buf[7] as u32) << 24)
-        | ((buf[8] as u32) << 16)) as f32
-        / 1000.0;
-    m.power = (((buf[9] as u32) << 8)
-        | ((buf[10] as u32) << 0)
-        | ((buf[11] as u32) << 24)
-        | ((buf[12] as u32) << 16)) as f32
-        / 10.0; // TODO something is wrong
-    m.energy = (((buf[13] as u32) << 8)
-        | ((buf[14] as u32) << 0)
-        | ((buf[15] as u32) << 24)
-        | ((buf[16] as u32) << 16)) as f32
-        / 1000.0;
-    m.frequency = (((buf[17] as u16) << 8) | ((buf[18] as u16) << 0)) as f32 / 10.0;
-    m.pf = (((buf[19] as u16) << 8) | ((buf[20] as u16) << 0)) as f32 / 100.0;
-    m.alarm = (((buf[21] as u16) << 8) | ((buf[22] as u16) << 0)) != 0;
-}
-
 /// Measurement results stored as the 32-bit floating point variables.
+#[cfg(not(feature = "no-float"))]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Measurement {
     pub voltage: f32,
@@ -139,13 +568,69 @@ pub struct Measurement {
     pub alarm: bool,
 }
 
+/// Type-state of a driver which hasn't confirmed yet that the sensor answers on its address.
+///
+/// Only the reading operations are available in this state.
+#[derive(Debug)]
+pub struct Unverified;
+
+/// Type-state of a driver which has confirmed, by [`probe`](struct.Pzem.html#method.probe),
+/// that the sensor answers on its address.
+///
+/// Operations changing the state of the sensor are only available in this state.
+#[derive(Debug)]
+pub struct Verified;
+
 /// Struct representing a PZEM004T sensor connected to a serial bus.
-pub struct Pzem<Serial> {
+///
+/// A freshly created driver is [`Unverified`](struct.Unverified.html) and has to be
+/// [`probe`](#method.probe)d before it is allowed to change the address or the threshold
+/// of the sensor, or to reset its energy counter. This prevents e.g. accidentally
+/// re-addressing an unknown slave through the general address.
+///
+/// # Threading
+///
+/// The driver is `Send` whenever the serial peripheral is, so that it can be handed over to
+/// the task serving the bus. It is never `Sync`: the attached sinks (look
+/// [`with_event_log`](#method.with_event_log) and [`with_journal`](#method.with_journal)) are
+/// only required to be `Send`. Tasks sharing a bus lock the driver through a
+/// [`SharedBus`](struct.SharedBus.html) instead.
+pub struct Pzem<Serial, State = Unverified> {
     uart: Serial,
     addr: u8,
+    crc: &'static dyn CrcProvider,
+    backoff: Backoff,
+    noise_limit: Option<u32>,
+    drain_limit: Option<u32>,
+    skip_drain: bool,
+    flush: bool,
+    turnaround: Option<fn()>,
+    echo_check: bool,
+    write_mode: WriteMode,
+    crc_mode: CrcMode,
+    events: Option<EventHook>,
+    journal: Option<JournalHook>,
+    #[cfg(not(feature = "size-opt"))]
+    phase_clock: Option<fn() -> u32>,
+    cancel: Option<&'static Cancel>,
+    addr_hook: Option<fn(AddrChange)>,
+    pending: Option<Operation>,
+    // Probing the addresses of a scan, which doesn't report its errors.
+    quiet: bool,
+    last_error: Option<Event>,
+    last_rx: u8,
+    exchange: ExchangeInfo,
+    #[cfg(not(feature = "no-float"))]
+    scaling: Scaling,
+    #[cfg(not(feature = "no-float"))]
+    correction: Option<Correction>,
+    last: Option<RawMeasurement>,
+    #[cfg(not(feature = "size-opt"))]
+    stats: Stats,
+    state: PhantomData<State>,
 }
 
-impl<Serial, WriteError, ReadError> Pzem<Serial>
+impl<Serial, WriteError, ReadError> Pzem<Serial, Unverified>
 where
     Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
 {
@@ -157,68 +642,1002 @@ where
     /// Can return `Err(Error::IllegalAddress)` if `addr` is not in range of legal addresses `[0x01..0xf8]`.
     pub fn new(uart: Serial, addr: Option<u8>) -> Result<Self, Error<WriteError, ReadError>> {
         let addr = addr.unwrap_or(ADDR_DEFAULT);
-        if addr != ADDR_DEFAULT && (addr < ADDR_MIN || addr > ADDR_MAX) {
+        if addr != ADDR_DEFAULT && !(ADDR_MIN..=ADDR_MAX).contains(&addr) {
             return Err(Error::IllegalAddress);
         }
 
-        Ok(Self { uart, addr })
+        Ok(Self {
+            uart,
+            addr,
+            crc: &SoftwareCrc,
+            backoff: Backoff::none(),
+            noise_limit: None,
+            drain_limit: None,
+            skip_drain: false,
+            flush: true,
+            turnaround: None,
+            echo_check: true,
+            write_mode: WriteMode::Single,
+            crc_mode: CrcMode::Strict,
+            events: None,
+            journal: None,
+            #[cfg(not(feature = "size-opt"))]
+            phase_clock: None,
+            cancel: None,
+            addr_hook: None,
+            pending: None,
+            quiet: false,
+            last_error: None,
+            last_rx: 0,
+            exchange: ExchangeInfo::default(),
+            #[cfg(not(feature = "no-float"))]
+            scaling: Scaling::DATASHEET,
+            #[cfg(not(feature = "no-float"))]
+            correction: None,
+            last: None,
+            #[cfg(not(feature = "size-opt"))]
+            stats: Stats::default(),
+            state: PhantomData,
+        })
     }
 
-    fn communicate<T: timer::CountDown>(
+    /// Confirms that the sensor answers on the configured address by reading its address parameter.
+    ///
+    /// In case of success, returns the [`Verified`](struct.Verified.html) driver, allowing
+    /// the operations changing the state of the sensor. Otherwise, gives the unverified driver back
+    /// along with the error.
+    ///
+    /// If the response fails the CRC check, the measurements are read a few times: when all of
+    /// them fail as well, the error is `Error::ProbableAddressConflict`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let pzem = pzem004t::Pzem::new(serial, None).unwrap();
+    /// let mut pzem = match pzem.probe(Some((&mut tim, TIMEOUT))) {
+    ///     Ok(pzem) => pzem,
+    ///     Err((_pzem, e)) => panic!("No PZEM004T on the bus: {}", e),
+    /// };
+    /// pzem.set_addr(0x10, Some((&mut tim, TIMEOUT))).unwrap();
+    /// ```
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    pub fn probe<Tm: Timeout>(
+        mut self,
+        mut timeout: Tm,
+    ) -> Result<Pzem<Serial, Verified>, (Self, Error<WriteError, ReadError>)> {
+        match self.get_addr(&mut timeout) {
+            Ok(_) => Ok(self.into_state()),
+            Err(Error::CrcMismatch) => match self.conflict_at(self.addr, &mut timeout) {
+                Ok(true) => Err((self, Error::ProbableAddressConflict)),
+                Ok(false) => Err((self, Error::CrcMismatch)),
+                Err(e) => Err((self, e)),
+            },
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+
+impl<'a, Serial, WriteError, ReadError> Pzem<SerialRef<'a, Serial>, Unverified>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Creates a new PZEM004T struct, borrowing the serial peripheral instead of consuming it.
+    ///
+    /// Useful for short-lived configuration utilities, which use the same UART for other
+    /// protocols before and after talking to the sensor. Arguments are the same as in [`new`](#method.new).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let threshold = pzem004t::Pzem::on(&mut serial, None)?.get_threshold(Some((&mut tim, TIMEOUT)))?;
+    /// serial.write(b'x');
+    /// ```
+    pub fn on(
+        uart: &'a mut Serial,
+        addr: Option<u8>,
+    ) -> Result<Self, Error<WriteError, ReadError>> {
+        Pzem::new(SerialRef(uart), addr)
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Replaces the software CRC implementation, e.g. with a hardware CRC unit.
+    ///
+    /// Look [`CrcProvider`](trait.CrcProvider.html).
+    pub fn with_crc(mut self, crc: &'static dyn CrcProvider) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// Sets the backoff-and-retry policy used when the bus is shared with another master.
+    ///
+    /// Look [`Backoff`](struct.Backoff.html).
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum number of unexpected bytes tolerated in the input queue before a request.
+    ///
+    /// The input queue is drained before every request, and the discarded bytes are counted in
+    /// [`Stats::drained_bytes`](struct.Stats.html#structfield.drained_bytes). With a limit set,
+    /// finding more bytes than `limit` fails the operation with `Err(Error::LineNoise)`,
+    /// pointing at a wiring problem rather than letting it show up as intermittent read failures.
+    pub fn with_noise_limit(mut self, limit: Option<u32>) -> Self {
+        self.noise_limit = limit;
+        self
+    }
+
+    /// Sets the maximum number of bytes discarded from the input queue before a request.
+    ///
+    /// Draining the input queue is always bounded by the timeout of the operation. If another
+    /// device keeps streaming on a mis-wired bus, the operation fails with `Err(Error::BusBusy)`
+    /// once the timeout expires or, with a limit set, once `limit` bytes have been discarded.
+    /// This gives the control back to the caller deterministically even without a timeout;
+    /// without either, the drain stops at the length of the longest response.
+    pub fn with_drain_limit(mut self, limit: Option<u32>) -> Self {
+        self.drain_limit = limit;
+        self
+    }
+
+    /// Skips draining the input queue before the requests, for high-rate polling over
+    /// a point-to-point link.
+    ///
+    /// The drain costs a `read` call per leftover byte, plus starting the timer, before every
+    /// request; on a PC behind a USB adapter each of these may be a system call. Instead,
+    /// the leftovers preceding the response are skipped until its header matches the request,
+    /// up to the [drain limit](#method.with_drain_limit) or the length of the longest response.
+    /// The line noise is not
+    /// detected in this mode, and the queue is still drained after an abandoned transaction.
+    ///
+    /// `pzemctl bench` measures the time per read with and without the drain.
+    pub fn with_skip_drain(mut self, skip: bool) -> Self {
+        self.skip_drain = skip;
+        self
+    }
+
+    /// Sets whether the transmission of every request is awaited with `flush` before
+    /// waiting for the response, which is the default.
+    ///
+    /// Some USB-serial adapters report the flush only once their own buffers drain, or never.
+    /// Without flushing, the response timeout starts as soon as the request has been written;
+    /// look [`with_turnaround`](#method.with_turnaround) to make up for the buffering.
+    pub fn with_flush(mut self, flush: bool) -> Self {
+        self.flush = flush;
+        self
+    }
+
+    /// Sets the delay called after every request has been written (and flushed), before the
+    /// response timeout starts.
+    ///
+    /// Adapters buffering the data aggressively transmit the request late, and the response
+    /// then arrives after the timeout. The delay allows for it without lengthening the timeouts.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let pzem = Pzem::new(serial, None)?
+    ///     .with_flush(false)
+    ///     .with_turnaround(Some(|| std::thread::sleep(Duration::from_millis(20))));
+    /// ```
+    pub fn with_turnaround(mut self, delay: Option<fn()>) -> Self {
+        self.turnaround = delay;
+        self
+    }
+
+    /// Sets whether the responses to the parameter writes are checked to echo the register
+    /// address and the value written, which is the default.
+    ///
+    /// Some clones acknowledge the writes echoing stale values; the check fails these writes
+    /// with `Err(Error::WriteVerificationFailed)` rather than letting them pass silently.
+    /// The responses of [`WriteMode::Multiple`](enum.WriteMode.html) echo the register address
+    /// and the number of registers only.
+    pub fn with_echo_check(mut self, check: bool) -> Self {
+        self.echo_check = check;
+        self
+    }
+
+    /// Sets the function used for writing the parameters of the sensor.
+    ///
+    /// Look [`WriteMode`](enum.WriteMode.html).
+    pub fn with_write_mode(mut self, mode: WriteMode) -> Self {
+        self.write_mode = mode;
+        self
+    }
+
+    /// Sets the handling of the response frames failing the CRC check.
+    ///
+    /// Look [`CrcMode`](enum.CrcMode.html).
+    pub fn with_crc_mode(mut self, mode: CrcMode) -> Self {
+        self.crc_mode = mode;
+        self
+    }
+
+    /// Overrides the conversion of the raw register values by [`read`](#method.read).
+    ///
+    /// Look [`Scaling`](struct.Scaling.html).
+    #[cfg(not(feature = "no-float"))]
+    pub fn with_scaling(mut self, scaling: Scaling) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    /// Corrects the measurements returned by [`read`](#method.read) for the systematic errors
+    /// of the sensor, found by calibration against a reference meter.
+    ///
+    /// Look [`Correction`](struct.Correction.html).
+    #[cfg(not(feature = "no-float"))]
+    pub fn with_correction(mut self, correction: Correction) -> Self {
+        self.correction = Some(correction);
+        self
+    }
+
+    /// Attaches the sink recording the protocol errors, alarm transitions and energy resets,
+    /// timestamped with the ticks returned by `clock`.
+    ///
+    /// Look [`EventLog`](struct.EventLog.html).
+    pub fn with_event_log(mut self, sink: &'static mut dyn EventSink, clock: fn() -> u32) -> Self {
+        self.events = Some(EventHook::new(sink, clock));
+        self
+    }
+
+    /// Attaches the journal receiving every frame sent and received, timestamped with the
+    /// ticks returned by `clock`.
+    ///
+    /// Look [`Journal`](trait.Journal.html).
+    pub fn with_journal(mut self, journal: &'static mut dyn Journal, clock: fn() -> u32) -> Self {
+        self.journal = Some(JournalHook::new(journal, clock));
+        self
+    }
+
+    /// Attaches the clock timing the phases of every exchange into
+    /// [`Stats::phases`](struct.Stats.html#structfield.phases), to tell whether the latency
+    /// comes from the sensor, the serial adapter or the HAL.
+    ///
+    /// Look [`PhaseTimes`](struct.PhaseTimes.html).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut pzem = Pzem::new(serial, None).unwrap().with_phase_clock(|| DWT::cycle_count());
+    /// pzem.read(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    /// let phases = pzem.stats().last_phases;
+    /// ```
+    #[cfg(not(feature = "size-opt"))]
+    pub fn with_phase_clock(mut self, clock: fn() -> u32) -> Self {
+        self.phase_clock = Some(clock);
+        self
+    }
+
+    /// Attaches the flag cancelling the transaction in flight.
+    ///
+    /// Look [`Cancel`](struct.Cancel.html).
+    pub fn with_cancel(mut self, cancel: &'static Cancel) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Attaches the hook called whenever the sensor address changes, e.g. to persist the
+    /// address mapping to flash.
+    ///
+    /// Called after a successful [`set_addr`](#method.set_addr), and after a scan of the bus
+    /// finding the sensor elsewhere: when the configured address is silent and exactly one
+    /// other slave answers. The driver keeps referring to the sensor by the configured address
+    /// in the latter case.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let pzem = Pzem::new(serial, Some(config.addr)).unwrap().with_addr_hook(|change| {
+    ///     cortex_m::interrupt::free(|cs| FLASH.borrow(cs).borrow_mut().store_addr(change.new));
+    /// });
+    /// ```
+    pub fn with_addr_hook(mut self, hook: fn(AddrChange)) -> Self {
+        self.addr_hook = Some(hook);
+        self
+    }
+
+    // Reports the address change, if a hook is attached.
+    pub(crate) fn addr_changed(&self, old: u8, new: u8) {
+        if let Some(hook) = self.addr_hook {
+            hook(AddrChange { old, new });
+        }
+    }
+
+    /// Returns the attached event sink.
+    pub fn event_log(&mut self) -> Option<&mut dyn EventSink> {
+        match &mut self.events {
+            Some(hook) => Some(&mut *hook.sink),
+            None => None,
+        }
+    }
+
+    // Records the event, if a sink is attached.
+    fn record(&mut self, event: Event) {
+        if let Some(hook) = &mut self.events {
+            hook.record(event);
+        }
+    }
+
+    /// Returns the communication statistics gathered so far.
+    #[cfg(not(feature = "size-opt"))]
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Resets all the communication statistics to zero.
+    #[cfg(not(feature = "size-opt"))]
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    fn into_state<S>(self) -> Pzem<Serial, S> {
+        Pzem {
+            uart: self.uart,
+            addr: self.addr,
+            crc: self.crc,
+            backoff: self.backoff,
+            noise_limit: self.noise_limit,
+            drain_limit: self.drain_limit,
+            skip_drain: self.skip_drain,
+            flush: self.flush,
+            turnaround: self.turnaround,
+            echo_check: self.echo_check,
+            write_mode: self.write_mode,
+            crc_mode: self.crc_mode,
+            events: self.events,
+            journal: self.journal,
+            #[cfg(not(feature = "size-opt"))]
+            phase_clock: self.phase_clock,
+            cancel: self.cancel,
+            addr_hook: self.addr_hook,
+            pending: self.pending,
+            quiet: self.quiet,
+            last_error: self.last_error,
+            last_rx: self.last_rx,
+            exchange: self.exchange,
+            #[cfg(not(feature = "no-float"))]
+            scaling: self.scaling,
+            #[cfg(not(feature = "no-float"))]
+            correction: self.correction,
+            last: self.last,
+            #[cfg(not(feature = "size-opt"))]
+            stats: self.stats,
+            state: PhantomData,
+        }
+    }
+
+    // Performs the exchange, retrying it after a backoff on suspected collisions.
+    fn communicate<Tm: Timeout, const REQ: usize>(
         &mut self,
-        req: &[u8],
+        op: Operation,
+        req: &[u8; REQ],
         resp: &mut [u8],
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        if let Some(cancel) = self.cancel {
+            cancel.clear();
+        }
+
+        self.exchange = ExchangeInfo::default();
+        #[cfg(not(feature = "size-opt"))]
+        let start = self.phase_clock.map(|clock| clock());
+
+        let mut attempt = 0;
+        loop {
+            count!(self.stats.transactions);
+
+            #[cfg(not(feature = "size-opt"))]
+            let mut phases = PhaseTimer::start(self.phase_clock);
+            #[cfg(feature = "size-opt")]
+            let mut phases = PhaseTimer::start(None);
+            let res = self
+                .drain_input(timeout.get(op))
+                .and_then(|()| {
+                    phases.times.drain = phases.lap();
+                    self.transmit(req, timeout.get(op))
+                })
+                .and_then(|()| {
+                    phases.times.write = phases.lap();
+                    self.receive(req, resp, timeout.get(op), &mut phases)
+                });
+            #[cfg(not(feature = "size-opt"))]
+            if self.phase_clock.is_some() {
+                self.stats.phases.accumulate(&phases.times);
+                self.stats.last_phases = phases.times;
+            }
+
+            let mut res = match res {
+                Err(_) if self.cancel.is_some_and(Cancel::is_cancelled) => self.abandon(),
+                res => res,
+            };
+
+            match res {
+                Err(Error::TimedOut { .. }) => timeout.observe(op, true),
+                Ok(())
+                | Err(Error::CrcMismatch)
+                | Err(Error::PzemError)
+                | Err(Error::IllegalAddress)
+                | Err(Error::LineNoise)
+                | Err(Error::Exception(_)) => timeout.observe(op, false),
+                _ => {}
+            }
+
+            match res {
+                Err(Error::TimedOut { .. }) => {
+                    count!(self.stats.timeouts);
+                }
+                Err(Error::CrcMismatch) => {
+                    count!(self.stats.crc_errors);
+
+                    if let Some(slots) = self.backoff.delay(attempt) {
+                        count!(self.stats.collisions);
+                        log_warn!("PZEM004T {:#04x}: collision, backing off", self.addr);
+
+                        for _ in 0..slots {
+                            if let Some((timer, time)) = timeout.get(op) {
+                                timer.start(time);
+                                while timer.wait().is_err()
+                                    && !self.cancel.is_some_and(Cancel::is_cancelled)
+                                {
+                                    let (n, _) = self
+                                        .uart
+                                        .drain(Some(&mut *timer), self.drain_limit)
+                                        .map_err(Error::ReadError)?;
+                                    count!(self.stats.drained_bytes, n);
+                                    self.exchange.resyncs = self.exchange.resyncs.wrapping_add(n);
+                                }
+                            }
+                        }
+
+                        if self.cancel.is_some_and(Cancel::is_cancelled) {
+                            res = self.abandon();
+                        } else {
+                            attempt += 1;
+                            self.exchange.retries = attempt;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            #[cfg(not(feature = "size-opt"))]
+            if let (Some(clock), Some(start)) = (self.phase_clock, start) {
+                self.exchange.latency = Some(clock().wrapping_sub(start));
+            }
+
+            return self.conclude(op, res);
+        }
+    }
+
+    // Records the outcome of the exchange.
+    fn conclude<T>(
+        &mut self,
+        op: Operation,
+        res: Result<T, Error<WriteError, ReadError>>,
+    ) -> Result<T, Error<WriteError, ReadError>> {
+        if let Err(e) = &res {
+            self.report(e);
+        }
+
+        // The response to an abandoned request may still be arriving, but hardly from
+        // a scanned address which stayed silent.
+        self.pending = match res {
+            Err(Error::TimedOut { received: 0 }) if self.quiet => None,
+            Err(Error::TimedOut { .. }) | Err(Error::Cancelled) => Some(op),
+            _ => None,
+        };
+
+        res
+    }
+
+    // Records the error into the event sink and the debug state, unless probing a scan.
+    fn report(&mut self, e: &Error<WriteError, ReadError>) {
+        if !self.quiet {
+            self.record(e.into());
+            self.last_error = Some(e.into());
+        }
+    }
+
+    // Discards the rest of the cancelled transaction, the response may still be arriving.
+    fn abandon(&mut self) -> Result<(), Error<WriteError, ReadError>> {
+        log_warn!("PZEM004T {:#04x}: transaction cancelled", self.addr);
+
+        let (n, _) = self
+            .uart
+            .drain::<NoTimeout>(None, self.drain_cap())
+            .map_err(Error::ReadError)?;
+        count!(self.stats.drained_bytes, n);
+
+        Err(Error::Cancelled)
+    }
+
+    // Bound of the drains without a timer, so that a streaming bus can't wedge the caller:
+    // the limit set, or the longest response.
+    pub(crate) fn drain_cap(&self) -> Option<u32> {
+        Some(self.drain_limit.unwrap_or(HOLDING_RESP_MAX as u32))
+    }
+
+    // Makes sure the input queue is empty before sending the request.
+    fn drain_input<T: timer::CountDown>(
+        &mut self,
         timeout: Option<(&mut T, T::Time)>,
     ) -> Result<(), Error<WriteError, ReadError>> {
-        // Make sure the input queue is empty before sending the request.
-        self.uart.drain().map_err(Error::ReadError)?;
+        // The response to an abandoned request can't be told from the expected one.
+        if self.skip_drain && self.pending.is_none() {
+            return Ok(());
+        }
 
-        self.uart.write_blocking(&req).map_err(Error::WriteError)?;
-        block!(self.uart.flush()).map_err(Error::WriteError)?;
+        // Without a timer, the drain is bounded by the count of the discarded bytes alone.
+        let limit = match timeout {
+            Some(_) => self.drain_limit,
+            None => self.drain_cap(),
+        };
+        let timer = timeout.map(|(timer, time)| {
+            timer.start(time);
+            timer
+        });
+        let mut timer = Cancellable::new(timer, self.cancel);
 
-        if self
+        let (n, complete) = self
             .uart
-            .read_blocking(timeout, resp)
-            .map_err(Error::ReadError)?
-            < resp.len() as u8
+            .drain(Some(&mut timer), limit)
+            .map_err(Error::ReadError)?;
+        count!(self.stats.drained_bytes, n);
+        self.exchange.resyncs = self.exchange.resyncs.wrapping_add(n);
+
+        if !complete {
+            count!(self.stats.bus_busy);
+            log_warn!("PZEM004T {:#04x}: bus is busy", self.addr);
+            return Err(Error::BusBusy);
+        }
+
+        if self.noise_limit.is_some_and(|limit| n > limit) {
+            count!(self.stats.line_noise);
+            log_warn!("PZEM004T {:#04x}: {} bytes of line noise", self.addr, n);
+            return Err(Error::LineNoise);
+        }
+
+        Ok(())
+    }
+
+    // Transmits the request. The transmission is bounded by the timeout as well,
+    // in case the UART is wedged (e.g. CTS stuck).
+    fn transmit<T: timer::CountDown>(
+        &mut self,
+        req: &[u8],
+        timeout: Option<(&mut T, T::Time)>,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let timer = timeout.map(|(timer, time)| {
+            timer.start(time);
+            timer
+        });
+        let mut timer = Cancellable::new(timer, self.cancel);
+
+        if !self
+            .uart
+            .write_blocking(Some(&mut timer), req, self.flush)
+            .map_err(Error::WriteError)?
         {
-            // If read_blocking has written less than N bytes,
-            // we had a timeout.
-            return Err(Error::TimedOut);
+            log_warn!("PZEM004T {:#04x}: transmission timed out", self.addr);
+            return Err(Error::TimedOut { received: 0 });
+        }
+
+        if let Some(journal) = &mut self.journal {
+            journal.sent(req);
+        }
+
+        if let Some(delay) = self.turnaround {
+            delay();
+        }
+
+        Ok(())
+    }
+
+    // Receives the response to the request.
+    fn receive<T: timer::CountDown, const REQ: usize>(
+        &mut self,
+        req: &[u8; REQ],
+        resp: &mut [u8],
+        timeout: Option<(&mut T, T::Time)>,
+        phases: &mut PhaseTimer,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let res = self.receive_frame(req, resp, timeout, phases);
+        if let Some(journal) = &mut self.journal {
+            journal.end_rx();
+        }
+
+        res
+    }
+
+    // Reads into `buf`, passing the bytes received on to the journal.
+    fn read_rx<T: timer::CountDown>(
+        &mut self,
+        timer: &mut T,
+        buf: &mut [u8],
+    ) -> Result<u8, Error<WriteError, ReadError>> {
+        let n = self
+            .uart
+            .read_blocking(Some(timer), buf)
+            .map_err(Error::ReadError)?;
+        if let Some(journal) = &mut self.journal {
+            journal.received(&buf[..n as usize]);
+        }
+
+        Ok(n)
+    }
+
+    fn receive_frame<T: timer::CountDown, const REQ: usize>(
+        &mut self,
+        req: &[u8; REQ],
+        resp: &mut [u8],
+        timeout: Option<(&mut T, T::Time)>,
+        phases: &mut PhaseTimer,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let timer = timeout.map(|(timer, time)| {
+            timer.start(time);
+            timer
+        });
+        let mut timer = Cancellable::new(timer, self.cancel);
+        self.last_rx = 0;
+
+        // Read the header (slave addr. + function code) first, as an exception
+        // response is shorter than the regular one.
+        let mut received = self.read_rx(&mut timer, &mut resp[0..1])?;
+        phases.times.first_byte = phases.lap();
+        if received == 1 {
+            received += self.read_rx(&mut timer, &mut resp[1..2])?;
+        }
+        self.last_rx = received;
+        if received < 2 {
+            log_warn!("PZEM004T {:#04x}: communication timed out", self.addr);
+            return Err(Error::TimedOut { received });
+        }
+
+        // Without the drain, leftovers may precede the response: skip them byte by byte
+        // until the header matches.
+        if self.skip_drain {
+            let mut skipped: u32 = 0;
+            while resp[0] != req[0] || (resp[1] != req[1] && resp[1] != req[1] | EXCEPTION_FLAG) {
+                if skipped >= self.drain_limit.unwrap_or(HOLDING_RESP_MAX as u32) {
+                    break;
+                }
+                resp[0] = resp[1];
+                if self.read_rx(&mut timer, &mut resp[1..2])? == 0 {
+                    return Err(Error::TimedOut { received: 1 });
+                }
+                skipped += 1;
+            }
+            count!(self.stats.drained_bytes, skipped);
+            self.exchange.resyncs = self.exchange.resyncs.wrapping_add(skipped);
+        }
+
+        if resp[0] == req[0] && resp[1] == req[1] | EXCEPTION_FLAG {
+            let mut exc = [resp[0], resp[1], 0, 0, 0];
+            let n = self.read_rx(&mut timer, &mut exc[2..])?;
+            self.last_rx = 2 + n;
+            phases.times.read_rest = phases.lap();
+            if n < (EXCEPTION_LEN - 2) as u8 {
+                return Err(Error::TimedOut { received: 2 + n });
+            }
+
+            if !crc_check(self.crc, &exc) {
+                self.corrupted(&exc);
+                return Err(Error::CrcMismatch);
+            }
+
+            log_warn!("PZEM004T {:#04x}: exception {:#04x}", self.addr, exc[2]);
+            return Err(Error::Exception(exc[2].into()));
         }
 
         // First two bytes of the response (slave addr. + function code)
         // must correspond to the request.
         if resp[0] != req[0] || resp[1] != req[1] {
+            log_warn!(
+                "PZEM004T {:#04x}: unexpected response header {:02x?}",
+                self.addr,
+                &resp[0..2]
+            );
             return Err(Error::PzemError);
         }
 
-        // If the response length is just 4 bytes, it is faster to compare
-        // with the request CRC, as they are exactly the same.
-        if resp.len() == 4 && (resp[2] != req[2] || resp[3] != req[3]) {
-            return Err(Error::CrcMismatch);
+        // The reads are answered with the byte count, which takes precedence over the length
+        // expected: some clones answer more registers than requested.
+        let mut start = 2;
+        let resp = match req[1] {
+            CMD_READ | CMD_READ_PARAM => {
+                start += self.read_rx(&mut timer, &mut resp[2..3])?;
+                self.last_rx = start;
+                if start < 3 {
+                    return Err(Error::TimedOut { received: start });
+                }
+
+                // Checked before waiting for the rest, lest a corrupted count wait for bytes
+                // which never come.
+                let count = resp[2] as usize;
+                let len = 3 + count + 2;
+                let valid = match req[1] {
+                    CMD_READ => decode::valid_count(count),
+                    _ => len == resp.len(),
+                };
+                if !valid || len > resp.len() {
+                    log_warn!(
+                        "PZEM004T {:#04x}: unexpected byte count {}",
+                        self.addr,
+                        count
+                    );
+                    return Err(Error::PzemError);
+                }
+                &mut resp[..len]
+            }
+            _ => resp,
+        };
+
+        // A genuine response whose byte count got corrupted into the one of a clone ends
+        // short: it is told by its CRC, computed with the genuine count.
+        let n = if req[1] == CMD_READ && resp.len() > READ_RESP_LEN {
+            let mut n = self.read_rx(&mut timer, &mut resp[start as usize..READ_RESP_LEN])?;
+            if start + n == READ_RESP_LEN as u8 {
+                let mut genuine = [0u8; READ_RESP_LEN];
+                genuine.copy_from_slice(&resp[..READ_RESP_LEN]);
+                genuine[2] = 2 * REG_COUNT as u8;
+                if crc_check(self.crc, &genuine) {
+                    log_warn!("PZEM004T {:#04x}: byte count corrupted", self.addr);
+                    self.last_rx = start + n;
+                    self.corrupted(&resp[..READ_RESP_LEN]);
+                    return Err(Error::CrcMismatch);
+                }
+                n += self.read_rx(&mut timer, &mut resp[READ_RESP_LEN..])?;
+            }
+            n
+        } else {
+            self.read_rx(&mut timer, &mut resp[start as usize..])?
+        };
+        self.last_rx = start + n;
+        phases.times.read_rest = phases.lap();
+        if n < (resp.len() - start as usize) as u8 {
+            // If read_blocking has written less than N bytes,
+            // we had a timeout.
+            log_warn!(
+                "PZEM004T {:#04x}: communication timed out, {} of {} bytes received",
+                self.addr,
+                start + n,
+                resp.len()
+            );
+            return Err(Error::TimedOut {
+                received: start + n,
+            });
         }
 
-        if !crc_check(&resp) {
+        // If the response length is just 4 bytes (reset), it is faster to compare
+        // with the request CRC, as they are exactly the same.
+        if (resp.len() == RESET_LEN && (resp[2] != req[2] || resp[3] != req[3]))
+            || !crc_check_slice(self.crc, resp)
+        {
+            log_warn!("PZEM004T {:#04x}: CRC doesn't match", self.addr);
+            self.corrupted(resp);
+
+            #[cfg(feature = "unchecked-frames")]
+            if let CrcMode::Unchecked(_) = self.crc_mode {
+                log_warn!(
+                    "PZEM004T {:#04x}: accepting unchecked {:02x?}",
+                    self.addr,
+                    resp
+                );
+                count!(self.stats.crc_errors);
+                return Ok(());
+            }
+
             return Err(Error::CrcMismatch);
         }
 
         Ok(())
     }
 
+    fn corrupted(&self, frame: &[u8]) {
+        match self.crc_mode {
+            CrcMode::Strict => {}
+            CrcMode::Lenient(callback) => callback(frame),
+            #[cfg(feature = "unchecked-frames")]
+            CrcMode::Unchecked(callback) => callback(frame),
+        }
+    }
+
     /// Reads the measurements off the sensor and stores them into `m`.
     ///
     /// The timeout can be omitted (will wait indefinitely) in such a way:
     ///
-    ///     pzem.communicate::<NoTimeout>(&mut m, None).unwrap();
+    /// ```ignore
+    /// pzem.read(&mut m, NoTimeout).unwrap();
+    /// ```
     ///
     /// Look [`NoTimeout`](struct.NoTimeout.html).
-    pub fn read<T: timer::CountDown>(
+    #[cfg(not(feature = "no-float"))]
+    pub fn read<Tm: Timeout>(
         &mut self,
         m: &mut Measurement,
-        timeout: Option<(&mut T, T::Time)>,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut raw = RawMeasurement::default();
+        self.read_raw(&mut raw, timeout)?;
+
+        *m = self.convert(&raw);
+        log_debug!("PZEM004T {:#04x}: {:?}", self.addr, m);
+
+        Ok(())
+    }
+
+    // Converts the raw measurement with the scaling and the correction of the driver.
+    #[cfg(not(feature = "no-float"))]
+    pub(crate) fn convert(&self, raw: &RawMeasurement) -> Measurement {
+        let m = self.scaling.apply(raw);
+        match &self.correction {
+            Some(correction) => correction.apply(&m),
+            None => m,
+        }
+    }
+
+    /// Reads the raw measurement registers off the sensor and stores them into `m`.
+    ///
+    /// Look [`RawMeasurement`](struct.RawMeasurement.html).
+    pub fn read_raw<Tm: Timeout>(
+        &mut self,
+        m: &mut RawMeasurement,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut resp = [0u8; READ_RESP_MAX];
+        self.read_frame(&mut resp, m, timeout)
+    }
+
+    /// Reads the measurements off the sensor and stores them into `m`, along with the
+    /// verified response frame into `frame`.
+    ///
+    /// The frames may be archived and decoded later on by [`decode::decode_frame`](decode/fn.decode_frame.html),
+    /// e.g. once the scaling of a clone board is figured out. The frame takes the first
+    /// `5 + frame[2]` bytes: [`READ_FRAME_LEN`](constant.READ_FRAME_LEN.html) from a genuine
+    /// sensor, up to [`READ_FRAME_MAX`](constant.READ_FRAME_MAX.html) from the clones answering
+    /// extra registers. A response not fitting into `frame` fails with `Err(Error::PzemError)`;
+    /// a `frame` shorter than `READ_FRAME_LEN` fails to compile.
+    #[cfg(not(feature = "no-float"))]
+    pub fn read_with_raw<Tm: Timeout, const N: usize>(
+        &mut self,
+        m: &mut Measurement,
+        frame: &mut [u8; N],
+        timeout: Tm,
     ) -> Result<(), Error<WriteError, ReadError>> {
-        let mut buf = [
+        let mut raw = RawMeasurement::default();
+        self.read_frame(frame, &mut raw, timeout)?;
+
+        *m = self.convert(&raw);
+        Ok(())
+    }
+
+    /// Reads the raw measurement registers off the sensor and stores them into `m`, along with
+    /// the verified response frame into `frame`.
+    ///
+    /// The length of the frame is the same as of [`read_with_raw`](#method.read_with_raw).
+    /// If the response is truncated, failing with `Err(Error::TimedOut { received })`,
+    /// the first `received` bytes of `frame` hold what arrived.
+    pub fn read_raw_with_frame<Tm: Timeout, const N: usize>(
+        &mut self,
+        m: &mut RawMeasurement,
+        frame: &mut [u8; N],
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        self.read_frame(frame, m, timeout)
+    }
+
+    /// Reads the measurement registers off the sensor and decodes them into a custom output type.
+    ///
+    /// Look [`FromRegisters`](decode/trait.FromRegisters.html).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let m: Milli = pzem.read_as(Some((&mut tim, TIMEOUT)))?;
+    /// ```
+    pub fn read_as<T: FromRegisters, Tm: Timeout>(
+        &mut self,
+        timeout: Tm,
+    ) -> Result<T, Error<WriteError, ReadError>> {
+        let mut resp = [0u8; READ_RESP_MAX];
+        self.read_frame(&mut resp, &mut RawMeasurement::default(), timeout)?;
+
+        Ok(T::from_registers(&registers(&resp)))
+    }
+
+    fn read_frame<Tm: Timeout, const N: usize>(
+        &mut self,
+        resp: &mut [u8; N],
+        m: &mut RawMeasurement,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let () = ReadBuf::<N>::FITS;
+        let buf = self.read_request();
+
+        // The response: slave address + CMD_RIR + number of bytes + 20 bytes (or more) + CRC + CRC
+        self.communicate(Operation::Read, &buf, resp, timeout)?;
+
+        *m = self.decode(resp)?;
+
+        if let Some(hook) = &mut self.events {
+            hook.alarm(m.alarm);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "no-float"))]
+    /// Sends the request to read the measurements, without waiting for the response.
+    ///
+    /// The first half of the exchange completed by [`finish_read`](#method.finish_read), so
+    /// that the sensors on several buses may be kept busy at once. Not retried on collisions.
+    pub(crate) fn start_read<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        count!(self.stats.transactions);
+
+        let buf = self.read_request();
+        let res = self
+            .drain_input(timeout.get(Operation::Read))
+            .and_then(|()| self.transmit(&buf, timeout.get(Operation::Read)));
+
+        self.conclude(Operation::Read, res)
+    }
+
+    #[cfg(not(feature = "no-float"))]
+    /// Receives the response to the request sent by [`start_read`](#method.start_read).
+    pub(crate) fn finish_read<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<RawMeasurement, Error<WriteError, ReadError>> {
+        let buf = self.read_request();
+        let mut resp = [0u8; READ_RESP_MAX];
+        let res = self.receive(
+            &buf,
+            &mut resp,
+            timeout.get(Operation::Read),
+            &mut PhaseTimer::start(None),
+        );
+        match res {
+            Err(Error::TimedOut { .. }) => {
+                count!(self.stats.timeouts);
+            }
+            Err(Error::CrcMismatch) => {
+                count!(self.stats.crc_errors);
+            }
+            _ => {}
+        }
+
+        self.conclude(Operation::Read, res)?;
+        let m = self.decode(&resp)?;
+
+        if let Some(hook) = &mut self.events {
+            hook.alarm(m.alarm);
+        }
+
+        Ok(m)
+    }
+
+    // Decodes the response to the measurement read, which passed the CRC check. The buffer
+    // may be longer than the response, which takes the bytes told by its byte count.
+    fn decode(&mut self, resp: &[u8]) -> Result<RawMeasurement, Error<WriteError, ReadError>> {
+        let len = (3 + resp[2] as usize + 2).min(resp.len());
+        decode::parse(&resp[..len]).map_err(|e| {
+            log_warn!("PZEM004T {:#04x}: undecodable response {:?}", self.addr, e);
+            let e = Error::Decode(e);
+            self.report(&e);
+            e
+        })
+    }
+
+    fn read_request(&self) -> [u8; REQ_LEN] {
+        let mut buf: [u8; REQ_LEN] = [
             self.addr,              // Slave address
             CMD_READ,               // Function code: read measurement result
             0,                      // Register address high byte
@@ -229,25 +1648,19 @@ where
             0,                      // CRC
         ];
 
-        crc_write(&mut buf);
+        crc_write(self.crc, &mut buf);
 
-        // The response: slave address + CMD_RIR + number of bytes + 20 bytes + CRC + CRC
-        let mut resp: [u8; 25] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
-
-        result_convert(&resp, m);
-
-        Ok(())
+        buf
     }
 
     /// Reads the current power alarm threshold value of the energy monitor.
     ///
     /// In case of success, returns the raw `u16` value of the alarm threshold, where 1LSB corresponds to 1W.
-    pub fn get_threshold<T: timer::CountDown>(
+    pub fn get_threshold<Tm: Timeout>(
         &mut self,
-        timeout: Option<(&mut T, T::Time)>,
+        timeout: Tm,
     ) -> Result<u16, Error<WriteError, ReadError>> {
-        let mut buf = [
+        let mut buf: [u8; REQ_LEN] = [
             self.addr,                    // Slave address
             CMD_READ_PARAM,               // Function code: read internal parameter
             (PARAM_THRESHOLD >> 8) as u8, // Parameter address
@@ -258,10 +1671,10 @@ where
             0,                            // CRC
         ];
 
-        crc_write(&mut buf);
+        crc_write(self.crc, &mut buf);
 
-        let mut resp: [u8; 7] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
+        let mut resp = [0u8; PARAM_RESP_LEN];
+        self.communicate(Operation::ReadParam, &buf, &mut resp, timeout)?;
 
         Ok(((resp[3] as u16) << 8) | ((resp[4] as u16) << 0))
     }
@@ -269,11 +1682,11 @@ where
     /// Reads the current Modbus-RTU address of the energy monitor.
     ///
     /// Returns the raw `u8` value of the address, or an error.
-    pub fn get_addr<T: timer::CountDown>(
+    pub fn get_addr<Tm: Timeout>(
         &mut self,
-        timeout: Option<(&mut T, T::Time)>,
+        timeout: Tm,
     ) -> Result<u16, Error<WriteError, ReadError>> {
-        let mut buf = [
+        let mut buf: [u8; REQ_LEN] = [
             self.addr,               // Slave address
             CMD_READ_PARAM,          // Function code: read internal parameter
             (PARAM_ADDR >> 8) as u8, // Parameter address
@@ -284,100 +1697,344 @@ where
             0,                       // CRC
         ];
 
-        crc_write(&mut buf);
+        crc_write(self.crc, &mut buf);
 
-        let mut resp: [u8; 7] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
+        let mut resp = [0u8; PARAM_RESP_LEN];
+        self.communicate(Operation::ReadParam, &buf, &mut resp, timeout)?;
 
         Ok(((resp[3] as u16) << 8) | ((resp[4] as u16) << 0))
     }
 
-    /// Sets the power alarm threshold value of the energy monitor.
+    /// Reads `N` consecutive holding registers starting at `start` in one transaction.
+    ///
+    /// Besides the alarm threshold (`0x0001`) and the address (`0x0002`), the reserved
+    /// registers are returned as raw values, as some firmware revisions keep e.g. the baud
+    /// rate or calibration data there. Registers the sensor doesn't implement make it
+    /// respond with the illegal address exception.
     ///
     /// # Example
     ///
-    ///     // Will set the alarm threshold to 230 W:
-    ///     pzem.set_threshold(230, Some(&mut ti, 2.hz())).unwrap();
+    /// ```ignore
+    /// let regs: [u16; 8] = pzem.read_holding_registers(0x0000, Some((&mut tim, 2.hz()))).unwrap();
+    /// ```
     ///
-    pub fn set_threshold<T: timer::CountDown>(
+    /// `N` of zero or above [`HOLDING_REG_MAX`](constant.HOLDING_REG_MAX.html) fails to compile.
+    pub fn read_holding_registers<Tm: Timeout, const N: usize>(
         &mut self,
-        threshold: u16,
-        timeout: Option<(&mut T, T::Time)>,
-    ) -> Result<(), Error<WriteError, ReadError>> {
-        let mut buf: [u8; 8] = [
-            self.addr,                    // Slave address
-            CMD_WRITE_PARAM,              // Function code: set internal parameter
-            (PARAM_THRESHOLD >> 8) as u8, // Threshold parameter register address
-            (PARAM_THRESHOLD >> 0) as u8, // Threshold parameter register address
-            (threshold >> 8) as u8,       // Threshold parameter value
-            (threshold >> 0) as u8,       // Threshold parameter value
-            0,                            // CRC
-            0,                            // CRC
+        start: u16,
+        timeout: Tm,
+    ) -> Result<[u16; N], Error<WriteError, ReadError>> {
+        let () = HoldingCount::<N>::VALID;
+
+        let mut buf: [u8; REQ_LEN] = [
+            self.addr,          // Slave address
+            CMD_READ_PARAM,     // Function code: read internal parameter
+            (start >> 8) as u8, // Starting register address high byte.
+            (start >> 0) as u8, // Starting register address low byte.
+            (N >> 8) as u8,     // Number of registers to be read high byte.
+            (N >> 0) as u8,     // Number of registers to be read low byte.
+            0,                  // CRC
+            0,                  // CRC
         ];
 
-        crc_write(&mut buf);
+        crc_write(self.crc, &mut buf);
 
-        let mut resp: [u8; 8] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
+        let mut resp = [0u8; HOLDING_RESP_MAX];
+        let resp = &mut resp[..3 + 2 * N + 2];
+        self.communicate(Operation::ReadParam, &buf, resp, timeout)?;
 
-        Ok(())
+        if resp[2] as usize != 2 * N {
+            return Err(Error::PzemError);
+        }
+
+        let mut regs = [0u16; N];
+        for (i, reg) in regs.iter_mut().enumerate() {
+            *reg = ((resp[3 + 2 * i] as u16) << 8) | ((resp[4 + 2 * i] as u16) << 0);
+        }
+
+        Ok(regs)
+    }
+
+    /// Releases the underlying serial peripheral.
+    ///
+    /// If the last transaction timed out or was cancelled, the late response may still be
+    /// arriving, and would be received by the next user of the peripheral. Look
+    /// [`release_idle`](#method.release_idle).
+    pub fn release(self) -> Serial {
+        self.uart
+    }
+
+    /// Returns `true` if the last transaction was abandoned after sending the request,
+    /// i.e. it timed out or was cancelled, so that the response may still be arriving.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Releases the underlying serial peripheral once the line is idle.
+    ///
+    /// If the last transaction was abandoned, waits out its timeout discarding the late
+    /// response, followed by the turnaround delay, if any. Should bytes still be arriving
+    /// by then, gives the driver back with `Err(Error::BusBusy)`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let serial = match pzem.release_idle(Some((&mut tim, TIMEOUT))) {
+    ///     Ok(serial) => serial,
+    ///     Err((_pzem, e)) => panic!("PZEM004T bus not idle: {}", e),
+    /// };
+    /// ```
+    #[allow(clippy::type_complexity, clippy::result_large_err)]
+    pub fn release_idle<Tm: Timeout>(
+        mut self,
+        mut timeout: Tm,
+    ) -> Result<Serial, (Self, Error<WriteError, ReadError>)> {
+        if let Some(op) = self.pending {
+            if let Some((timer, time)) = timeout.get(op) {
+                timer.start(time);
+                while timer.wait().is_err() {
+                    match self.uart.drain(Some(&mut *timer), self.drain_limit) {
+                        Ok((n, _)) => {
+                            count!(self.stats.drained_bytes, n);
+                        }
+                        Err(e) => return Err((self, Error::ReadError(e))),
+                    }
+                }
+            }
+
+            if let Some(delay) = self.turnaround {
+                delay();
+            }
+
+            match self.uart.drain::<NoTimeout>(None, self.drain_cap()) {
+                Ok((0, _)) => self.pending = None,
+                Ok(_) => return Err((self, Error::BusBusy)),
+                Err(e) => return Err((self, Error::ReadError(e))),
+            }
+        }
+
+        Ok(self.uart)
+    }
+}
+
+impl<Serial, WriteError, ReadError> Pzem<Serial, Verified>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Sets the power alarm threshold value of the energy monitor, in W.
+    ///
+    /// Values outside of `1..=THRESHOLD_MAX` are refused with `Err(Error::InvalidThreshold(_))`
+    /// rather than clamped, as zero turns the alarm always on with some firmware versions.
+    /// Clamp them explicitly with [`clamp_threshold`](fn.clamp_threshold.html), or turn the
+    /// alarm off with [`disable_alarm`](#method.disable_alarm).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Will set the alarm threshold to 230 W:
+    /// pzem.set_threshold(230, Some((&mut tim, 2.hz()))).unwrap();
+    /// ```
+    pub fn set_threshold<Tm: Timeout>(
+        &mut self,
+        threshold: u16,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        match threshold {
+            0 => Err(Error::InvalidThreshold(ThresholdError::Zero)),
+            t if t > THRESHOLD_MAX => Err(Error::InvalidThreshold(ThresholdError::AboveMax)),
+            t => self.write_param(PARAM_THRESHOLD, t, timeout),
+        }
+    }
+
+    /// Turns the power alarm off, by setting the threshold to [`THRESHOLD_MAX`](constant.THRESHOLD_MAX.html).
+    pub fn disable_alarm<Tm: Timeout>(
+        &mut self,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        self.write_param(PARAM_THRESHOLD, THRESHOLD_MAX, timeout)
     }
 
     /// Sets the Modbus-RTU address of the energy monitor.
     ///
     /// Also updates the [`Pzem`](struct.Pzem.html) struct to refer to the sensor by the new address.
     ///
-    /// # Example
+    /// The change is verified by reading the address parameter on the new address. Should
+    /// the write or the verification fail, the sensor is looked for on both addresses: if it
+    /// answers on the new one, the change is considered successful (e.g. only the acknowledgement
+    /// was lost). Otherwise `Err(Error::AddrChangeFailed(_))` reports whether the sensor still
+    /// answers on the old address, to which the driver reverts.
+    ///
+    /// The errors preventing the request from being sent, e.g. `Err(Error::BusBusy)`, and the
+    /// exceptions of the sensor refusing the change are returned as they are.
     ///
-    ///     // Will set the slave address to 0x10:
-    ///     pzem.set_addr(0x10, Some(&mut tim, 2.hz())).unwrap();
+    /// # Example
     ///
-    pub fn set_addr<T: timer::CountDown>(
+    /// ```ignore
+    /// // Will set the slave address to 0x10:
+    /// pzem.set_addr(0x10, Some((&mut tim, 2.hz()))).unwrap();
+    /// ```
+    pub fn set_addr<Tm: Timeout>(
         &mut self,
         addr: u8,
-        timeout: Option<(&mut T, T::Time)>,
+        mut timeout: Tm,
     ) -> Result<(), Error<WriteError, ReadError>> {
-        if addr < ADDR_MIN || addr > ADDR_MAX {
+        if !(ADDR_MIN..=ADDR_MAX).contains(&addr) {
             return Err(Error::IllegalAddress);
         }
 
-        let mut buf: [u8; 8] = [
-            self.addr,               // Slave address
-            CMD_WRITE_PARAM,         // Function code: set internal parameter
-            (PARAM_ADDR >> 8) as u8, // Slave address parameter reg.
-            (PARAM_ADDR >> 0) as u8, // Slave address parameter reg.
-            0,                       // High byte of the address reg. is always 0
-            addr,                    // New slave address
-            0,                       // CRC
-            0,                       // CRC
+        // High byte of the address reg. is always 0
+        match self.write_param(PARAM_ADDR, addr as u16, &mut timeout) {
+            // The request may have been carried out, with the acknowledgement lost or garbled.
+            Ok(())
+            | Err(Error::TimedOut { .. })
+            | Err(Error::CrcMismatch)
+            | Err(Error::PzemError)
+            | Err(Error::WriteVerificationFailed)
+            | Err(Error::ProbableAddressConflict)
+            | Err(Error::Decode(_)) => {}
+            // The sensor has refused the change, or the request wasn't sent.
+            Err(e) => return Err(e),
+        }
+
+        let old = self.addr;
+        self.addr = addr;
+        if self.get_addr(&mut timeout).is_ok_and(|a| a == addr as u16) {
+            self.addr_changed(old, addr);
+            return Ok(());
+        }
+
+        log_warn!("PZEM004T {:#04x}: no answer on {:#04x}", old, addr);
+        self.addr = old;
+        let state = match self.get_addr(&mut timeout) {
+            Ok(_) => AddrState::Unchanged,
+            Err(_) => AddrState::Unknown,
+        };
+        self.record(Event::AddrChangeFailed);
+        Err(Error::AddrChangeFailed(state))
+    }
+
+    fn write_param<Tm: Timeout>(
+        &mut self,
+        param: u16,
+        value: u16,
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        match self.write_mode {
+            WriteMode::Single => self.write_single(param, value, timeout),
+            WriteMode::Multiple => self.write_multiple(param, value, timeout),
+            WriteMode::Auto => match self.write_single(param, value, &mut timeout) {
+                Err(Error::Exception(Exception::IllegalFunction)) => {
+                    log_warn!("PZEM004T {:#04x}: falling back to 0x10", self.addr);
+                    self.write_mode = WriteMode::Multiple;
+                    self.write_multiple(param, value, timeout)
+                }
+                res => res,
+            },
+        }
+    }
+
+    fn write_single<Tm: Timeout>(
+        &mut self,
+        param: u16,
+        value: u16,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut buf: [u8; REQ_LEN] = [
+            self.addr,          // Slave address
+            CMD_WRITE_PARAM,    // Function code: set internal parameter
+            (param >> 8) as u8, // Parameter register address
+            (param >> 0) as u8, // Parameter register address
+            (value >> 8) as u8, // Parameter value
+            (value >> 0) as u8, // Parameter value
+            0,                  // CRC
+            0,                  // CRC
         ];
 
-        crc_write(&mut buf);
+        crc_write(self.crc, &mut buf);
 
-        let mut resp: [u8; 8] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
+        let mut resp = [0u8; WRITE_RESP_LEN];
+        self.communicate(Operation::WriteParam, &buf, &mut resp, timeout)?;
 
-        self.addr = addr;
+        // Register address and value
+        self.check_echo(&buf[2..6], &resp[2..6])
+    }
 
-        Ok(())
+    fn write_multiple<Tm: Timeout>(
+        &mut self,
+        param: u16,
+        value: u16,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut buf: [u8; WRITE_MULTI_REQ_LEN] = [
+            self.addr,          // Slave address
+            CMD_WRITE_MULTI,    // Function code: write multiple registers
+            (param >> 8) as u8, // Starting register address
+            (param >> 0) as u8, // Starting register address
+            0,                  // Number of registers high byte
+            1,                  // Number of registers low byte
+            2,                  // Number of value bytes
+            (value >> 8) as u8, // Parameter value
+            (value >> 0) as u8, // Parameter value
+            0,                  // CRC
+            0,                  // CRC
+        ];
+
+        crc_write(self.crc, &mut buf);
+
+        let mut resp = [0u8; WRITE_MULTI_RESP_LEN];
+        self.communicate(Operation::WriteParam, &buf, &mut resp, timeout)?;
+
+        // Register address and number of registers
+        self.check_echo(&buf[2..6], &resp[2..6])
+    }
+
+    fn check_echo(&mut self, req: &[u8], resp: &[u8]) -> Result<(), Error<WriteError, ReadError>> {
+        if !self.echo_check || req == resp {
+            return Ok(());
+        }
+
+        log_warn!(
+            "PZEM004T {:#04x}: write echoed {:02x?} instead of {:02x?}",
+            self.addr,
+            resp,
+            req
+        );
+        self.record(Event::WriteVerificationFailed);
+        Err(Error::WriteVerificationFailed)
     }
 
     /// Sets the energy counting register back to 0.
-    pub fn reset_energy<T: timer::CountDown>(
+    pub fn reset_energy<Tm: Timeout>(
         &mut self,
-        timeout: Option<(&mut T, T::Time)>,
+        timeout: Tm,
     ) -> Result<(), Error<WriteError, ReadError>> {
-        let mut buf = [self.addr, CMD_RESET, 0, 0];
-        crc_write(&mut buf);
+        let mut buf: [u8; RESET_LEN] = [self.addr, CMD_RESET, 0, 0];
+        crc_write(self.crc, &mut buf);
 
-        let mut resp: [u8; 4] = unsafe { core::mem::MaybeUninit::uninit().assume_init() };
-        self.communicate(&buf, &mut resp, timeout)?;
+        let mut resp = [0u8; RESET_LEN];
+        self.communicate(Operation::Reset, &buf, &mut resp, timeout)?;
+        self.record(Event::EnergyReset);
 
         Ok(())
     }
 
-    /// Releases the underlying serial peripheral.
-    pub fn release(self) -> Serial {
-        self.uart
+    /// Sets the energy counting register back to 0 and confirms it by reading the measurements.
+    ///
+    /// Some clone units acknowledge the reset without actually performing it: in such case
+    /// returns `Err(Error::ResetFailed)`, if the energy read afterwards exceeds one LSB (1 Wh).
+    pub fn reset_energy_verified<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        self.reset_energy(&mut timeout)?;
+
+        let mut m = RawMeasurement::default();
+        self.read_raw(&mut m, &mut timeout)?;
+        if m.energy > 1 {
+            self.record(Event::ResetFailed);
+            return Err(Error::ResetFailed);
+        }
+
+        Ok(())
     }
 }
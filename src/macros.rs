@@ -0,0 +1,79 @@
+// Internal logging macros, which expand to nothing unless the `log` feature is enabled.
+
+#[cfg(feature = "log")]
+#[allow(unused_macros)]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { log::debug!($($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+#[allow(unused_macros)]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(feature = "log")]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { log::warn!($($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {};
+}
+
+/// Reads the measurements off the sensor, returning `Option<Measurement>`: `None` if the
+/// read failed, in which case the error is passed to the handler, if given.
+///
+/// The failed reads are still counted in the [`Stats`](struct.Stats.html) and recorded into
+/// the attached event log.
+///
+/// # Example
+///
+/// ```ignore
+/// loop {
+///     if let Some(m) = read_pzem!(pzem, tim, TIMEOUT) {
+///         display.show_power(m.power);
+///     }
+///
+///     let m = read_pzem!(pzem, tim, TIMEOUT, |e| hprintln!("PZEM004T: {}", e).unwrap());
+///     // ...
+/// }
+/// ```
+#[cfg(not(feature = "no-float"))]
+#[macro_export]
+macro_rules! read_pzem {
+    ($pzem:expr, $tim:expr, $timeout:expr) => {
+        $crate::read_pzem!($pzem, $tim, $timeout, |_| ())
+    };
+    ($pzem:expr, $tim:expr, $timeout:expr, $on_error:expr) => {{
+        let mut m = $crate::Measurement::default();
+        match $pzem.read(&mut m, Some((&mut $tim, $timeout))) {
+            Ok(()) => Some(m),
+            Err(e) => {
+                #[allow(clippy::redundant_closure_call)]
+                ($on_error)(e);
+                None
+            }
+        }
+    }};
+}
+
+// Increments a counter of the `Stats`, which are removed by the `size-opt` feature.
+#[cfg(not(feature = "size-opt"))]
+macro_rules! count {
+    ($stat:expr) => {
+        $stat = $stat.wrapping_add(1)
+    };
+    ($stat:expr, $n:expr) => {
+        $stat = $stat.wrapping_add($n)
+    };
+}
+
+#[cfg(feature = "size-opt")]
+macro_rules! count {
+    ($stat:expr) => {};
+    ($stat:expr, $n:expr) => {
+        let _ = $n;
+    };
+}
@@ -0,0 +1,59 @@
+use core::fmt::Write;
+
+use crate::Measurement;
+
+impl Measurement {
+    /// Emits each field of the measurement as a topic/payload pair, in the layout used
+    /// by ESPHome and Tasmota sensor states: `{base}/{field}`.
+    ///
+    /// The topics are `voltage`, `current`, `power`, `energy`, `frequency`, `power_factor`
+    /// and `alarm`, the payloads are the plain decimal values and `ON`/`OFF` for the alarm.
+    /// Both the topic and the payload are formatted into `buf`, which must fit the `base`
+    /// along with 32 more bytes, otherwise `Err(core::fmt::Error)` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut buf = heapless::String::<64>::new();
+    /// m.to_mqtt_topics("pzem/sensor", &mut buf, |topic, payload| {
+    ///     // pzem/sensor/voltage 230.1
+    ///     mqtt.publish(topic, payload.as_bytes());
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn to_mqtt_topics<F, const N: usize>(
+        &self,
+        base: &str,
+        buf: &mut heapless::String<N>,
+        mut f: F,
+    ) -> core::fmt::Result
+    where
+        F: FnMut(&str, &str),
+    {
+        // Field, value and number of decimal places.
+        let fields = [
+            ("voltage", self.voltage, 1),
+            ("current", self.current, 3),
+            ("power", self.power, 1),
+            ("energy", self.energy, 3),
+            ("frequency", self.frequency, 1),
+            ("power_factor", self.pf, 2),
+        ];
+
+        for &(field, value, precision) in fields.iter() {
+            buf.clear();
+            write!(buf, "{}/{}", base, field)?;
+            let len = buf.len();
+            write!(buf, "{:.*}", precision, value)?;
+
+            let (topic, payload) = buf.split_at(len);
+            f(topic, payload);
+        }
+
+        buf.clear();
+        write!(buf, "{}/alarm", base)?;
+        f(buf, if self.alarm { "ON" } else { "OFF" });
+
+        Ok(())
+    }
+}
@@ -2,21 +2,32 @@ extern crate nb;
 
 use hal::timer::CountDown;
 
-/// Empty struct to satisfy Rust's type requirements, when using `None` for the `timeout` parameter.
+use crate::{Operation, Timeout};
+
+/// Infallible timeout which never expires, for waiting indefinitely on the sensor.
+///
+/// It can be passed as the `timeout` parameter directly, or used as a timer satisfying Rust's
+/// type requirements: starting it does nothing and waiting on it never completes.
+///
 /// # Example
-/// ```rust
-/// pzem.communicate::<NoTimeout>(&mut m, None);
+/// ```ignore
+/// pzem.read(&mut m, NoTimeout);
+/// pzem.read(&mut m, None::<(&mut NoTimeout, ())>);
 /// ```
-///
-/// Will panic with `unreachable!()` if used incorrectly to signalize a programmer error.
+#[derive(Debug, Default, Copy, Clone)]
 pub struct NoTimeout;
 
 impl CountDown for NoTimeout {
     type Time = ();
-    fn start<T: Into<Self::Time>>(&mut self, _count: T) {
-        unreachable!();
-    }
+    fn start<T: Into<Self::Time>>(&mut self, _count: T) {}
     fn wait(&mut self) -> nb::Result<(), void::Void> {
-        unreachable!();
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+impl Timeout for NoTimeout {
+    type Timer = NoTimeout;
+    fn get(&mut self, _op: Operation) -> Option<(&mut NoTimeout, ())> {
+        None
     }
 }
@@ -0,0 +1,79 @@
+use hal::serial;
+
+#[cfg(not(feature = "no-float"))]
+use crate::Measurement;
+use crate::{Error, Pzem, RawMeasurement, Timeout};
+
+/// Measurement along with what the driver knows about the exchange which read it, for the
+/// polling logic adapting to the quality of the link, e.g. slowing down when it gets flaky.
+///
+/// Returned by [`Pzem::read_outcome`](struct.Pzem.html#method.read_outcome) and
+/// [`Pzem::read_raw_outcome`](struct.Pzem.html#method.read_raw_outcome).
+#[derive(Debug, Copy, Clone)]
+pub struct ReadOutcome<M> {
+    pub measurement: M,
+    /// Number of exchanges retried after a collision, look [`Backoff`](struct.Backoff.html).
+    pub retries_used: u8,
+    /// Ticks from the start of the first exchange to the end of the response, with a clock
+    /// attached by [`Pzem::with_phase_clock`](struct.Pzem.html#method.with_phase_clock).
+    /// Always `None` with the `size-opt` feature.
+    pub latency_ticks: Option<u32>,
+    /// Number of stray bytes discarded to find the response, whether drained before the
+    /// requests or skipped to resynchronize on its header.
+    pub resyncs: u32,
+}
+
+// Metadata of the most recent operation, gathered by `communicate`.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct ExchangeInfo {
+    pub(crate) retries: u8,
+    pub(crate) latency: Option<u32>,
+    pub(crate) resyncs: u32,
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Reads the measurements off the sensor like [`read`](#method.read), along with the
+    /// metadata of the exchange.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let outcome = pzem.read_outcome(Some((&mut tim, TIMEOUT)))?;
+    /// if outcome.retries_used > 0 || outcome.resyncs > 0 {
+    ///     period = (period * 2).min(MAX_PERIOD);
+    /// }
+    /// ```
+    #[cfg(not(feature = "no-float"))]
+    pub fn read_outcome<Tm: Timeout>(
+        &mut self,
+        timeout: Tm,
+    ) -> Result<ReadOutcome<Measurement>, Error<WriteError, ReadError>> {
+        let outcome = self.read_raw_outcome(timeout)?;
+
+        Ok(ReadOutcome {
+            measurement: self.convert(&outcome.measurement),
+            retries_used: outcome.retries_used,
+            latency_ticks: outcome.latency_ticks,
+            resyncs: outcome.resyncs,
+        })
+    }
+
+    /// Reads the raw measurement registers off the sensor like [`read_raw`](#method.read_raw),
+    /// along with the metadata of the exchange.
+    pub fn read_raw_outcome<Tm: Timeout>(
+        &mut self,
+        timeout: Tm,
+    ) -> Result<ReadOutcome<RawMeasurement>, Error<WriteError, ReadError>> {
+        let mut m = RawMeasurement::default();
+        self.read_raw(&mut m, timeout)?;
+
+        Ok(ReadOutcome {
+            measurement: m,
+            retries_used: self.exchange.retries,
+            latency_ticks: self.exchange.latency,
+            resyncs: self.exchange.resyncs,
+        })
+    }
+}
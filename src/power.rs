@@ -0,0 +1,49 @@
+use crate::Measurement;
+
+/// Starting current of the sensor in A: below it, the power factor reads zero
+/// and the power is not reliable.
+pub const CURRENT_MIN: f32 = 0.01;
+
+impl Measurement {
+    /// Returns `true` if the current is below the starting current of the sensor,
+    /// in which case the power factor reads zero regardless of the load.
+    pub fn pf_unreliable(&self) -> bool {
+        self.current < CURRENT_MIN
+    }
+
+    /// Returns the apparent power in VA, or `None` if the power factor is unreliable.
+    pub fn apparent_power(&self) -> Option<f32> {
+        if self.pf_unreliable() {
+            return None;
+        }
+
+        Some(self.voltage * self.current)
+    }
+
+    /// Returns the magnitude of the reactive power in var, or `None` if the power factor is unreliable.
+    pub fn reactive_power(&self) -> Option<f32> {
+        let s = self.apparent_power()?;
+        let q2 = s * s - self.power * self.power;
+
+        // The rounding of the registers may make the power slightly exceed the apparent power.
+        Some(sqrt(q2.max(0.0)))
+    }
+}
+
+// Square root by Newton's method, as `f32::sqrt` is not available in `core`.
+pub(crate) fn sqrt(x: f32) -> f32 {
+    if x == 0.0 {
+        return 0.0;
+    }
+
+    let mut y = x.max(1.0);
+    for _ in 0..32 {
+        let next = 0.5 * (y + x / y);
+        if next >= y {
+            break;
+        }
+        y = next;
+    }
+
+    y
+}
@@ -0,0 +1,19 @@
+//! Commonly used types of the driver, to be imported at once.
+//!
+//! ```ignore
+//! use pzem004t::prelude::*;
+//! ```
+
+pub use crate::{
+    Backoff, Config, CrcMode, CrcProvider, Error, Exception, FromRegisters, NoTimeout, Operation,
+    Pzem, RawMeasurement, Session, Timeout, Timeouts, Unverified, Verified, WriteMode,
+};
+
+#[cfg(not(feature = "size-opt"))]
+pub use crate::Stats;
+
+#[cfg(not(feature = "no-float"))]
+pub use crate::{Correction, Measurement, Poller, Scaling, Validator};
+
+#[cfg(feature = "std")]
+pub use crate::{StdIo, StdTimer};
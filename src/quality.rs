@@ -0,0 +1,231 @@
+use crate::{Measurement, MeasurementSink};
+
+/// Voltage event emitted by an [`EventDetector`](struct.EventDetector.html).
+///
+/// The durations are counted in samples, i.e. in the measurements fed to the detector.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VoltageEvent {
+    /// The voltage has stayed below the sag threshold for the minimum duration.
+    SagStart {
+        /// Lowest voltage in V so far.
+        voltage: f32,
+    },
+    /// The voltage has recovered from a sag.
+    SagEnd {
+        /// Drop of the lowest voltage below the nominal one, in V.
+        depth: f32,
+        duration: u32,
+    },
+    /// The voltage has stayed above the swell threshold for the minimum duration.
+    SwellStart {
+        /// Highest voltage in V so far.
+        voltage: f32,
+    },
+    /// The voltage has recovered from a swell.
+    SwellEnd {
+        /// Rise of the highest voltage above the nominal one, in V.
+        height: f32,
+        duration: u32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Excursion {
+    Sag,
+    Swell,
+}
+
+/// Detector of the voltage sags and swells, for power quality monitoring.
+///
+/// A sag starts once the voltage has stayed below `sag_below` for `min_samples` consecutive
+/// measurements, and ends with the first measurement back at or above it; likewise for the
+/// swells above `swell_above`. The shorter excursions are ignored as noise.
+///
+/// # Example
+/// ```
+/// use pzem004t::{EventDetector, VoltageEvent};
+///
+/// let mut detector = EventDetector::new(230.0, 207.0, 253.0, 2);
+/// assert_eq!(detector.update_voltage(200.0), None); // Not yet, one sample only
+/// assert_eq!(detector.update_voltage(190.0), Some(VoltageEvent::SagStart { voltage: 190.0 }));
+/// assert_eq!(detector.update_voltage(195.0), None);
+/// assert_eq!(
+///     detector.update_voltage(229.0),
+///     Some(VoltageEvent::SagEnd { depth: 40.0, duration: 3 })
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EventDetector {
+    /// Nominal voltage in V, the depths and the heights are relative to.
+    pub nominal: f32,
+    /// Voltage in V below which a sag starts.
+    pub sag_below: f32,
+    /// Voltage in V above which a swell starts.
+    pub swell_above: f32,
+    /// Number of consecutive samples required to start an event.
+    pub min_samples: u32,
+    current: Option<Excursion>,
+    active: bool,
+    duration: u32,
+    extreme: f32,
+}
+
+impl EventDetector {
+    pub const fn new(nominal: f32, sag_below: f32, swell_above: f32, min_samples: u32) -> Self {
+        Self {
+            nominal,
+            sag_below,
+            swell_above,
+            min_samples,
+            current: None,
+            active: false,
+            duration: 0,
+            extreme: 0.0,
+        }
+    }
+
+    /// Returns `true` during a sag.
+    pub fn in_sag(&self) -> bool {
+        self.active && self.current == Some(Excursion::Sag)
+    }
+
+    /// Returns `true` during a swell.
+    pub fn in_swell(&self) -> bool {
+        self.active && self.current == Some(Excursion::Swell)
+    }
+
+    /// Feeds the voltage in V, returning the event it triggers, if any.
+    pub fn update_voltage(&mut self, voltage: f32) -> Option<VoltageEvent> {
+        let excursion = if voltage < self.sag_below {
+            Some(Excursion::Sag)
+        } else if voltage > self.swell_above {
+            Some(Excursion::Swell)
+        } else {
+            None
+        };
+
+        if excursion != self.current {
+            let event = match self.current {
+                Some(Excursion::Sag) if self.active => Some(VoltageEvent::SagEnd {
+                    depth: self.nominal - self.extreme,
+                    duration: self.duration,
+                }),
+                Some(Excursion::Swell) if self.active => Some(VoltageEvent::SwellEnd {
+                    height: self.extreme - self.nominal,
+                    duration: self.duration,
+                }),
+                _ => None,
+            };
+
+            self.current = excursion;
+            self.active = false;
+            self.duration = 0;
+            self.extreme = voltage;
+            if event.is_some() {
+                // The new excursion, if any, counts this sample already.
+                self.duration = excursion.map_or(0, |_| 1);
+                return event;
+            }
+        }
+
+        let excursion = self.current?;
+        self.duration += 1;
+        self.extreme = match excursion {
+            Excursion::Sag => self.extreme.min(voltage),
+            Excursion::Swell => self.extreme.max(voltage),
+        };
+
+        if self.active || self.duration < self.min_samples {
+            return None;
+        }
+
+        self.active = true;
+        Some(match excursion {
+            Excursion::Sag => VoltageEvent::SagStart {
+                voltage: self.extreme,
+            },
+            Excursion::Swell => VoltageEvent::SwellStart {
+                voltage: self.extreme,
+            },
+        })
+    }
+
+    /// Feeds the voltage of the measurement, returning the event it triggers, if any.
+    pub fn update(&mut self, m: &Measurement) -> Option<VoltageEvent> {
+        self.update_voltage(m.voltage)
+    }
+}
+
+/// Monitor of the frequency excursions, e.g. of a generator or an inverter.
+///
+/// The alarm goes on once the frequency has deviated from `nominal` by more than
+/// `max_deviation` for `samples` consecutive measurements, and goes off likewise once it has
+/// stayed within the bounds, so that a single noisy reading doesn't toggle it.
+///
+/// # Example
+/// ```
+/// use pzem004t::FrequencyMonitor;
+///
+/// let mut monitor = FrequencyMonitor::new(50.0, 0.5, 2);
+/// assert!(!monitor.update_frequency(50.7)); // Not yet, one sample only
+/// assert!(!monitor.update_frequency(50.1));
+/// assert!(!monitor.update_frequency(49.4));
+/// assert!(monitor.update_frequency(49.3));
+/// assert!(monitor.update_frequency(50.0)); // Not yet, one sample only
+/// assert!(!monitor.update_frequency(50.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrequencyMonitor {
+    /// Nominal frequency in Hz.
+    pub nominal: f32,
+    /// Largest tolerated deviation from the nominal frequency in Hz.
+    pub max_deviation: f32,
+    /// Number of consecutive samples required to change the state.
+    pub samples: u8,
+    on: bool,
+    count: u8,
+}
+
+impl FrequencyMonitor {
+    pub const fn new(nominal: f32, max_deviation: f32, samples: u8) -> Self {
+        Self {
+            nominal,
+            max_deviation,
+            samples,
+            on: false,
+            count: 0,
+        }
+    }
+
+    /// Returns the current state of the alarm.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Feeds the frequency in Hz, returning the new state of the alarm.
+    pub fn update_frequency(&mut self, frequency: f32) -> bool {
+        let excursion = (frequency - self.nominal).abs() > self.max_deviation;
+        if excursion == self.on {
+            self.count = 0;
+        } else {
+            self.count = self.count.saturating_add(1);
+            if self.count >= self.samples {
+                self.on = excursion;
+                self.count = 0;
+            }
+        }
+
+        self.on
+    }
+
+    /// Feeds the frequency of the measurement, returning the new state of the alarm.
+    pub fn update(&mut self, m: &Measurement) -> bool {
+        self.update_frequency(m.frequency)
+    }
+}
+
+impl MeasurementSink for FrequencyMonitor {
+    fn push(&mut self, m: &Measurement) {
+        self.update(m);
+    }
+}
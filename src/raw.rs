@@ -0,0 +1,60 @@
+use crate::regs;
+
+/// Measurement results stored as the raw integer register values, in the sensor resolution.
+///
+/// Unlike [`Measurement`](struct.Measurement.html), it involves no floating point arithmetic,
+/// and is available with the `no-float` feature.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RawMeasurement {
+    /// Voltage in 0.1 V.
+    pub voltage: u16,
+    /// Current in mA.
+    pub current: u32,
+    /// Power in 0.1 W.
+    pub power: u32,
+    /// Energy in Wh.
+    pub energy: u32,
+    /// Frequency in 0.1 Hz.
+    pub frequency: u16,
+    /// Power factor in 0.01.
+    pub pf: u16,
+    pub alarm: bool,
+}
+
+impl RawMeasurement {
+    /// Returns `true` if the current is below the starting current of the sensor (10 mA),
+    /// in which case the power factor reads zero regardless of the load.
+    pub const fn pf_unreliable(&self) -> bool {
+        self.current < 10
+    }
+
+    /// Decodes the whole block of the 10 measurement registers.
+    pub const fn from_registers(regs: &[u16; 10]) -> Self {
+        regs::decode(&regs::MEASUREMENT, regs)
+    }
+
+    /// Encodes the measurement into the block of the 10 measurement registers,
+    /// as sent by the sensor.
+    pub const fn to_registers(&self) -> [u16; 10] {
+        let mut regs = [0; 10];
+        regs::encode(&regs::MEASUREMENT, self, &mut regs);
+        regs
+    }
+}
+
+#[cfg(not(feature = "no-float"))]
+impl From<RawMeasurement> for crate::Measurement {
+    fn from(raw: RawMeasurement) -> Self {
+        use crate::decode;
+
+        Self {
+            voltage: decode::voltage(raw.voltage),
+            current: decode::current(raw.current),
+            power: decode::power(raw.power),
+            energy: decode::energy(raw.energy),
+            frequency: decode::frequency(raw.frequency),
+            pf: decode::pf(raw.pf),
+            alarm: raw.alarm,
+        }
+    }
+}
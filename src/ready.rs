@@ -0,0 +1,72 @@
+use hal::blocking::delay::DelayMs;
+use hal::serial;
+
+use crate::{Error, Pzem, RawMeasurement, Timeout};
+
+/// Maximal number of reads by [`Pzem::wait_ready`](struct.Pzem.html#method.wait_ready).
+pub const READY_ATTEMPTS: u8 = 20;
+
+/// Delay between the reads by [`Pzem::wait_ready`](struct.Pzem.html#method.wait_ready) in ms.
+pub const READY_INTERVAL: u16 = 100;
+
+// Measuring range of the voltage, 80-260 V, in raw units.
+const VOLTAGE_MIN: u16 = 800;
+const VOLTAGE_MAX: u16 = 2600;
+// Difference of the voltages of two consecutive reads of a settled sensor, 5 V.
+const VOLTAGE_JITTER: u16 = 50;
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Polls the sensor until it has warmed up after the power-on, returning the first
+    /// measurement deemed valid.
+    ///
+    /// The sensor answers with invalid data for a short while after the power-up. It is
+    /// considered ready once two consecutive reads succeed with consistent values: both
+    /// voltages within the measuring range of 80-260 V and within 5 V of each other.
+    /// The failed reads are retried, up to [`READY_ATTEMPTS`](constant.READY_ATTEMPTS.html)
+    /// reads in total, after which the error of the last read is returned, or
+    /// `Err(Error::Implausible)` if it succeeded.
+    ///
+    /// The reads are spaced by [`READY_INTERVAL`](constant.READY_INTERVAL.html) ms with
+    /// `delay`, so that the attempts span the warm-up of the sensor however fast the link.
+    /// The wait takes at most 1.9 s of delays plus 20 times the timeout of a read.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let m = pzem.wait_ready(&mut delay, Some((&mut tim, TIMEOUT)))?;
+    /// display.show(&Measurement::from(m));
+    /// ```
+    pub fn wait_ready<D: DelayMs<u16>, Tm: Timeout>(
+        &mut self,
+        delay: &mut D,
+        mut timeout: Tm,
+    ) -> Result<RawMeasurement, Error<WriteError, ReadError>> {
+        let mut prev: Option<RawMeasurement> = None;
+        let mut res = Err(Error::Implausible);
+        for attempt in 0..READY_ATTEMPTS {
+            if attempt > 0 {
+                delay.delay_ms(READY_INTERVAL);
+            }
+
+            let mut m = RawMeasurement::default();
+            res = self.read_raw(&mut m, &mut timeout);
+
+            let m = match res {
+                Ok(()) if (VOLTAGE_MIN..=VOLTAGE_MAX).contains(&m.voltage) => m,
+                _ => {
+                    prev = None;
+                    continue;
+                }
+            };
+
+            if prev.is_some_and(|prev| prev.voltage.abs_diff(m.voltage) <= VOLTAGE_JITTER) {
+                return Ok(m);
+            }
+            prev = Some(m);
+        }
+
+        res.and(Err(Error::Implausible))
+    }
+}
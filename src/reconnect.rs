@@ -0,0 +1,194 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::std_io::would_block;
+
+/// Policy of reopening the stream lost by [`Reconnecting`](struct.Reconnecting.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Minimal interval between the attempts to reopen the stream.
+    pub interval: Duration,
+    /// Number of consecutive failed attempts after which to give up, or `None` to retry forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    /// Retries forever, once per second.
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Status of the stream of [`Reconnecting`](struct.Reconnecting.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinkStatus {
+    Connected,
+    /// The stream failed and has been closed, waiting to be reopened.
+    Disconnected,
+    /// The stream has been reopened after having been lost.
+    Reconnected,
+}
+
+/// `std::io` stream reopened on errors, so that a long-running daemon survives the USB
+/// adapter being unplugged and plugged back, to be wrapped in [`StdIo`](struct.StdIo.html).
+///
+/// Any error other than the ones reported as `WouldBlock` by `StdIo`, or the end of the
+/// stream, closes the stream and fails the operation. The following operations try reopening it with `open`, as often as
+/// the [`RetryPolicy`](struct.RetryPolicy.html) allows, failing with `ErrorKind::NotConnected`
+/// until it succeeds.
+///
+/// # Example
+/// ```ignore
+/// let port = Reconnecting::new(
+///     || serialport::new("/dev/ttyUSB0", 9600).timeout(Duration::from_millis(10)).open().map_err(io::Error::from),
+///     RetryPolicy::default(),
+/// )?
+/// .with_hook(|status| eprintln!("PZEM004T link: {:?}", status));
+///
+/// let mut pzem = Pzem::new(StdIo::new(port), None).unwrap();
+/// loop {
+///     if let Err(e) = pzem.read(&mut m, Some((&mut tim, TIMEOUT))) {
+///         eprintln!("{}", e);
+///     }
+///     // ...
+/// }
+/// ```
+pub struct Reconnecting<T, F> {
+    inner: Option<T>,
+    open: F,
+    policy: RetryPolicy,
+    status: LinkStatus,
+    attempts: u32,
+    last_attempt: Option<Instant>,
+    hook: Option<fn(LinkStatus)>,
+}
+
+impl<T, F: FnMut() -> io::Result<T>> Reconnecting<T, F> {
+    /// Opens the stream with `open`, which is kept to reopen it.
+    pub fn new(mut open: F, policy: RetryPolicy) -> io::Result<Self> {
+        Ok(Self {
+            inner: Some(open()?),
+            open,
+            policy,
+            status: LinkStatus::Connected,
+            attempts: 0,
+            last_attempt: None,
+            hook: None,
+        })
+    }
+
+    /// Calls `hook` whenever the stream is lost or reopened.
+    pub fn with_hook(mut self, hook: fn(LinkStatus)) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Returns the status of the stream, `Reconnected` being reported once after reopening it.
+    pub fn take_status(&mut self) -> LinkStatus {
+        match self.status {
+            LinkStatus::Reconnected => {
+                self.status = LinkStatus::Connected;
+                LinkStatus::Reconnected
+            }
+            status => status,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Returns the number of consecutive failed attempts to reopen the stream.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns a mutable reference to the underlying stream, if open.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.inner.as_mut()
+    }
+
+    /// Reopens the stream at once, disregarding the retry policy, e.g. after having given up.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        self.inner = None;
+        self.attempts = 0;
+        self.last_attempt = None;
+        self.reopen()
+    }
+
+    fn set_status(&mut self, status: LinkStatus) {
+        self.status = status;
+        if let Some(hook) = self.hook {
+            hook(status);
+        }
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        let gave_up = self
+            .policy
+            .max_attempts
+            .is_some_and(|max| self.attempts >= max);
+        let early = self
+            .last_attempt
+            .is_some_and(|last| now.duration_since(last) < self.policy.interval);
+        if gave_up || early {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "disconnected"));
+        }
+
+        self.last_attempt = Some(now);
+        match (self.open)() {
+            Ok(inner) => {
+                self.inner = Some(inner);
+                self.attempts = 0;
+                self.set_status(LinkStatus::Reconnected);
+                Ok(())
+            }
+            Err(e) => {
+                self.attempts = self.attempts.saturating_add(1);
+                Err(io::Error::new(io::ErrorKind::NotConnected, e))
+            }
+        }
+    }
+
+    // Runs the operation on the stream, reopening it first if lost and closing it on failure.
+    fn with<R>(&mut self, f: impl FnOnce(&mut T) -> io::Result<R>) -> io::Result<R> {
+        if self.inner.is_none() {
+            self.reopen()?;
+        }
+
+        let res = f(self.inner.as_mut().unwrap());
+        if let Err(ref e) = res {
+            if !would_block(e) {
+                self.inner = None;
+                self.last_attempt = Some(Instant::now());
+                self.set_status(LinkStatus::Disconnected);
+            }
+        }
+
+        res
+    }
+}
+
+impl<T: io::Read, F: FnMut() -> io::Result<T>> io::Read for Reconnecting<T, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The end of the stream is the link lost, like the errors.
+        self.with(|inner| match inner.read(buf)? {
+            0 if !buf.is_empty() => Err(io::ErrorKind::UnexpectedEof.into()),
+            n => Ok(n),
+        })
+    }
+}
+
+impl<T: io::Write, F: FnMut() -> io::Result<T>> io::Write for Reconnecting<T, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.with(|inner| inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.with(|inner| inner.flush())
+    }
+}
@@ -0,0 +1,230 @@
+//! Register map of the sensor, as data.
+//!
+//! Every quantity is described by a [`RegisterInfo`](struct.RegisterInfo.html), so that the
+//! generic tools (register dumps, emulators) can present the registers symbolically. The
+//! driver decodes the measurements by the same table, with [`decode`](fn.decode.html).
+//!
+//! Supporting a model with another register layout takes a table of its own, rather than
+//! another decoder: the [`Quantity`](enum.Quantity.html) of each entry selects the field of
+//! [`RawMeasurement`](../struct.RawMeasurement.html) it is decoded into.
+//!
+//! # Example
+//!
+//! ```
+//! use pzem004t::regs;
+//!
+//! let block = [2301, 0x86a0, 0x0001, 0, 0, 0, 0, 500, 99, 0];
+//! for info in regs::MEASUREMENT.iter() {
+//!     println!("{:#06x} {}: {} {}", info.addr, info.name, info.raw(&block), info.unit);
+//! }
+//! assert_eq!(regs::CURRENT.raw(&block), 100_000);
+//! assert_eq!(regs::find("frequency"), Some(&regs::FREQUENCY));
+//! ```
+
+use crate::RawMeasurement;
+
+/// Quantity held in the registers described by a [`RegisterInfo`](struct.RegisterInfo.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Quantity {
+    Voltage,
+    Current,
+    Power,
+    Energy,
+    Frequency,
+    Pf,
+    Alarm,
+    /// A parameter, not decoded into the measurement.
+    Parameter,
+}
+
+/// Description of a quantity held in one or more registers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub name: &'static str,
+    pub quantity: Quantity,
+    /// Address of the first register.
+    pub addr: u16,
+    /// Number of the registers, the low word being at the lower address.
+    pub size: u8,
+    /// Resolution of the raw value: one `unit` is `divisor` raw.
+    pub divisor: u32,
+    /// Unit of the quantity, empty for the dimensionless ones.
+    pub unit: &'static str,
+}
+
+impl RegisterInfo {
+    /// Extracts the raw value from the block of the measurement registers, which starts at
+    /// the address `0x0000`.
+    ///
+    /// # Panics
+    ///
+    /// If the registers of the quantity lie past the end of `regs`.
+    pub const fn raw(&self, regs: &[u16]) -> u32 {
+        let i = self.addr as usize;
+        match self.size {
+            1 => regs[i] as u32,
+            _ => crate::decode::decode_u32_registers(regs[i + 1], regs[i]),
+        }
+    }
+
+    /// Stores the raw value into the block of the measurement registers, the reverse of
+    /// [`raw`](#method.raw).
+    ///
+    /// # Panics
+    ///
+    /// If the registers of the quantity lie past the end of `regs`.
+    pub const fn write(&self, regs: &mut [u16], raw: u32) {
+        let i = self.addr as usize;
+        regs[i] = raw as u16;
+        if self.size > 1 {
+            regs[i + 1] = (raw >> 16) as u16;
+        }
+    }
+
+    /// Converts the raw value into `unit`.
+    #[cfg(not(feature = "no-float"))]
+    pub fn scale(&self, raw: u32) -> f32 {
+        raw as f32 / self.divisor as f32
+    }
+}
+
+const fn reg(
+    name: &'static str,
+    quantity: Quantity,
+    addr: u16,
+    size: u8,
+    divisor: u32,
+    unit: &'static str,
+) -> RegisterInfo {
+    RegisterInfo {
+        name,
+        quantity,
+        addr,
+        size,
+        divisor,
+        unit,
+    }
+}
+
+pub const VOLTAGE: RegisterInfo = reg("voltage", Quantity::Voltage, 0x0000, 1, 10, "V");
+pub const CURRENT: RegisterInfo = reg("current", Quantity::Current, 0x0001, 2, 1000, "A");
+pub const POWER: RegisterInfo = reg("power", Quantity::Power, 0x0003, 2, 10, "W");
+pub const ENERGY: RegisterInfo = reg("energy", Quantity::Energy, 0x0005, 2, 1000, "kWh");
+pub const FREQUENCY: RegisterInfo = reg("frequency", Quantity::Frequency, 0x0007, 1, 10, "Hz");
+pub const PF: RegisterInfo = reg("pf", Quantity::Pf, 0x0008, 1, 100, "");
+/// `0xffff` when the alarm is on.
+pub const ALARM: RegisterInfo = reg("alarm", Quantity::Alarm, 0x0009, 1, 1, "");
+
+/// Power alarm threshold, a holding register.
+pub const THRESHOLD: RegisterInfo = reg("threshold", Quantity::Parameter, 0x0001, 1, 1, "W");
+/// Modbus-RTU address of the sensor, a holding register.
+pub const ADDRESS: RegisterInfo = reg("address", Quantity::Parameter, 0x0002, 1, 1, "");
+
+/// The measurement (input) registers, in the order of the addresses.
+pub const MEASUREMENT: [RegisterInfo; 7] = [VOLTAGE, CURRENT, POWER, ENERGY, FREQUENCY, PF, ALARM];
+
+/// The parameter (holding) registers, in the order of the addresses.
+pub const PARAMETERS: [RegisterInfo; 2] = [THRESHOLD, ADDRESS];
+
+/// Looks up the register of the quantity by its name.
+pub fn find(name: &str) -> Option<&'static RegisterInfo> {
+    MEASUREMENT
+        .iter()
+        .chain(PARAMETERS.iter())
+        .find(|info| info.name == name)
+}
+
+/// Decodes the block of the measurement registers by `table`.
+///
+/// The quantities missing from the table are left zero, and the parameters are skipped.
+///
+/// # Example
+///
+/// ```
+/// use pzem004t::regs::{self, Quantity, RegisterInfo};
+///
+/// // DC model without the frequency and the power factor, current in 0.01 A.
+/// const DC: [RegisterInfo; 4] = [
+///     regs::VOLTAGE,
+///     RegisterInfo {
+///         name: "current",
+///         quantity: Quantity::Current,
+///         addr: 0x0001,
+///         size: 1,
+///         divisor: 100,
+///         unit: "A",
+///     },
+///     RegisterInfo { addr: 0x0002, ..regs::POWER },
+///     RegisterInfo { addr: 0x0004, ..regs::ENERGY },
+/// ];
+///
+/// let m = regs::decode(&DC, &[1200, 150, 1800, 0, 42, 0]);
+/// assert_eq!((m.voltage, m.current, m.power, m.energy, m.frequency), (1200, 150, 1800, 42, 0));
+///
+/// let mut block = [0; 6];
+/// regs::encode(&DC, &m, &mut block);
+/// assert_eq!(block, [1200, 150, 1800, 0, 42, 0]);
+/// ```
+///
+/// # Panics
+///
+/// If the registers of any entry other than a parameter lie past the end of `regs`.
+pub const fn decode(table: &[RegisterInfo], regs: &[u16]) -> RawMeasurement {
+    let mut m = RawMeasurement {
+        voltage: 0,
+        current: 0,
+        power: 0,
+        energy: 0,
+        frequency: 0,
+        pf: 0,
+        alarm: false,
+    };
+
+    let mut i = 0;
+    while i < table.len() {
+        // The parameters lie in the holding registers, not in `regs`.
+        let quantity = table[i].quantity;
+        if !matches!(quantity, Quantity::Parameter) {
+            let raw = table[i].raw(regs);
+            match quantity {
+                Quantity::Voltage => m.voltage = raw as u16,
+                Quantity::Current => m.current = raw,
+                Quantity::Power => m.power = raw,
+                Quantity::Energy => m.energy = raw,
+                Quantity::Frequency => m.frequency = raw as u16,
+                Quantity::Pf => m.pf = raw as u16,
+                Quantity::Alarm => m.alarm = raw != 0,
+                Quantity::Parameter => {}
+            }
+        }
+        i += 1;
+    }
+
+    m
+}
+
+/// Encodes the measurement into the block of the measurement registers by `table`,
+/// the reverse of [`decode`](fn.decode.html).
+///
+/// # Panics
+///
+/// If the registers of any entry other than a parameter lie past the end of `regs`.
+pub const fn encode(table: &[RegisterInfo], m: &RawMeasurement, regs: &mut [u16]) {
+    let mut i = 0;
+    while i < table.len() {
+        let raw = match table[i].quantity {
+            Quantity::Voltage => Some(m.voltage as u32),
+            Quantity::Current => Some(m.current),
+            Quantity::Power => Some(m.power),
+            Quantity::Energy => Some(m.energy),
+            Quantity::Frequency => Some(m.frequency as u32),
+            Quantity::Pf => Some(m.pf as u32),
+            Quantity::Alarm => Some(if m.alarm { 0xffff } else { 0 }),
+            Quantity::Parameter => None,
+        };
+        if let Some(raw) = raw {
+            table[i].write(regs, raw);
+        }
+        i += 1;
+    }
+}
@@ -0,0 +1,196 @@
+use core::cell::RefCell;
+use core::str::{Lines, SplitWhitespace};
+
+use hal::{serial, timer};
+
+use crate::Direction;
+
+/// Divergence of the driver from a capture played back by [`Replay`](struct.Replay.html).
+/// The lines are numbered from 1, counting the comments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The driver sent `written` where the capture has `expected`.
+    Mismatch {
+        line: usize,
+        expected: u8,
+        written: u8,
+    },
+    /// The driver sent `written` while the capture has response bytes left to receive, or
+    /// after the end of the capture (`line` is then the last one).
+    Unexpected { line: usize, written: u8 },
+    /// The line isn't a frame written by [`CaptureWriter`](struct.CaptureWriter.html).
+    Malformed { line: usize },
+}
+
+/// Serial port playing back a capture written by [`CaptureWriter`](struct.CaptureWriter.html),
+/// turning the session of a bug report into a regression test.
+///
+/// The requests sent by the driver are checked against the `tx` frames of the capture, byte
+/// by byte, and the `rx` frames are received in their place. The serial traits are
+/// implemented for `&Replay`, so that the [`timer`](#method.timer) can be borrowed along: it
+/// expires as soon as the capture has nothing left to receive before the next request, which
+/// replays the timeouts of the session without waiting for them. Blank lines and the lines
+/// starting with `#` are skipped, for the annotations of the reporter.
+///
+/// Only available with the `test-support` feature.
+///
+/// # Example
+///
+/// ```ignore
+/// let replay = Replay::new(include_str!("captures/issue-42.txt"));
+/// let mut pzem = Pzem::new(&replay, Some(0x01)).unwrap();
+/// let mut m = RawMeasurement::default();
+/// let res = pzem.read_raw(&mut m, Some((&mut replay.timer(), 0)));
+/// assert!(matches!(res, Err(Error::CrcMismatch)));
+/// assert!(replay.is_finished());
+/// ```
+pub struct Replay<'a> {
+    cursor: RefCell<Cursor<'a>>,
+}
+
+struct Cursor<'a> {
+    lines: Lines<'a>,
+    line: usize,
+    tick: u32,
+    direction: Direction,
+    bytes: SplitWhitespace<'a>,
+    next: Option<u8>,
+}
+
+impl<'a> Cursor<'a> {
+    // Returns the next byte of the capture, without consuming it.
+    fn peek(&mut self) -> Result<Option<(Direction, u8)>, ReplayError> {
+        loop {
+            if let Some(b) = self.next {
+                return Ok(Some((self.direction, b)));
+            }
+
+            let malformed = ReplayError::Malformed { line: self.line };
+            if let Some(hex) = self.bytes.next() {
+                if hex.len() != 2 {
+                    return Err(malformed);
+                }
+                self.next = Some(u8::from_str_radix(hex, 16).map_err(|_| malformed)?);
+                continue;
+            }
+
+            let line = match self.lines.next() {
+                Some(line) => line.trim(),
+                None => return Ok(None),
+            };
+            self.line += 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let malformed = ReplayError::Malformed { line: self.line };
+            let mut words = line.split_whitespace();
+            self.tick = words
+                .next()
+                .and_then(|tick| tick.parse().ok())
+                .ok_or(malformed)?;
+            self.direction = match words.next() {
+                Some("tx") => Direction::Tx,
+                Some("rx") => Direction::Rx,
+                _ => return Err(malformed),
+            };
+            self.bytes = words;
+        }
+    }
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(capture: &'a str) -> Self {
+        Self {
+            cursor: RefCell::new(Cursor {
+                lines: capture.lines(),
+                line: 0,
+                tick: 0,
+                direction: Direction::Tx,
+                bytes: "".split_whitespace(),
+                next: None,
+            }),
+        }
+    }
+
+    /// Returns the timer expiring once the capture has nothing left to receive.
+    pub fn timer(&self) -> ReplayTimer<'_, 'a> {
+        ReplayTimer { replay: self }
+    }
+
+    /// Returns whether the whole capture has been played back.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.cursor.borrow_mut().peek(), Ok(None))
+    }
+
+    /// Returns the tick of the frame being played back, as recorded by the journal.
+    pub fn tick(&self) -> u32 {
+        self.cursor.borrow().tick
+    }
+
+    /// Returns the number of the line being played back.
+    pub fn line(&self) -> usize {
+        self.cursor.borrow().line
+    }
+}
+
+impl serial::Read<u8> for &Replay<'_> {
+    type Error = ReplayError;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let mut cursor = self.cursor.borrow_mut();
+        match cursor.peek()? {
+            Some((Direction::Rx, b)) => {
+                cursor.next = None;
+                Ok(b)
+            }
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl serial::Write<u8> for &Replay<'_> {
+    type Error = ReplayError;
+
+    fn write(&mut self, written: u8) -> nb::Result<(), Self::Error> {
+        let mut cursor = self.cursor.borrow_mut();
+        match cursor.peek()? {
+            Some((Direction::Tx, expected)) if expected == written => {
+                cursor.next = None;
+                Ok(())
+            }
+            Some((Direction::Tx, expected)) => Err(nb::Error::Other(ReplayError::Mismatch {
+                line: cursor.line,
+                expected,
+                written,
+            })),
+            _ => Err(nb::Error::Other(ReplayError::Unexpected {
+                line: cursor.line,
+                written,
+            })),
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Timer of a [`Replay`](struct.Replay.html), look [`Replay::timer`](struct.Replay.html#method.timer).
+/// The duration it's started with is ignored.
+pub struct ReplayTimer<'r, 'a> {
+    replay: &'r Replay<'a>,
+}
+
+impl timer::CountDown for ReplayTimer<'_, '_> {
+    type Time = u32;
+
+    fn start<T: Into<u32>>(&mut self, _: T) {}
+
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        match self.replay.cursor.borrow_mut().peek() {
+            Ok(Some((Direction::Rx, _))) => Err(nb::Error::WouldBlock),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use core::cell::UnsafeCell;
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use hal::serial;
+
+/// Lock-free single-producer single-consumer byte queue for the received data.
+///
+/// The queue is meant to be filled from an interrupt handler (e.g. DMA half/full transfer
+/// or UART IDLE line interrupt) through the [`Producer`](struct.Producer.html), while the
+/// driver consumes it through the [`RingBufferRx`](struct.RingBufferRx.html) adapter.
+///
+/// # Example
+/// ```ignore
+/// let rx: &'static mut RingBuffer<64> = cortex_m::singleton!(: RingBuffer<64> = RingBuffer::new()).unwrap();
+///
+/// let (producer, consumer) = rx.split();
+/// // Move `producer` into the interrupt handler...
+/// let mut pzem = Pzem::new(RingBufferRx::new(tx, consumer), None).unwrap();
+///
+/// // In the interrupt handler:
+/// producer.push_slice(&dma_buf[..received]);
+/// ```
+pub struct RingBuffer<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    head: AtomicUsize, // Read index, only written by the consumer
+    tail: AtomicUsize, // Write index, only written by the producer
+}
+
+// SAFETY: access to the buffer is only possible through the `Producer` and `Consumer`
+// handles, each of which touches disjoint slots synchronized by the atomic indices.
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    // The indices wrap around at `2 * N` rather than at the integer overflow, which would
+    // break the mapping to the slots unless `N` is a power of two; twice the capacity tells
+    // a full buffer from an empty one.
+    const VALID: () = assert!(
+        N > 0 && N <= usize::MAX / 2,
+        "the capacity must be between 1 and usize::MAX / 2"
+    );
+
+    /// Creates an empty ring buffer.
+    pub const fn new() -> Self {
+        let () = Self::VALID;
+        Self {
+            buf: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Splits the ring buffer into the producer and consumer halves.
+    pub fn split(&mut self) -> (Producer<'_, N>, Consumer<'_, N>) {
+        let rb: &Self = self;
+        (Producer { rb }, Consumer { rb })
+    }
+
+    fn len(&self) -> usize {
+        Self::distance(
+            self.head.load(Ordering::Acquire),
+            self.tail.load(Ordering::Acquire),
+        )
+    }
+
+    fn distance(head: usize, tail: usize) -> usize {
+        if tail >= head {
+            tail - head
+        } else {
+            tail + 2 * N - head
+        }
+    }
+
+    fn next(index: usize) -> usize {
+        if index + 1 == 2 * N {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn slot(index: usize) -> usize {
+        if index >= N {
+            index - N
+        } else {
+            index
+        }
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Producer half of the [`RingBuffer`](struct.RingBuffer.html), to be used from the interrupt handler.
+pub struct Producer<'a, const N: usize> {
+    rb: &'a RingBuffer<N>,
+}
+
+// SAFETY: the producer is the only one writing to the free slots and the tail index.
+unsafe impl<'a, const N: usize> Send for Producer<'a, N> {}
+
+impl<'a, const N: usize> Producer<'a, N> {
+    /// Enqueues a single byte. Returns `false` if the buffer is full and the byte was dropped.
+    pub fn push(&mut self, b: u8) -> bool {
+        let tail = self.rb.tail.load(Ordering::Relaxed);
+        if RingBuffer::<N>::distance(self.rb.head.load(Ordering::Acquire), tail) >= N {
+            return false;
+        }
+
+        // SAFETY: the slot at `tail` is free and not accessed by the consumer
+        // until the tail index is published.
+        unsafe { (*self.rb.buf.get())[RingBuffer::<N>::slot(tail)] = b };
+        self.rb
+            .tail
+            .store(RingBuffer::<N>::next(tail), Ordering::Release);
+
+        true
+    }
+
+    /// Enqueues as many bytes of `buf` as fit, returning their number.
+    pub fn push_slice(&mut self, buf: &[u8]) -> usize {
+        let mut n = 0;
+        for &b in buf {
+            if !self.push(b) {
+                break;
+            }
+            n += 1;
+        }
+
+        n
+    }
+
+    /// Returns `true` if the buffer can't accept any more bytes.
+    pub fn is_full(&self) -> bool {
+        self.rb.len() >= N
+    }
+}
+
+/// Consumer half of the [`RingBuffer`](struct.RingBuffer.html), to be used by the driver.
+pub struct Consumer<'a, const N: usize> {
+    rb: &'a RingBuffer<N>,
+}
+
+// SAFETY: the consumer is the only one reading the filled slots and writing the head index.
+unsafe impl<'a, const N: usize> Send for Consumer<'a, N> {}
+
+impl<'a, const N: usize> Consumer<'a, N> {
+    /// Dequeues a single byte, if any.
+    pub fn pop(&mut self) -> Option<u8> {
+        let head = self.rb.head.load(Ordering::Relaxed);
+        if self.rb.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+
+        // SAFETY: the slot at `head` has been published by the producer
+        // and won't be overwritten until the head index is advanced.
+        let b = unsafe { (*self.rb.buf.get())[RingBuffer::<N>::slot(head)] };
+        self.rb
+            .head
+            .store(RingBuffer::<N>::next(head), Ordering::Release);
+
+        Some(b)
+    }
+
+    /// Returns the number of bytes waiting in the buffer.
+    pub fn len(&self) -> usize {
+        self.rb.len()
+    }
+
+    /// Returns `true` if there are no bytes waiting in the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Serial adapter which transmits through `Tx` and receives from a [`RingBuffer`](struct.RingBuffer.html).
+pub struct RingBufferRx<'a, Tx, const N: usize> {
+    tx: Tx,
+    rx: Consumer<'a, N>,
+}
+
+impl<'a, Tx, const N: usize> RingBufferRx<'a, Tx, N> {
+    /// Creates the adapter from the transmitting half of the UART and the ring buffer consumer.
+    pub fn new(tx: Tx, rx: Consumer<'a, N>) -> Self {
+        Self { tx, rx }
+    }
+
+    /// Releases the transmitter and the ring buffer consumer.
+    pub fn release(self) -> (Tx, Consumer<'a, N>) {
+        (self.tx, self.rx)
+    }
+}
+
+impl<'a, Tx, const N: usize> serial::Read<u8> for RingBufferRx<'a, Tx, N> {
+    type Error = Infallible;
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.pop().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<'a, Tx: serial::Write<u8>, const N: usize> serial::Write<u8> for RingBufferRx<'a, Tx, N> {
+    type Error = Tx::Error;
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.tx.write(word)
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.tx.flush()
+    }
+}
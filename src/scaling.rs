@@ -0,0 +1,120 @@
+use crate::{Measurement, RawMeasurement};
+
+/// Divisors converting the raw register values into the units of [`Measurement`](struct.Measurement.html).
+///
+/// Defaults to the datasheet values. Some clone boards use a nonstandard scaling, which can
+/// be overridden on the driver with [`Pzem::with_scaling`](struct.Pzem.html#method.with_scaling).
+///
+/// # Example
+/// ```ignore
+/// // Clone reporting the current in 0.1 mA.
+/// let scaling = Scaling { current: 10000.0, ..Scaling::DATASHEET };
+/// let mut pzem = Pzem::new(serial, None).unwrap().with_scaling(scaling);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scaling {
+    /// Raw units per V.
+    pub voltage: f32,
+    /// Raw units per A.
+    pub current: f32,
+    /// Raw units per W.
+    pub power: f32,
+    /// Raw units per kWh.
+    pub energy: f32,
+}
+
+impl Scaling {
+    /// Scaling documented in the datasheet: 0.1 V, 1 mA, 0.1 W and 1 Wh.
+    pub const DATASHEET: Self = Self {
+        voltage: 10.0,
+        current: 1000.0,
+        power: 10.0,
+        energy: 1000.0,
+    };
+
+    /// Converts the raw measurement.
+    pub fn apply(&self, raw: &RawMeasurement) -> Measurement {
+        Measurement {
+            voltage: raw.voltage as f32 / self.voltage,
+            current: raw.current as f32 / self.current,
+            power: raw.power as f32 / self.power,
+            energy: raw.energy as f32 / self.energy,
+            ..Measurement::from(*raw)
+        }
+    }
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Self::DATASHEET
+    }
+}
+
+/// Linear correction of the measurements, `gain * value + offset`, calibrating the sensor
+/// against a reference meter.
+///
+/// Applied by the driver after the conversion by [`Scaling`](struct.Scaling.html), look
+/// [`Pzem::with_correction`](struct.Pzem.html#method.with_correction). The energy is only
+/// scaled, as an offset would make the counter jump.
+///
+/// # Example
+/// ```ignore
+/// // The sensor reads 231.5 V and 4.95 A against the reference of 230.0 V and 5.00 A.
+/// let correction = Correction {
+///     voltage_gain: 230.0 / 231.5,
+///     current_gain: 5.00 / 4.95,
+///     ..Correction::IDENTITY
+/// };
+/// let mut pzem = Pzem::new(serial, None).unwrap().with_correction(correction);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Correction {
+    pub voltage_gain: f32,
+    /// In V.
+    pub voltage_offset: f32,
+    pub current_gain: f32,
+    /// In A.
+    pub current_offset: f32,
+    pub power_gain: f32,
+    /// In W.
+    pub power_offset: f32,
+    pub energy_gain: f32,
+}
+
+impl Correction {
+    /// Correction leaving the measurements as they are.
+    pub const IDENTITY: Self = Self {
+        voltage_gain: 1.0,
+        voltage_offset: 0.0,
+        current_gain: 1.0,
+        current_offset: 0.0,
+        power_gain: 1.0,
+        power_offset: 0.0,
+        energy_gain: 1.0,
+    };
+
+    /// Corrects the measurement.
+    ///
+    /// ```
+    /// use pzem004t::{Correction, Measurement};
+    ///
+    /// let correction = Correction { voltage_gain: 0.5, current_offset: 0.25, ..Correction::IDENTITY };
+    /// let m = correction.apply(&Measurement { voltage: 230.0, current: 1.0, ..Measurement::default() });
+    /// assert_eq!((m.voltage, m.current), (115.0, 1.25));
+    /// ```
+    pub fn apply(&self, m: &Measurement) -> Measurement {
+        Measurement {
+            voltage: m.voltage * self.voltage_gain + self.voltage_offset,
+            current: m.current * self.current_gain + self.current_offset,
+            power: m.power * self.power_gain + self.power_offset,
+            energy: m.energy * self.energy_gain,
+            ..*m
+        }
+    }
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
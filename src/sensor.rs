@@ -0,0 +1,102 @@
+use hal::serial;
+
+use crate::{Error, Measurement, Pzem, Timeout};
+
+/// Instantaneous electrical quantities measured by a power sensor.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PowerReading {
+    /// Voltage in V.
+    pub voltage: f32,
+    /// Current in A.
+    pub current: f32,
+    /// Active power in W.
+    pub power: f32,
+}
+
+/// Generic power sensor, allowing higher-level code to handle different sensors
+/// (e.g. PZEM004T, INA226, ADE7953) polymorphically.
+pub trait PowerSensor {
+    type Error;
+
+    /// Measures the instantaneous electrical quantities.
+    fn read_power(&mut self) -> Result<PowerReading, Self::Error>;
+}
+
+/// Generic energy meter.
+pub trait EnergyMeter {
+    type Error;
+
+    /// Reads the accumulated energy in kWh.
+    fn read_energy(&mut self) -> Result<f32, Self::Error>;
+}
+
+/// PZEM004T driver bound to its timeout, implementing the [`PowerSensor`](trait.PowerSensor.html)
+/// and [`EnergyMeter`](trait.EnergyMeter.html) traits.
+///
+/// # Example
+/// ```ignore
+/// fn report<S: PowerSensor>(sensor: &mut S) { ... }
+///
+/// let mut sensor = pzem.into_sensor(Some((&mut tim, TIMEOUT)));
+/// report(&mut sensor);
+/// ```
+pub struct Sensor<Serial, State, Tm> {
+    pzem: Pzem<Serial, State>,
+    timeout: Tm,
+}
+
+impl<Serial, State> Pzem<Serial, State> {
+    /// Binds the driver to the `timeout` used for every measurement.
+    pub fn into_sensor<Tm: Timeout>(self, timeout: Tm) -> Sensor<Serial, State, Tm> {
+        Sensor {
+            pzem: self,
+            timeout,
+        }
+    }
+}
+
+impl<Serial, State, Tm> Sensor<Serial, State, Tm> {
+    /// Releases the driver and the timeout.
+    pub fn release(self) -> (Pzem<Serial, State>, Tm) {
+        (self.pzem, self.timeout)
+    }
+}
+
+impl<Serial, State, Tm, WriteError, ReadError> Sensor<Serial, State, Tm>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+    Tm: Timeout,
+{
+    fn measure(&mut self) -> Result<Measurement, Error<WriteError, ReadError>> {
+        let mut m = Measurement::default();
+        self.pzem.read(&mut m, &mut self.timeout)?;
+        Ok(m)
+    }
+}
+
+impl<Serial, State, Tm, WriteError, ReadError> PowerSensor for Sensor<Serial, State, Tm>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+    Tm: Timeout,
+{
+    type Error = Error<WriteError, ReadError>;
+    fn read_power(&mut self) -> Result<PowerReading, Self::Error> {
+        let m = self.measure()?;
+        Ok(PowerReading {
+            voltage: m.voltage,
+            current: m.current,
+            power: m.power,
+        })
+    }
+}
+
+impl<Serial, State, Tm, WriteError, ReadError> EnergyMeter for Sensor<Serial, State, Tm>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+    Tm: Timeout,
+{
+    type Error = Error<WriteError, ReadError>;
+    fn read_energy(&mut self) -> Result<f32, Self::Error> {
+        Ok(self.measure()?.energy)
+    }
+}
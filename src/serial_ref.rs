@@ -0,0 +1,23 @@
+use hal::serial;
+
+/// Mutably borrowed serial peripheral, implementing the serial traits by delegation.
+///
+/// Created by [`Pzem::on`](struct.Pzem.html#method.on).
+pub struct SerialRef<'a, Serial>(pub &'a mut Serial);
+
+impl<'a, Serial: serial::Read<u8>> serial::Read<u8> for SerialRef<'a, Serial> {
+    type Error = Serial::Error;
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.0.read()
+    }
+}
+
+impl<'a, Serial: serial::Write<u8>> serial::Write<u8> for SerialRef<'a, Serial> {
+    type Error = Serial::Error;
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.0.write(word)
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,123 @@
+use hal::serial;
+
+use crate::{Error, Pzem, ADDR_DEFAULT, ADDR_MAX, ADDR_MIN};
+
+/// Mutex guarding the data shared by several tasks, e.g. the driver of a serial bus.
+///
+/// Implemented for `critical_section::Mutex<RefCell<T>>` with the `critical-section` feature,
+/// and for `std::sync::Mutex<T>` with the `std` feature. The mutexes of an RTOS are easily
+/// adapted, keeping its priority-aware locking.
+///
+/// # Examples
+///
+/// embassy-sync blocking mutex:
+///
+/// ```ignore
+/// struct EmbassyMutex<M: RawMutex, T>(embassy_sync::blocking_mutex::Mutex<M, RefCell<T>>);
+///
+/// impl<M: RawMutex, T> BusMutex for EmbassyMutex<M, T> {
+///     type Data = T;
+///     fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+///         self.0.lock(|cell| f(&mut cell.borrow_mut()))
+///     }
+/// }
+/// ```
+///
+/// freertos-rust mutex, with priority inheritance:
+///
+/// ```ignore
+/// struct FreeRtosMutex<T>(freertos_rust::Mutex<T>);
+///
+/// impl<T> BusMutex for FreeRtosMutex<T> {
+///     type Data = T;
+///     fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+///         f(&mut self.0.lock(freertos_rust::Duration::infinite()).unwrap())
+///     }
+/// }
+/// ```
+pub trait BusMutex {
+    type Data;
+
+    /// Runs `f` with exclusive access to the data, blocking until it is available.
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Data) -> R) -> R;
+}
+
+#[cfg(feature = "critical-section")]
+impl<T> BusMutex for critical_section::Mutex<core::cell::RefCell<T>> {
+    type Data = T;
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.borrow_ref_mut(cs)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> BusMutex for std::sync::Mutex<T> {
+    type Data = T;
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+}
+
+/// Driver shared by several tasks, each issuing its transactions to its own slave on the bus.
+///
+/// `Sync` as long as the mutex is, which holds for the mutexes above guarding a driver over
+/// a `Send` serial peripheral, hence it can be placed in a `static`.
+///
+/// # Example
+///
+/// ```ignore
+/// static BUS: SharedBus<Mutex<CriticalSectionRawMutex, RefCell<Pzem<Serial>>>> = ...;
+///
+/// // Display task
+/// let power = BUS.with_slave(0x01, |pzem| pzem.read(&mut m, Some((&mut tim, TIMEOUT))))?;
+///
+/// // Logger task
+/// BUS.with_slave(0x02, |pzem| pzem.read(&mut m, Some((&mut tim, TIMEOUT))))?;
+/// ```
+pub struct SharedBus<M> {
+    mutex: M,
+}
+
+impl<M> SharedBus<M> {
+    pub const fn new(mutex: M) -> Self {
+        Self { mutex }
+    }
+
+    /// Releases the mutex.
+    pub fn into_inner(self) -> M {
+        self.mutex
+    }
+}
+
+impl<M, Serial, State, WriteError, ReadError> SharedBus<M>
+where
+    M: BusMutex<Data = Pzem<Serial, State>>,
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Runs `f` with exclusive access to the driver, on its configured address.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut Pzem<Serial, State>) -> R) -> R {
+        self.mutex.lock(f)
+    }
+
+    /// Runs `f` with exclusive access to the driver, addressing the slave at `addr` meanwhile.
+    ///
+    /// Can return `Err(Error::IllegalAddress)` if `addr` is not in range of legal addresses `[0x01..0xf8]`.
+    pub fn with_slave<R>(
+        &self,
+        addr: u8,
+        f: impl FnOnce(&mut Pzem<Serial, State>) -> Result<R, Error<WriteError, ReadError>>,
+    ) -> Result<R, Error<WriteError, ReadError>> {
+        if addr != ADDR_DEFAULT && !(ADDR_MIN..=ADDR_MAX).contains(&addr) {
+            return Err(Error::IllegalAddress);
+        }
+
+        self.mutex.lock(|pzem| {
+            let old = core::mem::replace(&mut pzem.addr, addr);
+            let res = f(pzem);
+            pzem.addr = old;
+            res
+        })
+    }
+}
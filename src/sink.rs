@@ -0,0 +1,308 @@
+#[cfg(not(feature = "size-opt"))]
+use core::fmt::Write;
+
+use hal::serial;
+use heapless::HistoryBuffer;
+
+use crate::{Error, Measurement, Pzem, Timeout};
+
+/// Destination of the measurements read by [`Pzem::read_into`](struct.Pzem.html#method.read_into).
+///
+/// Implemented for mutable references and for pairs of sinks, so that several sinks may be
+/// fed at once: `pzem.read_into(&mut (&mut history, &mut aggregator), timeout)`.
+pub trait MeasurementSink {
+    fn push(&mut self, m: &Measurement);
+}
+
+impl<S: MeasurementSink + ?Sized> MeasurementSink for &mut S {
+    fn push(&mut self, m: &Measurement) {
+        (**self).push(m)
+    }
+}
+
+impl<A: MeasurementSink, B: MeasurementSink> MeasurementSink for (A, B) {
+    fn push(&mut self, m: &Measurement) {
+        self.0.push(m);
+        self.1.push(m);
+    }
+}
+
+/// Sink keeping the `N` most recent measurements.
+#[derive(Default)]
+pub struct History<const N: usize> {
+    buf: HistoryBuffer<Measurement, N>,
+}
+
+impl<const N: usize> History<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: HistoryBuffer::new(),
+        }
+    }
+
+    /// Returns the number of measurements kept.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the most recent measurement.
+    pub fn last(&self) -> Option<&Measurement> {
+        self.buf.recent()
+    }
+
+    /// Iterates over the measurements, from the oldest to the most recent.
+    pub fn iter(&self) -> impl Iterator<Item = &Measurement> {
+        self.buf.oldest_ordered()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+impl<const N: usize> MeasurementSink for History<N> {
+    fn push(&mut self, m: &Measurement) {
+        self.buf.write(*m);
+    }
+}
+
+/// Sink computing the mean values and the power extremes of the measurements pushed since
+/// the last [`reset`](#method.reset), e.g. to report once per minute while polling every second.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Aggregator {
+    count: u32,
+    sum: Measurement,
+    min_power: f32,
+    max_power: f32,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of measurements aggregated.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the mean of the measurements, with the energy and the alarm status of the
+    /// latest one, or `None` if nothing has been aggregated.
+    pub fn mean(&self) -> Option<Measurement> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let n = self.count as f32;
+        Some(Measurement {
+            voltage: self.sum.voltage / n,
+            current: self.sum.current / n,
+            power: self.sum.power / n,
+            frequency: self.sum.frequency / n,
+            pf: self.sum.pf / n,
+            ..self.sum
+        })
+    }
+
+    /// Returns the minimal and the maximal power in W, or `None` if nothing has been aggregated.
+    pub fn power_range(&self) -> Option<(f32, f32)> {
+        match self.count {
+            0 => None,
+            _ => Some((self.min_power, self.max_power)),
+        }
+    }
+
+    /// Starts over the aggregation.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl MeasurementSink for Aggregator {
+    fn push(&mut self, m: &Measurement) {
+        if self.count == 0 {
+            self.min_power = m.power;
+            self.max_power = m.power;
+        } else {
+            self.min_power = self.min_power.min(m.power);
+            self.max_power = self.max_power.max(m.power);
+        }
+
+        self.count += 1;
+        self.sum.voltage += m.voltage;
+        self.sum.current += m.current;
+        self.sum.power += m.power;
+        self.sum.frequency += m.frequency;
+        self.sum.pf += m.pf;
+        self.sum.energy = m.energy;
+        self.sum.alarm = m.alarm;
+    }
+}
+
+#[cfg(not(feature = "size-opt"))]
+/// Sink writing the measurements as CSV records, look [`Measurement::write_csv`](struct.Measurement.html#method.write_csv).
+///
+/// The timestamp of each record is taken from `clock`. As `push` can't fail, the formatting
+/// errors (e.g. a full buffer) are remembered and reported by [`take_error`](#method.take_error).
+///
+/// # Example
+/// ```ignore
+/// let mut csv = CsvWriter::new(heapless::String::<1024>::new(), || rtc.unix_time());
+/// pzem.read_into(&mut csv, Some((&mut tim, TIMEOUT))).unwrap();
+/// ```
+pub struct CsvWriter<W> {
+    w: W,
+    clock: fn() -> u32,
+    error: bool,
+}
+
+#[cfg(not(feature = "size-opt"))]
+impl<W: Write> CsvWriter<W> {
+    /// Creates the writer; the header is not written, look [`Measurement::csv_header`](struct.Measurement.html#method.csv_header).
+    pub fn new(w: W, clock: fn() -> u32) -> Self {
+        Self {
+            w,
+            clock,
+            error: false,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.w
+    }
+
+    /// Releases the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Returns `Err` if writing any record failed since the last call.
+    pub fn take_error(&mut self) -> core::fmt::Result {
+        if core::mem::take(&mut self.error) {
+            Err(core::fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(feature = "size-opt"))]
+impl<W: Write> MeasurementSink for CsvWriter<W> {
+    fn push(&mut self, m: &Measurement) {
+        if m.write_csv(&mut self.w, (self.clock)()).is_err() {
+            self.error = true;
+        }
+    }
+}
+
+type Callback<'a> = &'a mut dyn FnMut(&Measurement);
+
+/// Handle of a callback subscribed to a [`Broadcast`](struct.Broadcast.html), needed to unsubscribe it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Subscription(usize);
+
+/// Sink feeding every measurement to up to `N` subscribed callbacks, so that the parts of the
+/// firmware interested in the measurements share a single read per period.
+///
+/// # Example
+/// ```ignore
+/// let mut display = |m: &Measurement| lcd.show_power(m.power);
+/// let mut logger = |m: &Measurement| log.append(m.energy);
+///
+/// let mut broadcast = Broadcast::<4>::new();
+/// let _ = broadcast.subscribe(&mut display);
+/// let logger = broadcast.subscribe(&mut logger).ok().unwrap();
+/// loop {
+///     pzem.read_into(&mut broadcast, Some((&mut tim, TIMEOUT))).ok();
+///     // ...
+///     if log.is_full() {
+///         broadcast.unsubscribe(logger);
+///     }
+/// }
+/// ```
+pub struct Broadcast<'a, const N: usize> {
+    subscribers: [Option<Callback<'a>>; N],
+}
+
+impl<'a, const N: usize> Broadcast<'a, N> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: [(); N].map(|()| None),
+        }
+    }
+
+    /// Subscribes the callback, giving it back if all `N` slots are taken.
+    pub fn subscribe(&mut self, f: Callback<'a>) -> Result<Subscription, Callback<'a>> {
+        match self.subscribers.iter().position(Option::is_none) {
+            Some(i) => {
+                self.subscribers[i] = Some(f);
+                Ok(Subscription(i))
+            }
+            None => Err(f),
+        }
+    }
+
+    /// Unsubscribes the callback, giving it back.
+    ///
+    /// Returns `None` if the slot of the subscription is empty or out of range, e.g. for
+    /// a subscription of another broadcast.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> Option<Callback<'a>> {
+        self.subscribers.get_mut(subscription.0)?.take()
+    }
+
+    /// Returns the number of subscribed callbacks.
+    pub fn len(&self) -> usize {
+        self.subscribers.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const N: usize> Default for Broadcast<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MeasurementSink for Broadcast<'_, N> {
+    fn push(&mut self, m: &Measurement) {
+        for f in self.subscribers.iter_mut().flatten() {
+            f(m);
+        }
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Reads the measurements off the sensor and pushes them into `sink`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut history = History::<60>::new();
+    /// let mut minute = Aggregator::new();
+    /// loop {
+    ///     pzem.read_into(&mut (&mut history, &mut minute), Some((&mut tim, TIMEOUT))).ok();
+    ///     // ...
+    /// }
+    /// ```
+    pub fn read_into<S: MeasurementSink, Tm: Timeout>(
+        &mut self,
+        sink: &mut S,
+        timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut m = Measurement::default();
+        self.read(&mut m, timeout)?;
+        sink.push(&m);
+
+        Ok(())
+    }
+}
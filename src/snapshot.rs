@@ -0,0 +1,109 @@
+use hal::serial;
+
+#[cfg(not(feature = "size-opt"))]
+use crate::Stats;
+use crate::{Error, Event, Pzem, RawMeasurement, Timeout};
+
+/// Parameters of the sensor, as read off it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct DeviceParams {
+    /// Power alarm threshold, 1LSB corresponds to 1W.
+    pub threshold: u16,
+    /// Modbus-RTU address reported by the sensor.
+    pub addr: u16,
+}
+
+/// State of the sensor and the driver captured by [`Pzem::snapshot`](struct.Pzem.html#method.snapshot).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct DeviceSnapshot {
+    pub measurement: RawMeasurement,
+    pub params: DeviceParams,
+    /// Communication statistics, including the transactions of the snapshot itself.
+    #[cfg(not(feature = "size-opt"))]
+    pub stats: Stats,
+    /// Address the driver refers to the sensor by.
+    pub address: u8,
+}
+
+/// State of the driver captured by [`Pzem::debug_state`](struct.Pzem.html#method.debug_state),
+/// for the panic handlers and the crash dumps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DebugState {
+    /// Address the driver refers to the sensor by.
+    pub address: u8,
+    /// Most recent error of a transaction, if any.
+    pub last_error: Option<Event>,
+    /// Number of bytes received in the most recent transaction.
+    pub last_rx: u8,
+    /// Whether the response to an abandoned request may still be arriving, look
+    /// [`Pzem::is_pending`](struct.Pzem.html#method.is_pending).
+    pub pending: bool,
+    #[cfg(not(feature = "size-opt"))]
+    pub stats: Stats,
+}
+
+impl<Serial, State> Pzem<Serial, State> {
+    /// Captures the state of the driver without communicating.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// static mut PZEM_STATE: Option<DebugState> = None;
+    ///
+    /// #[panic_handler]
+    /// fn panic(info: &PanicInfo) -> ! {
+    ///     hprintln!("{}\n{:?}", info, unsafe { PZEM_STATE }).ok();
+    ///     loop {}
+    /// }
+    ///
+    /// loop {
+    ///     pzem.read(&mut m, Some((&mut tim, TIMEOUT))).ok();
+    ///     unsafe { PZEM_STATE = Some(pzem.debug_state()) };
+    /// }
+    /// ```
+    pub fn debug_state(&self) -> DebugState {
+        DebugState {
+            address: self.addr,
+            last_error: self.last_error,
+            last_rx: self.last_rx,
+            pending: self.pending.is_some(),
+            #[cfg(not(feature = "size-opt"))]
+            stats: self.stats,
+        }
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Captures the measurements, the parameters and the communication statistics in one
+    /// sequence of transactions, e.g. for periodic health reports.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let snapshot = pzem.snapshot(Some((&mut tim, TIMEOUT))).unwrap();
+    /// hprintln!("{:#?}", snapshot).unwrap();
+    /// ```
+    pub fn snapshot<Tm: Timeout>(
+        &mut self,
+        mut timeout: Tm,
+    ) -> Result<DeviceSnapshot, Error<WriteError, ReadError>> {
+        let mut measurement = RawMeasurement::default();
+        self.read_raw(&mut measurement, &mut timeout)?;
+
+        let params = DeviceParams {
+            threshold: self.get_threshold(&mut timeout)?,
+            addr: self.get_addr(&mut timeout)?,
+        };
+
+        Ok(DeviceSnapshot {
+            measurement,
+            params,
+            #[cfg(not(feature = "size-opt"))]
+            stats: self.stats,
+            address: self.addr,
+        })
+    }
+}
@@ -0,0 +1,227 @@
+use hal::serial;
+use heapless::{Deque, Vec};
+
+use crate::codec::{
+    crc_check_slice, SoftwareCrc, EXCEPTION_LEN, HOLDING_RESP_MAX, REQ_LEN, RESET_LEN,
+    WRITE_MULTI_RESP_LEN,
+};
+use crate::{
+    decode, RawMeasurement, ADDR_DEFAULT, ADDR_MIN, CMD_READ, CMD_READ_PARAM, CMD_RESET,
+    CMD_WRITE_MULTI, CMD_WRITE_PARAM, EXCEPTION_FLAG,
+};
+
+/// Frame observed on the bus by the [`Sniffer`](struct.Sniffer.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sniffed {
+    /// Response carrying the measurements of the sensor at `addr`.
+    Measurement {
+        addr: u8,
+        measurement: RawMeasurement,
+    },
+    /// Any other valid frame, either a request or a response.
+    Frame { addr: u8, function: u8 },
+}
+
+/// Passive listener decoding the frames exchanged on the bus by another master, e.g. to
+/// log the measurements of an existing sensor and display installation without transmitting.
+///
+/// The frames are delimited by their layout and the CRC, resynchronizing byte by byte on
+/// garbage. Calling [`reset`](#method.reset) on an idle line, where detected, speeds up
+/// the resynchronization.
+///
+/// Up to `Q` frames are queued by [`fill`](#method.fill), e.g. called from the receive
+/// interrupt while the main loop takes them with [`pop`](#method.pop) at its own pace. The
+/// memory taken is fixed: the frames arriving at a full queue are dropped and counted, look
+/// [`overflows`](#method.overflows).
+///
+/// # Example
+/// ```ignore
+/// let mut sniffer = Sniffer::new(rx);
+/// loop {
+///     match sniffer.poll() {
+///         Ok(Sniffed::Measurement { addr, measurement }) => {
+///             let m: Measurement = measurement.into();
+///             hprintln!("{:#04x}: {:.1} W", addr, m.power).unwrap();
+///         }
+///         Ok(_) | Err(nb::Error::WouldBlock) => {}
+///         Err(nb::Error::Other(e)) => panic!("{:?}", e),
+///     }
+/// }
+/// ```
+pub struct Sniffer<Rx, const Q: usize = 4> {
+    rx: Rx,
+    buf: Vec<u8, HOLDING_RESP_MAX>,
+    queue: Deque<Sniffed, Q>,
+    skipped: u32,
+    overflows: u32,
+}
+
+// Outcome of matching the start of the buffer against the frame layouts.
+enum Match {
+    Frame(usize),
+    Incomplete,
+    Garbage,
+}
+
+impl<Rx: serial::Read<u8>> Sniffer<Rx> {
+    /// Listens on the receiver of the serial line, queueing up to 4 frames.
+    pub fn new(rx: Rx) -> Self {
+        Self::with_queue(rx)
+    }
+}
+
+impl<Rx: serial::Read<u8>, const Q: usize> Sniffer<Rx, Q> {
+    /// Listens on the receiver of the serial line, queueing up to `Q` frames:
+    /// `Sniffer::<_, 16>::with_queue(rx)`.
+    pub fn with_queue(rx: Rx) -> Self {
+        Self {
+            rx,
+            buf: Vec::new(),
+            queue: Deque::new(),
+            skipped: 0,
+            overflows: 0,
+        }
+    }
+
+    /// Returns the oldest queued frame, or else reads the bytes received, returning the next
+    /// complete frame, or `WouldBlock` if none.
+    pub fn poll(&mut self) -> nb::Result<Sniffed, Rx::Error> {
+        if let Some(frame) = self.queue.pop_front() {
+            return Ok(frame);
+        }
+
+        loop {
+            if let Some(frame) = self.parse() {
+                return Ok(frame);
+            }
+
+            let byte = self.rx.read()?;
+            self.push(byte);
+        }
+    }
+
+    /// Reads all the bytes received, queueing the complete frames.
+    pub fn fill(&mut self) -> Result<(), Rx::Error> {
+        loop {
+            while let Some(frame) = self.parse() {
+                if self.queue.push_back(frame).is_err() {
+                    self.overflows = self.overflows.wrapping_add(1);
+                }
+            }
+
+            match self.rx.read() {
+                Ok(byte) => self.push(byte),
+                Err(nb::Error::WouldBlock) => return Ok(()),
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Takes the oldest queued frame.
+    pub fn pop(&mut self) -> Option<Sniffed> {
+        self.queue.pop_front()
+    }
+
+    /// Returns the number of frames queued.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns the number of frames dropped as the queue was full.
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    /// Discards the partial frame, e.g. once the line has been idle longer than 3.5 characters.
+    /// The queued frames are kept.
+    pub fn reset(&mut self) {
+        self.skipped = self.skipped.wrapping_add(self.buf.len() as u32);
+        self.buf.clear();
+    }
+
+    /// Returns the number of bytes not belonging to any valid frame.
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Releases the receiver.
+    pub fn release(self) -> Rx {
+        self.rx
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.buf.is_full() {
+            self.skip(1);
+        }
+        let _ = self.buf.push(byte);
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.buf.rotate_left(n);
+        self.buf.truncate(self.buf.len() - n);
+        self.skipped = self.skipped.wrapping_add(n as u32);
+    }
+
+    // Extracts the frame at the start of the buffer, skipping the garbage before it.
+    fn parse(&mut self) -> Option<Sniffed> {
+        loop {
+            match self.matches() {
+                Match::Incomplete => return None,
+                Match::Garbage => self.skip(1),
+                Match::Frame(n) => {
+                    let frame = self.decode(n);
+                    self.buf.rotate_left(n);
+                    self.buf.truncate(self.buf.len() - n);
+                    return Some(frame);
+                }
+            }
+        }
+    }
+
+    fn matches(&self) -> Match {
+        let buf = &self.buf[..];
+        let (addr, function) = match buf {
+            [] | [_] => return Match::Incomplete,
+            [addr, function, ..] => (*addr, *function),
+        };
+        if !(ADDR_MIN..=ADDR_DEFAULT).contains(&addr) {
+            return Match::Garbage;
+        }
+
+        // Lengths of the request and the response of the function. Until the byte count
+        // field is received, the length is known to be at least one byte more.
+        let count =
+            |i: usize, base: usize| buf.get(i).map_or(buf.len() + 1, |&n| base + n as usize);
+        let candidates = match function {
+            f if f & EXCEPTION_FLAG != 0 => [EXCEPTION_LEN; 2],
+            CMD_READ | CMD_READ_PARAM => [REQ_LEN, count(2, 3 + 2)],
+            CMD_WRITE_PARAM => [REQ_LEN; 2],
+            CMD_WRITE_MULTI => [WRITE_MULTI_RESP_LEN, count(6, 7 + 2)],
+            CMD_RESET => [RESET_LEN; 2],
+            _ => return Match::Garbage,
+        };
+
+        let mut incomplete = false;
+        for len in candidates {
+            if len > buf.len() {
+                incomplete |= len <= self.buf.capacity();
+            } else if crc_check_slice(&SoftwareCrc, &buf[..len]) {
+                return Match::Frame(len);
+            }
+        }
+
+        if incomplete {
+            Match::Incomplete
+        } else {
+            Match::Garbage
+        }
+    }
+
+    fn decode(&self, len: usize) -> Sniffed {
+        let (addr, function) = (self.buf[0], self.buf[1]);
+        match decode::parse(&self.buf[..len]) {
+            Ok(measurement) if function == CMD_READ => Sniffed::Measurement { addr, measurement },
+            _ => Sniffed::Frame { addr, function },
+        }
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(not(feature = "size-opt"))]
+/// Communication statistics gathered by the driver.
+///
+/// All counters wrap around on overflow.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of request/response exchanges attempted, including the retries.
+    pub transactions: u32,
+    /// Number of exchanges which timed out.
+    pub timeouts: u32,
+    /// Number of responses failing the CRC check.
+    pub crc_errors: u32,
+    /// Number of CRC failures treated as a collision with another master and retried after a backoff.
+    pub collisions: u32,
+    /// Number of unexpected bytes discarded from the input queue before the requests.
+    pub drained_bytes: u32,
+    /// Number of requests refused because of line noise, look [`Error::LineNoise`](enum.Error.html#variant.LineNoise).
+    pub line_noise: u32,
+    /// Number of requests refused because the input queue couldn't be emptied, look [`Error::BusBusy`](enum.Error.html#variant.BusBusy).
+    pub bus_busy: u32,
+    /// Number of measurements rejected by a [`Validator`](trait.Validator.html).
+    pub rejected: u32,
+    /// Time spent in each phase of all the exchanges, with a clock attached by
+    /// [`Pzem::with_phase_clock`](struct.Pzem.html#method.with_phase_clock).
+    pub phases: PhaseTimes,
+    /// Time spent in each phase of the most recent exchange, likewise.
+    pub last_phases: PhaseTimes,
+}
+
+/// Time spent in the phases of the request/response exchanges, in the ticks of the clock
+/// attached by [`Pzem::with_phase_clock`](struct.Pzem.html#method.with_phase_clock).
+///
+/// The phases not reached by an exchange, e.g. after a timeout, count as zero.
+/// A long `write` points at the serial adapter or the HAL, while a long `first_byte`
+/// points at the sensor itself.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PhaseTimes {
+    /// Emptying the input queue before the request.
+    pub drain: u32,
+    /// Writing the request, until flushed.
+    pub write: u32,
+    /// Waiting for the first byte of the response.
+    pub first_byte: u32,
+    /// Reading the rest of the response.
+    pub read_rest: u32,
+}
+
+#[cfg(not(feature = "size-opt"))]
+impl PhaseTimes {
+    pub(crate) fn accumulate(&mut self, other: &PhaseTimes) {
+        self.drain = self.drain.wrapping_add(other.drain);
+        self.write = self.write.wrapping_add(other.write);
+        self.first_byte = self.first_byte.wrapping_add(other.first_byte);
+        self.read_rest = self.read_rest.wrapping_add(other.read_rest);
+    }
+}
+
+// Measures the phases of a single exchange.
+pub(crate) struct PhaseTimer {
+    clock: Option<fn() -> u32>,
+    last: u32,
+    pub(crate) times: PhaseTimes,
+}
+
+impl PhaseTimer {
+    pub(crate) fn start(clock: Option<fn() -> u32>) -> Self {
+        Self {
+            clock,
+            last: clock.map_or(0, |clock| clock()),
+            times: PhaseTimes::default(),
+        }
+    }
+
+    // Returns the ticks elapsed since the previous lap, or zero without a clock.
+    pub(crate) fn lap(&mut self) -> u32 {
+        match self.clock {
+            Some(clock) => {
+                let now = clock();
+                let elapsed = now.wrapping_sub(self.last);
+                self.last = now;
+                elapsed
+            }
+            None => 0,
+        }
+    }
+}
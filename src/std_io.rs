@@ -0,0 +1,238 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use hal::serial;
+use hal::timer::CountDown;
+
+use crate::{Diagnosis, Pzem};
+
+/// Adapter implementing the embedded-hal serial traits over any `std::io::Read + Write`.
+///
+/// This allows the driver to be used over TCP sockets, PTYs, serial port handles or
+/// in-memory pipes in tests.
+///
+/// The driver relies on reads returning `WouldBlock` once no more data is available (e.g.
+/// when draining the input queue before a request). Therefore, the underlying stream should
+/// be either non-blocking or have a read timeout set: `ErrorKind::WouldBlock` and
+/// `ErrorKind::TimedOut` are reported as `nb::Error::WouldBlock`. A zero-length read is the
+/// end of the stream, e.g. the peer of a TCP socket gone, and fails with
+/// `ErrorKind::UnexpectedEof`, unless [`with_idle_eof`](#method.with_idle_eof) is set.
+///
+/// # Example
+/// ```ignore
+/// let stream = std::net::TcpStream::connect("192.168.1.10:4196")?;
+/// stream.set_read_timeout(Some(Duration::from_millis(10)))?;
+///
+/// let mut pzem = Pzem::new(StdIo::new(stream), None).unwrap();
+/// let mut tim = StdTimer::new();
+/// pzem.read(&mut m, Some((&mut tim, Duration::from_millis(500))))?;
+/// ```
+#[derive(Debug)]
+pub struct StdIo<T> {
+    inner: T,
+    idle_eof: bool,
+}
+
+impl<T> StdIo<T> {
+    /// Wraps the `std::io` stream.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            idle_eof: false,
+        }
+    }
+
+    /// Reports the zero-length reads as `WouldBlock` rather than the end of the stream, for
+    /// the in-memory pipes (e.g. over `std::io::Cursor`) whose end only means that no more
+    /// data is available yet. Such a pipe holding the response before the request is sent
+    /// needs [`Pzem::with_skip_drain`](struct.Pzem.html#method.with_skip_drain).
+    ///
+    /// # Example
+    /// ```ignore
+    /// let pipe = Pipe { rx: Cursor::new(response.to_vec()), tx: Vec::new() };
+    /// let mut pzem = Pzem::new(StdIo::new(pipe).with_idle_eof(true), Some(0x01))
+    ///     .unwrap()
+    ///     .with_skip_drain(true);
+    /// ```
+    pub fn with_idle_eof(mut self, idle: bool) -> Self {
+        self.idle_eof = idle;
+        self
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Releases the underlying stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+pub(crate) fn would_block(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+impl<T: io::Read> serial::Read<u8> for StdIo<T> {
+    type Error = io::Error;
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let mut b = [0u8];
+        match self.inner.read(&mut b) {
+            Ok(0) if self.idle_eof => Err(nb::Error::WouldBlock),
+            Ok(0) => Err(nb::Error::Other(io::ErrorKind::UnexpectedEof.into())),
+            Ok(_) => Ok(b[0]),
+            Err(ref e) if would_block(e) => Err(nb::Error::WouldBlock),
+            Err(e) => Err(nb::Error::Other(e)),
+        }
+    }
+}
+
+impl<T: io::Write> serial::Write<u8> for StdIo<T> {
+    type Error = io::Error;
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match self.inner.write(&[word]) {
+            Ok(0) => Err(nb::Error::WouldBlock),
+            Ok(_) => Ok(()),
+            Err(ref e) if would_block(e) => Err(nb::Error::WouldBlock),
+            Err(e) => Err(nb::Error::Other(e)),
+        }
+    }
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        match self.inner.flush() {
+            Ok(()) => Ok(()),
+            Err(ref e) if would_block(e) => Err(nb::Error::WouldBlock),
+            Err(e) => Err(nb::Error::Other(e)),
+        }
+    }
+}
+
+/// Timeout timer based on `std::time::Instant`, to be used along with [`StdIo`](struct.StdIo.html).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct StdTimer {
+    deadline: Option<Instant>,
+}
+
+impl StdTimer {
+    /// Creates a new timer, which is not started.
+    pub fn new() -> Self {
+        Self { deadline: None }
+    }
+}
+
+impl CountDown for StdTimer {
+    type Time = Duration;
+    fn start<T: Into<Self::Time>>(&mut self, count: T) {
+        self.deadline = Some(Instant::now() + count.into());
+    }
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        match self.deadline {
+            Some(deadline) if Instant::now() < deadline => Err(nb::Error::WouldBlock),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parity of the serial line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Configuration of the serial line tried by [`Pzem::auto_probe`](struct.Pzem.html#method.auto_probe).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LineConfig {
+    pub baud: u32,
+    pub parity: Parity,
+    pub stop_bits: u8,
+}
+
+impl LineConfig {
+    /// Configuration of PZEM004T: 9600 baud, 8 data bits, no parity, 1 stop bit.
+    pub const PZEM: Self = Self {
+        baud: 9600,
+        parity: Parity::None,
+        stop_bits: 1,
+    };
+
+    /// Configurations tried by [`Pzem::auto_probe`](struct.Pzem.html#method.auto_probe):
+    /// the right one first, then the common misconfigurations.
+    pub const CANDIDATES: [Self; 9] = [
+        Self::PZEM,
+        Self::with(9600, Parity::Even, 1),
+        Self::with(9600, Parity::Odd, 1),
+        Self::with(9600, Parity::None, 2),
+        Self::with(4800, Parity::None, 1),
+        Self::with(19200, Parity::None, 1),
+        Self::with(38400, Parity::None, 1),
+        Self::with(57600, Parity::None, 1),
+        Self::with(115200, Parity::None, 1),
+    ];
+
+    const fn with(baud: u32, parity: Parity, stop_bits: u8) -> Self {
+        Self {
+            baud,
+            parity,
+            stop_bits,
+        }
+    }
+}
+
+impl<T: io::Read + io::Write, State> Pzem<StdIo<T>, State> {
+    /// Tries the serial line configurations of [`LineConfig::CANDIDATES`](struct.LineConfig.html#associatedconstant.CANDIDATES)
+    /// one by one, for transports where the host controls the port settings.
+    ///
+    /// `configure` applies the configuration to the underlying port. Returns the diagnosis
+    /// of [`verify_link`](#method.verify_link) for every configuration tried, up to and
+    /// including the first one which works. The port is left in the working configuration,
+    /// or reverted to [`LineConfig::PZEM`](struct.LineConfig.html#associatedconstant.PZEM)
+    /// if none does.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let results = pzem.auto_probe(|port, config| {
+    ///     port.set_baud_rate(config.baud)?;
+    ///     // ...
+    ///     Ok(())
+    /// }, &mut tim, Duration::from_millis(500))?;
+    /// ```
+    pub fn auto_probe<F>(
+        &mut self,
+        mut configure: F,
+        timer: &mut StdTimer,
+        timeout: Duration,
+    ) -> io::Result<Vec<(LineConfig, Diagnosis)>>
+    where
+        F: FnMut(&mut T, LineConfig) -> io::Result<()>,
+    {
+        let mut results = Vec::new();
+        for &config in LineConfig::CANDIDATES.iter() {
+            configure(self.uart.get_mut(), config)?;
+
+            let diagnosis =
+                self.verify_link(Some((&mut *timer, timeout)))
+                    .map_err(|e| match e {
+                        crate::Error::WriteError(e) | crate::Error::ReadError(e) => e,
+                        _ => unreachable!(),
+                    })?;
+
+            results.push((config, diagnosis));
+            if diagnosis == Diagnosis::Healthy {
+                return Ok(results);
+            }
+        }
+
+        configure(self.uart.get_mut(), LineConfig::PZEM)?;
+        Ok(results)
+    }
+}
@@ -0,0 +1,102 @@
+//! Canonical request and response frames, along with the values they encode.
+//!
+//! The frames follow the layout of the datasheet and are the ones the driver sends and
+//! expects, byte for byte. They allow the alternate transports and the downstream integration
+//! tests to validate themselves against the same golden data as the driver.
+//!
+//! Every module holds the exchange of a single operation with the slave at [`ADDR`](constant.ADDR.html),
+//! except for [`write_addr`](write_addr/index.html), which uses the general address `0xf8`.
+//!
+//! Only available with the `test-support` feature.
+
+use crate::RawMeasurement;
+
+/// Slave address the frames are addressed to.
+pub const ADDR: u8 = 0x01;
+
+/// Reading the measurements: [`Pzem::read_raw`](../struct.Pzem.html#method.read_raw).
+pub mod read {
+    use super::RawMeasurement;
+    use crate::{READ_RESPONSE_LEN, REQUEST_LEN};
+
+    pub const REQUEST: [u8; REQUEST_LEN] = [0x01, 0x04, 0x00, 0x00, 0x00, 0x0a, 0x70, 0x0d];
+
+    pub const RESPONSE: [u8; READ_RESPONSE_LEN] = [
+        0x01, 0x04, 0x14, // Address, function, byte count
+        0x08, 0xfd, // Voltage: 230.1 V
+        0x04, 0xd2, 0x00, 0x00, // Current: 1.234 A
+        0x0b, 0x17, 0x00, 0x00, // Power: 283.9 W
+        0x30, 0x39, 0x00, 0x00, // Energy: 12345 Wh
+        0x01, 0xf4, // Frequency: 50.0 Hz
+        0x00, 0x63, // Power factor: 0.99
+        0x00, 0x00, // Alarm: off
+        0xd5, 0x70,
+    ];
+
+    /// Measurement encoded by the [`RESPONSE`](constant.RESPONSE.html).
+    pub const MEASUREMENT: RawMeasurement = RawMeasurement {
+        voltage: 2301,
+        current: 1234,
+        power: 2839,
+        energy: 12345,
+        frequency: 500,
+        pf: 99,
+        alarm: false,
+    };
+}
+
+/// Reading the power alarm threshold: [`Pzem::get_threshold`](../struct.Pzem.html#method.get_threshold).
+pub mod get_threshold {
+    use crate::{PARAM_RESPONSE_LEN, REQUEST_LEN};
+
+    pub const REQUEST: [u8; REQUEST_LEN] = [0x01, 0x03, 0x00, 0x01, 0x00, 0x01, 0xd5, 0xca];
+    pub const RESPONSE: [u8; PARAM_RESPONSE_LEN] = [0x01, 0x03, 0x02, 0x08, 0xfc, 0xbf, 0xc5];
+
+    /// Threshold encoded by the [`RESPONSE`](constant.RESPONSE.html), in W.
+    pub const THRESHOLD: u16 = 2300;
+}
+
+/// Reading the slave address: [`Pzem::get_addr`](../struct.Pzem.html#method.get_addr),
+/// also sent by [`Pzem::probe`](../struct.Pzem.html#method.probe).
+pub mod get_addr {
+    use crate::{PARAM_RESPONSE_LEN, REQUEST_LEN};
+
+    pub const REQUEST: [u8; REQUEST_LEN] = [0x01, 0x03, 0x00, 0x02, 0x00, 0x01, 0x25, 0xca];
+    pub const RESPONSE: [u8; PARAM_RESPONSE_LEN] = [0x01, 0x03, 0x02, 0x00, 0x01, 0x79, 0x84];
+}
+
+/// Writing the power alarm threshold: [`Pzem::set_threshold`](../struct.Pzem.html#method.set_threshold)
+/// with [`WriteMode::Single`](../enum.WriteMode.html).
+pub mod set_threshold {
+    use crate::REQUEST_LEN;
+
+    /// Threshold written by the [`REQUEST`](constant.REQUEST.html), in W.
+    pub const THRESHOLD: u16 = 2300;
+
+    pub const REQUEST: [u8; REQUEST_LEN] = [0x01, 0x06, 0x00, 0x01, 0x08, 0xfc, 0xdf, 0x8b];
+    /// The sensor echoes the request.
+    pub const RESPONSE: [u8; REQUEST_LEN] = REQUEST;
+}
+
+/// Writing the slave address through the general address `0xf8`:
+/// [`Pzem::set_addr`](../struct.Pzem.html#method.set_addr) with [`WriteMode::Single`](../enum.WriteMode.html).
+pub mod write_addr {
+    use crate::REQUEST_LEN;
+
+    /// Address written by the [`REQUEST`](constant.REQUEST.html).
+    pub const NEW_ADDR: u8 = 0x05;
+
+    pub const REQUEST: [u8; REQUEST_LEN] = [0xf8, 0x06, 0x00, 0x02, 0x00, 0x05, 0xfc, 0x60];
+    /// The sensor echoes the request.
+    pub const RESPONSE: [u8; REQUEST_LEN] = REQUEST;
+}
+
+/// Resetting the energy counter: [`Pzem::reset_energy`](../struct.Pzem.html#method.reset_energy).
+pub mod reset {
+    pub const REQUEST: [u8; 4] = [0x01, 0x42, 0x80, 0x11];
+    /// The sensor echoes the request.
+    pub const RESPONSE: [u8; 4] = REQUEST;
+
+    /// Exception response to the [`REQUEST`](constant.REQUEST.html): slave error.
+    pub const EXCEPTION: [u8; 5] = [0x01, 0xc2, 0x04, 0x70, 0xa3];
+}
@@ -0,0 +1,204 @@
+use hal::timer::CountDown;
+
+use crate::{Operation, Timeout, Timeouts};
+
+/// Timer counting down on a free-running millisecond counter, look [`TickTimeout`](struct.TickTimeout.html).
+///
+/// The counter may wrap around; the timeouts must be shorter than its period.
+#[derive(Debug, Copy, Clone)]
+pub struct TickTimer {
+    clock: fn() -> u32,
+    start: u32,
+    ms: u32,
+}
+
+impl TickTimer {
+    pub fn new(clock: fn() -> u32) -> Self {
+        Self {
+            clock,
+            start: clock(),
+            ms: 0,
+        }
+    }
+}
+
+impl CountDown for TickTimer {
+    /// Milliseconds.
+    type Time = u32;
+
+    fn start<T: Into<u32>>(&mut self, count: T) {
+        self.start = (self.clock)();
+        self.ms = count.into();
+    }
+
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        if (self.clock)().wrapping_sub(self.start) >= self.ms {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+/// Timeouts in milliseconds measured with a user-provided millisecond counter, e.g. the
+/// SysTick interrupt count or the DWT cycle counter scaled down, for the projects without
+/// a spare `CountDown` timer.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use embedded_hal::timer::CountDown;
+/// use pzem004t::{Operation, Timeout, TickTimeout};
+///
+/// // Incremented by the SysTick interrupt; here, by every reading.
+/// static MILLIS: AtomicU32 = AtomicU32::new(u32::MAX - 2);
+/// fn millis() -> u32 {
+///     MILLIS.fetch_add(1, Ordering::Relaxed)
+/// }
+///
+/// let mut timeout = TickTimeout::new(millis, 100);
+/// // pzem.read(&mut m, &mut timeout)?;
+///
+/// let (timer, ms) = timeout.get(Operation::Read).unwrap();
+/// timer.start(ms);
+/// let polls = (0..).take_while(|_| timer.wait().is_err()).count();
+/// assert_eq!(polls, 99);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct TickTimeout {
+    timer: TickTimer,
+    timeouts: Timeouts<u32>,
+}
+
+impl TickTimeout {
+    /// Uses the same timeout of `ms` milliseconds for every operation.
+    pub fn new(clock: fn() -> u32, ms: u32) -> Self {
+        Self::with_timeouts(clock, Timeouts::uniform(ms))
+    }
+
+    /// Uses the timeouts in milliseconds depending on the operation.
+    pub fn with_timeouts(clock: fn() -> u32, timeouts: Timeouts<u32>) -> Self {
+        Self {
+            timer: TickTimer::new(clock),
+            timeouts,
+        }
+    }
+}
+
+impl Timeout for TickTimeout {
+    type Timer = TickTimer;
+    fn get(&mut self, op: Operation) -> Option<(&mut TickTimer, u32)> {
+        Some((&mut self.timer, self.timeouts.get(op)))
+    }
+}
+
+// Smoothed latency of an operation, after RFC 6298: the mean scaled by 8 and the mean
+// deviation scaled by 4, in milliseconds.
+#[derive(Debug, Default, Copy, Clone)]
+struct Estimate {
+    srtt: u32,
+    rttvar: u32,
+    sampled: bool,
+}
+
+/// Timeouts in milliseconds, like [`TickTimeout`](struct.TickTimeout.html), adapting to the
+/// latency of the link within the given bounds.
+///
+/// Each timeout starts at its upper bound. The latency of every answered attempt, measured
+/// from the start of the wait for the response, is smoothed like the TCP retransmission
+/// timer: the timeout settles at the mean latency plus four times its mean deviation, never
+/// below the lower bound, so that a dead or unplugged sensor stalls a healthy link for little
+/// longer than its own responses take. Each timeout doubles it again, up to the upper bound,
+/// for the adapters whose latency grows under load. The operations are tracked separately,
+/// as writing the EEPROM of the sensor takes much longer than reading it.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut timeout = AdaptiveTimeout::new(millis, 20, 500);
+/// loop {
+///     pzem.read(&mut m, &mut timeout)?;
+///     defmt::info!("timeout settled at {} ms", timeout.current(Operation::Read));
+/// }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct AdaptiveTimeout {
+    timer: TickTimer,
+    min: Timeouts<u32>,
+    max: Timeouts<u32>,
+    current: Timeouts<u32>,
+    estimates: [Estimate; 4],
+}
+
+impl AdaptiveTimeout {
+    /// Uses the same bounds, in milliseconds, for every operation.
+    pub fn new(clock: fn() -> u32, min: u32, max: u32) -> Self {
+        Self::with_bounds(clock, Timeouts::uniform(min), Timeouts::uniform(max))
+    }
+
+    /// Uses the bounds in milliseconds depending on the operation.
+    pub fn with_bounds(clock: fn() -> u32, min: Timeouts<u32>, max: Timeouts<u32>) -> Self {
+        Self {
+            timer: TickTimer::new(clock),
+            min,
+            max,
+            current: max,
+            estimates: [Estimate::default(); 4],
+        }
+    }
+
+    /// Returns the timeout of the operation `op` in milliseconds.
+    pub fn current(&self, op: Operation) -> u32 {
+        self.current.get(op)
+    }
+
+    /// Forgets the latencies measured, starting over from the upper bounds, e.g. after
+    /// replacing the adapter.
+    pub fn reset(&mut self) {
+        self.current = self.max;
+        self.estimates = [Estimate::default(); 4];
+    }
+
+    fn slot(current: &mut Timeouts<u32>, op: Operation) -> &mut u32 {
+        match op {
+            Operation::Read => &mut current.read,
+            Operation::ReadParam => &mut current.read_param,
+            Operation::WriteParam => &mut current.write_param,
+            Operation::Reset => &mut current.reset,
+        }
+    }
+}
+
+impl Timeout for AdaptiveTimeout {
+    type Timer = TickTimer;
+    fn get(&mut self, op: Operation) -> Option<(&mut TickTimer, u32)> {
+        Some((&mut self.timer, self.current.get(op)))
+    }
+
+    fn observe(&mut self, op: Operation, timed_out: bool) {
+        let (min, max) = (self.min.get(op), self.max.get(op));
+        let current = Self::slot(&mut self.current, op);
+        if timed_out {
+            *current = current.saturating_mul(2).clamp(min, max.max(min));
+            return;
+        }
+
+        let latency = (self.timer.clock)().wrapping_sub(self.timer.start);
+        let e = &mut self.estimates[op as usize];
+        if e.sampled {
+            let delta = latency as i64 - (e.srtt >> 3) as i64;
+            e.srtt = (e.srtt as i64 + delta).clamp(0, u32::MAX as i64) as u32;
+            let var = e.rttvar as i64 + delta.abs() - (e.rttvar >> 2) as i64;
+            e.rttvar = var.clamp(0, u32::MAX as i64) as u32;
+        } else {
+            e.srtt = latency.saturating_mul(8);
+            e.rttvar = latency.saturating_mul(2);
+            e.sampled = true;
+        }
+
+        *current = (e.srtt >> 3)
+            .saturating_add(e.rttvar)
+            .clamp(min, max.max(min));
+    }
+}
@@ -0,0 +1,172 @@
+use hal::timer::CountDown;
+
+/// Kinds of operations, which may warrant different timeouts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Reading the measurement registers.
+    Read,
+    /// Reading a parameter of the sensor (alarm threshold, address).
+    ReadParam,
+    /// Writing a parameter of the sensor.
+    WriteParam,
+    /// Resetting the energy counter.
+    Reset,
+}
+
+/// Source of the timeout for each operation of the driver.
+///
+/// Implemented for:
+///
+/// - `Option<(&mut T, T::Time)>`: the same timeout for every operation, or none at all;
+/// - `(&mut T, Timeouts<T::Time>)`: the timeout depending on the operation, look [`Timeouts`](struct.Timeouts.html);
+/// - [`Session`](struct.Session.html): the same, to be handed to several consecutive calls;
+/// - [`NoTimeout`](struct.NoTimeout.html): wait indefinitely.
+///
+/// Replaces the `Option<(&mut T, T::Time)>` parameter of 0.1, which now requires
+/// `T::Time: Clone`; `pzem.read::<NoTimeout>(&mut m, None)` becomes `pzem.read(&mut m, NoTimeout)`.
+pub trait Timeout {
+    /// Timer counting down the timeout.
+    type Timer: CountDown;
+
+    /// Returns the timer along with the timeout for the operation `op`, or `None` to wait indefinitely.
+    fn get(
+        &mut self,
+        op: Operation,
+    ) -> Option<(&mut Self::Timer, <Self::Timer as CountDown>::Time)>;
+
+    /// Called after each attempt of `op` which was either answered by the sensor, even with
+    /// a corrupted frame or an exception, or `timed_out`, for the timeouts adapting to the
+    /// latency of the link, look [`AdaptiveTimeout`](struct.AdaptiveTimeout.html). The timer
+    /// has not been restarted since the response was awaited. Does nothing by default.
+    fn observe(&mut self, op: Operation, timed_out: bool) {
+        let _ = (op, timed_out);
+    }
+}
+
+impl<T: CountDown> Timeout for Option<(&mut T, T::Time)>
+where
+    T::Time: Clone,
+{
+    type Timer = T;
+    fn get(&mut self, _op: Operation) -> Option<(&mut T, T::Time)> {
+        self.as_mut()
+            .map(|(timer, time)| (&mut **timer, time.clone()))
+    }
+}
+
+impl<Tm: Timeout + ?Sized> Timeout for &mut Tm {
+    type Timer = Tm::Timer;
+    fn get(
+        &mut self,
+        op: Operation,
+    ) -> Option<(&mut Self::Timer, <Self::Timer as CountDown>::Time)> {
+        (**self).get(op)
+    }
+
+    fn observe(&mut self, op: Operation, timed_out: bool) {
+        (**self).observe(op, timed_out)
+    }
+}
+
+/// Per-operation timeouts.
+///
+/// Practical values are about 100 ms for reading, 500 ms for writing a parameter
+/// and 1 s for resetting the energy counter.
+///
+/// # Example
+/// ```ignore
+/// const TIMEOUTS: Timeouts<MilliSeconds> = Timeouts {
+///     read: MilliSeconds(100),
+///     read_param: MilliSeconds(100),
+///     write_param: MilliSeconds(500),
+///     reset: MilliSeconds(1000),
+/// };
+///
+/// pzem.read(&mut m, (&mut tim, TIMEOUTS))?;
+/// pzem.reset_energy((&mut tim, TIMEOUTS))?;
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Timeouts<Time> {
+    pub read: Time,
+    pub read_param: Time,
+    pub write_param: Time,
+    pub reset: Time,
+}
+
+impl<Time: Clone> Timeouts<Time> {
+    /// Uses the same timeout for every operation.
+    pub fn uniform(time: Time) -> Self {
+        Self {
+            read: time.clone(),
+            read_param: time.clone(),
+            write_param: time.clone(),
+            reset: time,
+        }
+    }
+
+    /// Returns the timeout for the operation `op`.
+    pub fn get(&self, op: Operation) -> Time {
+        match op {
+            Operation::Read => self.read.clone(),
+            Operation::ReadParam => self.read_param.clone(),
+            Operation::WriteParam => self.write_param.clone(),
+            Operation::Reset => self.reset.clone(),
+        }
+    }
+}
+
+impl<T: CountDown> Timeout for (&mut T, Timeouts<T::Time>)
+where
+    T::Time: Clone,
+{
+    type Timer = T;
+    fn get(&mut self, op: Operation) -> Option<(&mut T, T::Time)> {
+        Some((&mut *self.0, self.1.get(op)))
+    }
+}
+
+/// Timer borrowed once along with the timeouts, for a sequence of operations on one or more devices.
+///
+/// Handing the same session to every call of a polling cycle keeps the timeouts consistent
+/// across the devices.
+///
+/// # Example
+/// ```ignore
+/// let mut session = Session::uniform(&mut tim, TIMEOUT);
+/// pzem1.read(&mut m1, &mut session)?;
+/// pzem2.read(&mut m2, &mut session)?;
+/// ```
+pub struct Session<'a, T: CountDown> {
+    timer: &'a mut T,
+    timeouts: Timeouts<T::Time>,
+}
+
+impl<'a, T: CountDown> Session<'a, T>
+where
+    T::Time: Clone,
+{
+    /// Starts a session with per-operation timeouts.
+    pub fn new(timer: &'a mut T, timeouts: Timeouts<T::Time>) -> Self {
+        Self { timer, timeouts }
+    }
+
+    /// Starts a session using the same timeout for every operation.
+    pub fn uniform(timer: &'a mut T, time: T::Time) -> Self {
+        Self::new(timer, Timeouts::uniform(time))
+    }
+
+    /// Returns the timeouts of the session.
+    pub fn timeouts(&self) -> &Timeouts<T::Time> {
+        &self.timeouts
+    }
+}
+
+impl<T: CountDown> Timeout for Session<'_, T>
+where
+    T::Time: Clone,
+{
+    type Timer = T;
+    fn get(&mut self, op: Operation) -> Option<(&mut T, T::Time)> {
+        Some((&mut *self.timer, self.timeouts.get(op)))
+    }
+}
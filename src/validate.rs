@@ -0,0 +1,71 @@
+use hal::serial;
+
+use crate::{Error, Event, Measurement, Pzem, RawMeasurement, Timeout};
+
+/// User-defined plausibility rules, checked after decoding each measurement by
+/// [`Pzem::read_validated`](struct.Pzem.html#method.read_validated).
+///
+/// Implemented for closures taking the new and the previous measurement.
+///
+/// # Example
+/// ```ignore
+/// // Power can't jump by 5 kW in a single polling period in this installation.
+/// let mut validator = |m: &Measurement, prev: Option<&Measurement>| match prev {
+///     Some(prev) => (m.power - prev.power).abs() < 5000.0,
+///     None => true,
+/// };
+///
+/// pzem.read_validated(&mut m, &mut validator, Some((&mut tim, TIMEOUT)))?;
+/// ```
+pub trait Validator {
+    /// Returns `true` if the measurement `m` is plausible, given the previously accepted
+    /// measurement `prev`, if any.
+    fn validate(&mut self, m: &Measurement, prev: Option<&Measurement>) -> bool;
+
+    /// Number of times a rejected measurement is read again before giving up.
+    fn retries(&self) -> u8 {
+        1
+    }
+}
+
+impl<F: FnMut(&Measurement, Option<&Measurement>) -> bool> Validator for F {
+    fn validate(&mut self, m: &Measurement, prev: Option<&Measurement>) -> bool {
+        self(m, prev)
+    }
+}
+
+impl<Serial, State, WriteError, ReadError> Pzem<Serial, State>
+where
+    Serial: serial::Write<u8, Error = WriteError> + serial::Read<u8, Error = ReadError>,
+{
+    /// Reads the measurements off the sensor like [`read`](#method.read), checking them with `validator`.
+    ///
+    /// A rejected measurement is read again, up to [`Validator::retries`](trait.Validator.html#method.retries)
+    /// times, after which `Err(Error::Implausible)` is returned and `m` is left untouched.
+    /// The previous measurement passed to the validator is the last one accepted by this method.
+    pub fn read_validated<V: Validator, Tm: Timeout>(
+        &mut self,
+        m: &mut Measurement,
+        validator: &mut V,
+        mut timeout: Tm,
+    ) -> Result<(), Error<WriteError, ReadError>> {
+        let mut sample_raw = RawMeasurement::default();
+        for _ in 0..=validator.retries() {
+            self.read_raw(&mut sample_raw, &mut timeout)?;
+            let sample = self.convert(&sample_raw);
+
+            let prev = self.last.map(|raw| self.convert(&raw));
+            if validator.validate(&sample, prev.as_ref()) {
+                self.last = Some(sample_raw);
+                *m = sample;
+                return Ok(());
+            }
+
+            count!(self.stats.rejected);
+            log_warn!("PZEM004T {:#04x}: implausible {:?}", self.addr, sample);
+        }
+
+        self.record(Event::Implausible);
+        Err(Error::Implausible)
+    }
+}
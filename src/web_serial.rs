@@ -0,0 +1,200 @@
+use crate::codec::{
+    crc_check_slice, crc_write, SoftwareCrc, EXCEPTION_LEN, PARAM_RESP_LEN, REQ_LEN,
+};
+#[cfg(not(feature = "no-float"))]
+use crate::Measurement;
+use crate::{
+    decode, Error, RawMeasurement, ADDR_DEFAULT, ADDR_MAX, ADDR_MIN, CMD_READ, CMD_READ_PARAM,
+    EXCEPTION_FLAG, PARAM_ADDR, PARAM_THRESHOLD, READ_FRAME_MAX, REG_COUNT,
+};
+
+/// Asynchronous serial port, as provided to WebAssembly by the
+/// [Web Serial API](https://developer.mozilla.org/en-US/docs/Web/API/Web_Serial_API).
+///
+/// Implemented by the application over the streams of a `SerialPort` opened at 9600 baud,
+/// e.g. through `wasm-bindgen-futures`. The browser can't block, hence the driver awaits the
+/// port instead of polling it.
+///
+/// # Example
+///
+/// ```ignore
+/// struct Port {
+///     reader: web_sys::ReadableStreamDefaultReader,
+///     writer: web_sys::WritableStreamDefaultWriter,
+///     pending: Vec<u8>,
+/// }
+///
+/// impl AsyncPort for Port {
+///     type Error = JsValue;
+///
+///     async fn write(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+///         JsFuture::from(self.writer.write_with_chunk(&Uint8Array::from(bytes))).await?;
+///         Ok(())
+///     }
+///
+///     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, JsValue> {
+///         if self.pending.is_empty() {
+///             // Race the reader against a 500 ms timer, resolving to an empty chunk.
+///             self.pending = read_chunk_or_timeout(&self.reader, 500).await?;
+///         }
+///         let n = buf.len().min(self.pending.len());
+///         buf[..n].copy_from_slice(&self.pending[..n]);
+///         self.pending.drain(..n);
+///         Ok(n)
+///     }
+/// }
+/// ```
+#[allow(async_fn_in_trait)]
+pub trait AsyncPort {
+    type Error;
+
+    /// Writes all the bytes to the port.
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Reads the bytes received into `buf`, returning their number. Resolves to zero once
+    /// nothing has been received within the read timeout of the application.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+/// PZEM004T driver over an [`AsyncPort`](trait.AsyncPort.html), for the browser dashboards
+/// talking to a USB-RS485 dongle directly.
+///
+/// Encodes the same frames and checks the responses the same way as [`Pzem`](struct.Pzem.html),
+/// reporting the failures with the same [`Error`](enum.Error.html), but covers only the
+/// reading operations. A request is answered within the read timeout of the port, or fails
+/// with `Err(Error::TimedOut { received })`.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut pzem = WebSerial::new(Port::open().await?, None).unwrap();
+/// let m = pzem.read().await?;
+/// ```
+pub struct WebSerial<P> {
+    port: P,
+    addr: u8,
+}
+
+impl<P: AsyncPort> WebSerial<P> {
+    /// Creates the driver for the sensor at `addr`, or at the general address if `None`.
+    ///
+    /// Can return `Err(Error::IllegalAddress)` if `addr` is not in the range of legal addresses
+    /// `[0x01..0xf7]`.
+    pub fn new(port: P, addr: Option<u8>) -> Result<Self, Error<P::Error, P::Error>> {
+        let addr = addr.unwrap_or(ADDR_DEFAULT);
+        if addr != ADDR_DEFAULT && !(ADDR_MIN..=ADDR_MAX).contains(&addr) {
+            return Err(Error::IllegalAddress);
+        }
+
+        Ok(Self { port, addr })
+    }
+
+    /// Reads the measurements off the sensor, decoded with the datasheet scaling.
+    #[cfg(not(feature = "no-float"))]
+    pub async fn read(&mut self) -> Result<Measurement, Error<P::Error, P::Error>> {
+        self.read_raw().await.map(Measurement::from)
+    }
+
+    /// Reads the raw measurement registers off the sensor.
+    pub async fn read_raw(&mut self) -> Result<RawMeasurement, Error<P::Error, P::Error>> {
+        let mut resp = [0u8; READ_FRAME_MAX];
+        let len = self
+            .exchange(CMD_READ, 0x0000, REG_COUNT, &mut resp)
+            .await?;
+
+        decode::parse(&resp[..len]).map_err(Error::Decode)
+    }
+
+    /// Reads the power alarm threshold of the sensor in W.
+    pub async fn get_threshold(&mut self) -> Result<u16, Error<P::Error, P::Error>> {
+        self.read_param(PARAM_THRESHOLD).await
+    }
+
+    /// Reads the Modbus-RTU address of the sensor.
+    pub async fn get_addr(&mut self) -> Result<u16, Error<P::Error, P::Error>> {
+        self.read_param(PARAM_ADDR).await
+    }
+
+    /// Returns a mutable reference to the port.
+    pub fn port_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Releases the port.
+    pub fn release(self) -> P {
+        self.port
+    }
+
+    async fn read_param(&mut self, param: u16) -> Result<u16, Error<P::Error, P::Error>> {
+        let mut resp = [0u8; PARAM_RESP_LEN];
+        self.exchange(CMD_READ_PARAM, param, 1, &mut resp).await?;
+
+        Ok(u16::from_be_bytes([resp[3], resp[4]]))
+    }
+
+    // Sends the request and receives the response into `resp`, checking its header and CRC.
+    // Returns the length of the response, as told by its byte count.
+    async fn exchange(
+        &mut self,
+        function: u8,
+        reg: u16,
+        count: u16,
+        resp: &mut [u8],
+    ) -> Result<usize, Error<P::Error, P::Error>> {
+        let mut req = [0u8; REQ_LEN];
+        req[..2].copy_from_slice(&[self.addr, function]);
+        req[2..4].copy_from_slice(&reg.to_be_bytes());
+        req[4..6].copy_from_slice(&count.to_be_bytes());
+        crc_write(&SoftwareCrc, &mut req);
+
+        self.port.write(&req).await.map_err(Error::WriteError)?;
+
+        // Until the byte count is received, the length is known to be at least 3 bytes.
+        let mut len = 3;
+        let mut received = 0;
+        while received < len {
+            let n = self
+                .port
+                .read(&mut resp[received..len])
+                .await
+                .map_err(Error::ReadError)?;
+            if n == 0 {
+                return Err(Error::TimedOut {
+                    received: received as u8,
+                });
+            }
+
+            // The exception responses are shorter than the regular ones.
+            if received < 2 && received + n >= 2 {
+                if resp[0] != req[0] || (resp[1] != req[1] && resp[1] != req[1] | EXCEPTION_FLAG) {
+                    return Err(Error::PzemError);
+                }
+                if resp[1] & EXCEPTION_FLAG != 0 {
+                    len = EXCEPTION_LEN;
+                }
+            }
+            received += n;
+
+            if received >= 3 && len == 3 && resp[1] & EXCEPTION_FLAG == 0 {
+                let bytes = resp[2] as usize;
+                let valid = match function {
+                    CMD_READ => decode::valid_count(bytes),
+                    _ => bytes == 2 * count as usize,
+                };
+                len = 3 + bytes + 2;
+                if !valid || len > resp.len() {
+                    return Err(Error::PzemError);
+                }
+            }
+        }
+
+        if !crc_check_slice(&SoftwareCrc, &resp[..len]) {
+            return Err(Error::CrcMismatch);
+        }
+        if len == EXCEPTION_LEN && resp[1] & EXCEPTION_FLAG != 0 {
+            return Err(Error::Exception(resp[2].into()));
+        }
+
+        Ok(len)
+    }
+}
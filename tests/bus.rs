@@ -0,0 +1,218 @@
+//! Scans and polls of several simulated sensors sharing a single bus.
+
+#![cfg(not(feature = "no-float"))]
+
+use core::convert::Infallible;
+use std::collections::VecDeque;
+
+use embedded_hal::serial;
+use pzem004t::{CrcProvider, Error, Event, EventLog, Measurement, Poller, Pzem, SoftwareCrc};
+
+// Simulated sensor on the bus, answering the reads of the measurements only.
+struct Slave {
+    addr: u8,
+    voltage: u16,
+    // Whether the responses fail the CRC check, as of two sensors answering at once.
+    garbled: bool,
+}
+
+impl Slave {
+    fn new(addr: u8, voltage: u16) -> Self {
+        Self {
+            addr,
+            voltage,
+            garbled: false,
+        }
+    }
+}
+
+// Bus answering every complete request at once, silent on the addresses without a slave.
+struct Bus {
+    slaves: Vec<Slave>,
+    req: Vec<u8>,
+    rx: VecDeque<u8>,
+}
+
+impl Bus {
+    fn new(slaves: Vec<Slave>) -> Self {
+        Self {
+            slaves,
+            req: Vec::new(),
+            rx: VecDeque::new(),
+        }
+    }
+
+    fn respond(&mut self) {
+        let req = std::mem::take(&mut self.req);
+        assert_eq!(req[1], 0x04, "unexpected request: {:02x?}", req);
+        let slave = match self.slaves.iter().find(|s| s.addr == req[0]) {
+            Some(slave) => slave,
+            None => return,
+        };
+
+        let mut resp = vec![slave.addr, 0x04, 20];
+        resp.extend_from_slice(&slave.voltage.to_be_bytes());
+        resp.extend_from_slice(&[0; 18]);
+        let crc = SoftwareCrc.crc(&resp);
+        resp.extend_from_slice(&crc.to_le_bytes());
+        if slave.garbled {
+            resp[5] ^= 0x10;
+        }
+
+        self.rx.extend(resp);
+    }
+}
+
+impl serial::Read<u8> for Bus {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for Bus {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.req.push(word);
+        if self.req.len() == 8 {
+            self.respond();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Timer expiring after a number of polls, the time of the simulated bus.
+struct PollTimer(u32);
+
+impl embedded_hal::timer::CountDown for PollTimer {
+    type Time = u32;
+
+    fn start<T: Into<u32>>(&mut self, count: T) {
+        self.0 = count.into();
+    }
+
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        match self.0.checked_sub(1) {
+            Some(left) => {
+                self.0 = left;
+                Err(nb::Error::WouldBlock)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn garbled(addr: u8) -> Slave {
+    Slave {
+        garbled: true,
+        ..Slave::new(addr, 0)
+    }
+}
+
+#[test]
+fn scan_bus_results() {
+    let bus = Bus::new(vec![
+        Slave::new(0x03, 2301),
+        Slave::new(0x10, 2190),
+        garbled(0x20),
+    ]);
+    let mut pzem = Pzem::new(bus, None).unwrap();
+    let mut tim = PollTimer(0);
+
+    let results = pzem.scan_bus::<_, 4>(Some((&mut tim, 100u32)));
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].0, 0x03);
+    assert_eq!(results[0].1.as_ref().unwrap().voltage, 230.1);
+    assert_eq!(results[1].0, 0x10);
+    assert_eq!(results[1].1.as_ref().unwrap().voltage, 219.0);
+    assert_eq!(results[2].0, 0x20);
+    assert!(matches!(results[2].1, Err(Error::ProbableAddressConflict)));
+}
+
+#[test]
+fn scan_bus_stops_when_full() {
+    let bus = Bus::new(vec![
+        Slave::new(0x03, 2301),
+        Slave::new(0x10, 2190),
+        Slave::new(0x20, 2250),
+    ]);
+    let mut pzem = Pzem::new(bus, None).unwrap();
+    let mut tim = PollTimer(0);
+
+    let results = pzem.scan_bus::<_, 2>(Some((&mut tim, 100u32)));
+    let addrs: Vec<u8> = results.iter().map(|(addr, _)| *addr).collect();
+    assert_eq!(addrs, [0x03, 0x10]);
+    assert!(results.iter().all(|(_, res)| res.is_ok()));
+}
+
+#[test]
+fn scan_keeps_event_log() {
+    let bus = Bus::new(vec![garbled(0x01)]);
+    let log = Box::leak(Box::new(EventLog::<8>::new()));
+    let mut pzem = Pzem::new(bus, Some(0x01))
+        .unwrap()
+        .with_event_log(log, || 0);
+    let mut tim = PollTimer(0);
+
+    let mut m = Measurement::default();
+    assert!(pzem.read(&mut m, Some((&mut tim, 100u32))).is_err());
+    let recorded = |pzem: &mut Pzem<Bus>| {
+        let log = pzem.event_log().unwrap();
+        log.iter().map(|r| r.event).collect::<Vec<_>>()
+    };
+    assert_eq!(recorded(&mut pzem), [Event::CrcMismatch]);
+
+    // Nobody on the scanned addresses: every probe times out.
+    let results = pzem.scan_bus_range::<_, 4>(0x10..=0x30, Some((&mut tim, 100u32)));
+    assert!(results.is_empty());
+    assert_eq!(pzem.scan(0x40..=0x50, Some((&mut tim, 100u32))).count(), 17);
+
+    assert_eq!(recorded(&mut pzem), [Event::CrcMismatch]);
+    let state = pzem.debug_state();
+    assert_eq!(state.last_error, Some(Event::CrcMismatch));
+    assert!(!state.pending);
+}
+
+#[test]
+fn poller_results() {
+    let bus = Bus::new(vec![Slave::new(0x01, 2301), garbled(0x03)]);
+    let mut poller = Poller::<_, 4>::new(bus, &[0x01, 0x02, 0x03]).unwrap();
+    let mut tim = PollTimer(0);
+
+    for _ in 0..2 {
+        let results = poller.poll(Some((&mut tim, 100u32)));
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, 0x01);
+        assert_eq!(results[0].1.as_ref().unwrap().voltage, 230.1);
+        assert_eq!(results[1].0, 0x02);
+        assert!(matches!(results[1].1, Err(Error::TimedOut { received: 0 })));
+        assert_eq!(results[2].0, 0x03);
+        assert!(matches!(results[2].1, Err(Error::CrcMismatch)));
+    }
+
+    assert!(poller.release().rx.is_empty());
+}
+
+#[test]
+fn poller_addresses() {
+    let bus = || Bus::new(Vec::new());
+
+    assert!(matches!(
+        Poller::<_, 2>::new(bus(), &[0x01, 0x02, 0x03]),
+        Err(Error::TooManyAddresses)
+    ));
+    assert!(matches!(
+        Poller::<_, 2>::new(bus(), &[0x01, 0xf8]),
+        Err(Error::IllegalAddress)
+    ));
+    assert_eq!(
+        Poller::<_, 2>::new(bus(), &[0x01, 0x02]).unwrap().addrs(),
+        [0x01, 0x02]
+    );
+}
@@ -0,0 +1,195 @@
+//! Runs the driver against the sensor emulator over a socket pair.
+
+#![cfg(all(feature = "std", unix))]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use pzem004t::{
+    EmulatedSlave, Error, Faults, LinkStatus, Pzem, PzemEmulator, RawMeasurement, Reconnecting,
+    RetryPolicy, StdIo, StdTimer, Verified,
+};
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+const LOAD: RawMeasurement = RawMeasurement {
+    voltage: 2301,
+    current: 4350,
+    power: 10_000,
+    energy: 12345,
+    frequency: 500,
+    pf: 100,
+    alarm: false,
+};
+
+// Spawns the emulator serving the slaves, returning the stream of the master.
+fn serve(slaves: Vec<EmulatedSlave>, faults: Faults) -> StdIo<UnixStream> {
+    let (master, emulated) = UnixStream::pair().unwrap();
+    master
+        .set_read_timeout(Some(Duration::from_millis(5)))
+        .unwrap();
+    emulated
+        .set_read_timeout(Some(Duration::from_millis(5)))
+        .unwrap();
+
+    let mut emulator = slaves
+        .into_iter()
+        .fold(PzemEmulator::new(emulated), PzemEmulator::with_slave)
+        .with_faults(faults);
+    thread::spawn(move || emulator.run());
+
+    StdIo::new(master)
+}
+
+fn verified(stream: StdIo<UnixStream>, addr: u8) -> Pzem<StdIo<UnixStream>, Verified> {
+    Pzem::new(stream, Some(addr))
+        .unwrap()
+        .probe(Some((&mut StdTimer::new(), TIMEOUT)))
+        .map_err(|(_, e)| e)
+        .unwrap()
+}
+
+#[test]
+fn read_and_configure() {
+    let stream = serve(
+        vec![
+            EmulatedSlave::new(0x01, LOAD),
+            EmulatedSlave::new(0x02, RawMeasurement::default()),
+        ],
+        Faults::default(),
+    );
+    let mut tim = StdTimer::new();
+    let mut pzem = verified(stream, 0x01);
+
+    let mut m = RawMeasurement::default();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    assert_eq!(m, LOAD);
+
+    // 1000 W load against the alarm threshold.
+    pzem.set_threshold(900, Some((&mut tim, TIMEOUT))).unwrap();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    assert!(m.alarm);
+
+    pzem.reset_energy(Some((&mut tim, TIMEOUT))).unwrap();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    assert_eq!(m.energy, 0);
+
+    pzem.set_addr(0x03, Some((&mut tim, TIMEOUT))).unwrap();
+    assert_eq!(pzem.get_addr(Some((&mut tim, TIMEOUT))).unwrap(), 0x03);
+
+    // The other slave is still there.
+    let mut pzem = Pzem::new(pzem.release(), Some(0x02)).unwrap();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    assert_eq!(m, RawMeasurement::default());
+}
+
+#[test]
+fn faults() {
+    let faults = Faults {
+        drop_one_in: Some(4),
+        ..Faults::default()
+    };
+    let stream = serve(vec![EmulatedSlave::new(0x01, LOAD)], faults);
+    let mut tim = StdTimer::new();
+    let mut pzem = Pzem::new(stream, Some(0x01)).unwrap();
+
+    let mut m = RawMeasurement::default();
+    for _ in 0..10 {
+        match pzem.read_raw(&mut m, Some((&mut tim, Duration::from_millis(50)))) {
+            Ok(()) => assert_eq!(m, LOAD),
+            Err(Error::TimedOut { .. }) | Err(Error::CrcMismatch) | Err(Error::PzemError) => {}
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+    #[cfg(not(feature = "size-opt"))]
+    assert!(pzem.stats().timeouts > 0);
+}
+
+#[test]
+fn reconnect() {
+    let open = || Ok(serve(vec![EmulatedSlave::new(0x01, LOAD)], Faults::default()).into_inner());
+    let policy = RetryPolicy {
+        interval: Duration::ZERO,
+        max_attempts: Some(1),
+    };
+    let stream = Reconnecting::new(open, policy).unwrap();
+    let mut tim = StdTimer::new();
+    let mut pzem = Pzem::new(StdIo::new(stream), Some(0x01)).unwrap();
+
+    let mut m = RawMeasurement::default();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+
+    // Adapter unplugged.
+    let mut stream = pzem.release();
+    stream
+        .get_mut()
+        .get_mut()
+        .unwrap()
+        .shutdown(std::net::Shutdown::Both)
+        .unwrap();
+    let mut pzem = Pzem::new(stream, Some(0x01)).unwrap();
+    match pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))) {
+        Err(Error::ReadError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        res => panic!("read over a closed stream: {:?}", res),
+    }
+
+    let mut stream = pzem.release();
+    assert_eq!(stream.get_mut().take_status(), LinkStatus::Disconnected);
+
+    let mut pzem = Pzem::new(stream, Some(0x01)).unwrap();
+    pzem.read_raw(&mut m, Some((&mut tim, TIMEOUT))).unwrap();
+    assert_eq!(m, LOAD);
+
+    let mut stream = pzem.release().into_inner();
+    assert_eq!(stream.take_status(), LinkStatus::Reconnected);
+    assert_eq!(stream.take_status(), LinkStatus::Connected);
+}
+
+#[cfg(not(feature = "no-float"))]
+#[test]
+fn scan_range() {
+    let stream = serve(
+        vec![
+            EmulatedSlave::new(0x02, LOAD),
+            EmulatedSlave::new(0x05, LOAD),
+            EmulatedSlave::new(0x09, LOAD),
+        ],
+        Faults::default(),
+    );
+    let mut tim = StdTimer::new();
+    let mut pzem = Pzem::new(stream, None).unwrap();
+    let timeout = Duration::from_millis(30);
+
+    let mut scan = pzem
+        .scan(0x00..=0x10, Some((&mut tim, timeout)))
+        .stop_after(2);
+    let probed: Vec<_> = scan
+        .by_ref()
+        .map(|(addr, res)| (addr, res.is_ok()))
+        .collect();
+    assert_eq!(
+        probed,
+        [(1, false), (2, true), (3, false), (4, false), (5, true)]
+    );
+    assert_eq!(scan.found(), 2);
+
+    let results = pzem.scan_bus_range::<_, 4>(0x04..=0x0a, Some((&mut tim, timeout)));
+    let found: Vec<_> = results.iter().map(|(addr, _)| *addr).collect();
+    assert_eq!(found, [0x05, 0x09]);
+}
+
+#[test]
+fn emulator_buffer_overflow() {
+    use std::io::Write;
+
+    let (mut master, emulated) = UnixStream::pair().unwrap();
+    emulated.set_nonblocking(true).unwrap();
+    let mut emulator =
+        PzemEmulator::<_, 16>::with_buffer(emulated).with_slave(EmulatedSlave::new(0x01, LOAD));
+
+    // Garbage flooding the line faster than it's parsed.
+    master.write_all(&[0xff; 40]).unwrap();
+    assert_eq!(emulator.poll().unwrap(), 0);
+    assert_eq!(emulator.overflows(), 40 - 16);
+}
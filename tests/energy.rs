@@ -0,0 +1,125 @@
+//! Projections and book-keeping of the energy counter.
+
+#![cfg(not(feature = "no-float"))]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use pzem004t::{DailyEnergy, Date, Measurement, Rtc, ENERGY_MAX};
+
+fn counter(energy: f32) -> Measurement {
+    Measurement {
+        energy,
+        ..Measurement::default()
+    }
+}
+
+fn date(year: u16, month: u8, day: u8) -> Date {
+    Date { year, month, day }
+}
+
+// Fake real-time clock, set by the test through the returned cell.
+fn clock(today: Date) -> (Rc<Cell<Date>>, impl Rtc) {
+    let cell = Rc::new(Cell::new(today));
+    let rtc = {
+        let cell = cell.clone();
+        move || cell.get()
+    };
+    (cell, rtc)
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 0.01,
+        "{} kWh instead of {} kWh",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn needs_reset_soon() {
+    // 1 kWh left at 100 W on average: 10 hours.
+    let m = counter(ENERGY_MAX - 1.0);
+    assert!((m.hours_to_rollover(100.0).unwrap() - 10.0).abs() < 0.01);
+    assert!(m.needs_reset_soon(100.0, 12.0));
+    assert!(!m.needs_reset_soon(100.0, 8.0));
+
+    // Idle at the time of the sample: the average decides.
+    let idle = Measurement { power: 0.0, ..m };
+    assert!(idle.needs_reset_soon(100.0, 12.0));
+
+    // No consumption: never rolls over.
+    assert_eq!(m.hours_to_rollover(0.0), None);
+    assert!(!m.needs_reset_soon(0.0, 12.0));
+}
+
+#[test]
+fn counter_at_max() {
+    let m = counter(ENERGY_MAX);
+    assert_eq!(m.hours_to_rollover(100.0), Some(0.0));
+    assert!(m.needs_reset_soon(100.0, 0.0));
+    assert!(m.needs_reset_soon(0.0, 0.0));
+}
+
+#[test]
+fn daily_energy_days() {
+    let (now, rtc) = clock(date(2024, 2, 28));
+    let mut daily = DailyEnergy::new(rtc).with_total(5.0);
+
+    // The first measurement only sets the reference of the counter.
+    daily.update_energy(100.0);
+    daily.update_energy(101.5);
+    assert_close(daily.today(), 1.5);
+    assert_close(daily.yesterday(), 0.0);
+    assert_close(daily.total(), 6.5);
+
+    // Midnight, on a leap year.
+    now.set(date(2024, 2, 29));
+    daily.update_energy(102.0);
+    assert_close(daily.today(), 0.5);
+    assert_close(daily.yesterday(), 1.5);
+    assert_close(daily.total(), 7.0);
+
+    // Counter reset by the user.
+    daily.update_energy(0.25);
+    assert_close(daily.today(), 0.75);
+    assert_close(daily.total(), 7.25);
+
+    // Nothing measured on the 1st of March.
+    now.set(date(2024, 3, 2));
+    daily.update_energy(0.5);
+    assert_close(daily.today(), 0.25);
+    assert_close(daily.yesterday(), 0.0);
+    assert_close(daily.total(), 7.5);
+}
+
+#[test]
+fn daily_energy_rollover() {
+    let (now, rtc) = clock(date(2024, 12, 31));
+    let mut daily = DailyEnergy::new(rtc);
+
+    daily.update_energy(ENERGY_MAX - 1.0);
+    daily.update_energy(ENERGY_MAX - 0.5);
+    assert_close(daily.today(), 0.5);
+
+    // The counter rolls over right after midnight on New Year's Eve.
+    now.set(date(2025, 1, 1));
+    daily.update_energy(0.25);
+    assert_close(daily.today(), 0.75);
+    assert_close(daily.yesterday(), 0.5);
+    assert_close(daily.total(), 1.25);
+}
+
+#[test]
+fn daily_energy_exact_total() {
+    let (_, rtc) = clock(date(2024, 6, 1));
+    let mut daily = DailyEnergy::new(rtc).with_total(20_000.0);
+
+    // Steps of 1 Wh, finer than the resolution of `f32` at 20 MWh.
+    for wh in 0..=1000 {
+        daily.update_energy(1234.0 + wh as f32 / 1000.0);
+    }
+    assert_eq!(daily.today(), 1.0);
+    assert_eq!(daily.total(), 20_001.0);
+}
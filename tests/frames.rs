@@ -0,0 +1,1206 @@
+//! Round-trip tests of the request/response frames against a simulated sensor,
+//! driven by pseudo-random addresses, parameters and register values.
+
+use core::convert::Infallible;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use embedded_hal::serial;
+use pzem004t::{
+    clamp_threshold, decode, Command, CrcProvider, Diagnosis, Error, NoTimeout, Pzem,
+    RawMeasurement, ResponseBuf, Sniffed, Sniffer, SoftwareCrc, ThresholdError, Verified,
+    WriteMode, READ_FRAME_LEN, READ_FRAME_MAX, THRESHOLD_MAX,
+};
+
+const ITERATIONS: usize = 1000;
+
+// Xorshift PRNG, so that the failing case can be reproduced from the seed.
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.next() as u8
+    }
+
+    fn u16(&mut self) -> u16 {
+        self.next() as u16
+    }
+
+    // Valid slave address, 0x01..=0xf7.
+    fn addr(&mut self) -> u8 {
+        (self.next() % 0xf7) as u8 + 1
+    }
+}
+
+fn crc_valid(frame: &[u8]) -> bool {
+    let (data, crc) = frame.split_at(frame.len() - 2);
+    SoftwareCrc.crc(data) == u16::from_le_bytes([crc[0], crc[1]])
+}
+
+fn push_crc(frame: &mut Vec<u8>) {
+    let crc = SoftwareCrc.crc(frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+}
+
+// Simulated sensor, answering every complete request at once.
+#[derive(Default)]
+struct Device {
+    addr: u8,
+    threshold: u16,
+    regs: [u16; 10],
+    // Registers answered past the 10 measurement ones, as by some clones.
+    extra: Vec<u16>,
+    energy_reset: bool,
+    // Index of the response byte to corrupt.
+    corrupt: Option<usize>,
+    req: Vec<u8>,
+    rx: VecDeque<u8>,
+}
+
+impl Device {
+    fn request_len(&self) -> Option<usize> {
+        match *self.req.get(1)? {
+            0x42 => Some(4),
+            0x10 => Some(11),
+            _ => Some(8),
+        }
+    }
+
+    fn respond(&mut self) {
+        let req = std::mem::take(&mut self.req);
+        assert!(crc_valid(&req), "invalid request CRC: {:02x?}", req);
+        assert!(
+            req[0] == self.addr || req[0] == 0xf8,
+            "wrong address: {:02x?}",
+            req
+        );
+
+        let mut resp = vec![req[0], req[1]];
+        match req[1] {
+            0x04 => {
+                assert_eq!(req[2..6], [0, 0, 0, 10]);
+                resp.push(20 + 2 * self.extra.len() as u8);
+                for reg in self.regs.iter().chain(&self.extra) {
+                    resp.extend_from_slice(&reg.to_be_bytes());
+                }
+            }
+            0x03 => {
+                let start = u16::from_be_bytes([req[2], req[3]]);
+                let count = u16::from_be_bytes([req[4], req[5]]);
+                resp.push(2 * count as u8);
+                for reg in start..start + count {
+                    let value = match reg {
+                        1 => self.threshold,
+                        2 => self.addr as u16,
+                        _ => panic!("unknown parameter: {:02x?}", req),
+                    };
+                    resp.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            0x06 | 0x10 => {
+                let value = if req[1] == 0x06 {
+                    u16::from_be_bytes([req[4], req[5]])
+                } else {
+                    assert_eq!(req[4..7], [0, 1, 2]);
+                    u16::from_be_bytes([req[7], req[8]])
+                };
+                resp.extend_from_slice(&req[2..6]);
+                match req[3] {
+                    1 => self.threshold = value,
+                    2 => self.addr = value as u8,
+                    _ => panic!("unknown parameter: {:02x?}", req),
+                }
+            }
+            0x42 => self.energy_reset = true,
+            _ => panic!("unknown function: {:02x?}", req),
+        }
+        push_crc(&mut resp);
+
+        if let Some(i) = self.corrupt {
+            let len = resp.len();
+            resp[i % len] ^= 1 << (i % 8);
+        }
+
+        self.rx.extend(resp);
+    }
+}
+
+impl serial::Read<u8> for Device {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for Device {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.req.push(word);
+        if Some(self.req.len()) == self.request_len() {
+            self.respond();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Timer expiring after a number of polls, the time of the simulated device.
+struct PollTimer(u32);
+
+impl embedded_hal::timer::CountDown for PollTimer {
+    type Time = u32;
+
+    fn start<T: Into<u32>>(&mut self, count: T) {
+        self.0 = count.into();
+    }
+
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        match self.0.checked_sub(1) {
+            Some(left) => {
+                self.0 = left;
+                Err(nb::Error::WouldBlock)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// Device going silent past the timeout, then streaming without end.
+struct Babbler {
+    silent: u32,
+}
+
+impl serial::Read<u8> for Babbler {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        match self.silent.checked_sub(1) {
+            Some(left) => {
+                self.silent = left;
+                Err(nb::Error::WouldBlock)
+            }
+            None => Ok(0x55),
+        }
+    }
+}
+
+impl serial::Write<u8> for Babbler {
+    type Error = Infallible;
+
+    fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn verified(device: Device, addr: Option<u8>) -> Pzem<Device, Verified> {
+    Pzem::new(device, addr)
+        .unwrap()
+        .probe(NoTimeout)
+        .map_err(|(_, e)| e)
+        .unwrap()
+}
+
+#[test]
+fn read_round_trip() {
+    let mut rng = Rng(0x2545_f491);
+    for _ in 0..ITERATIONS {
+        let mut regs = [0; 10];
+        regs.iter_mut().for_each(|reg| *reg = rng.u16());
+        let addr = rng.addr();
+
+        let device = Device {
+            addr,
+            regs,
+            ..Device::default()
+        };
+        let mut pzem = Pzem::new(device, Some(addr)).unwrap();
+
+        let mut m = RawMeasurement::default();
+        pzem.read_raw(&mut m, NoTimeout).unwrap();
+        assert_eq!(m, RawMeasurement::from_registers(&regs));
+
+        let mut frame = [0; READ_FRAME_LEN];
+        pzem.read_raw_with_frame(&mut m, &mut frame, NoTimeout)
+            .unwrap();
+        assert_eq!(decode::decode_frame(&frame), Some(m));
+    }
+}
+
+#[test]
+fn param_round_trip() {
+    let mut rng = Rng(0x9e37_79b9);
+    for i in 0..ITERATIONS {
+        let (addr, new_addr) = (rng.addr(), rng.addr());
+        let threshold = clamp_threshold(rng.u16() as u32);
+        let mode = if i % 2 == 0 {
+            WriteMode::Single
+        } else {
+            WriteMode::Multiple
+        };
+
+        let device = Device {
+            addr,
+            threshold: rng.u16(),
+            ..Device::default()
+        };
+        let mut pzem = verified(device, Some(addr)).with_write_mode(mode);
+
+        pzem.set_threshold(threshold, NoTimeout).unwrap();
+        assert_eq!(pzem.get_threshold(NoTimeout).unwrap(), threshold);
+
+        match pzem.set_threshold(0, NoTimeout) {
+            Err(Error::InvalidThreshold(ThresholdError::Zero)) => {}
+            res => panic!("zero threshold accepted: {:?}", res),
+        }
+        match pzem.set_threshold(THRESHOLD_MAX + 1, NoTimeout) {
+            Err(Error::InvalidThreshold(ThresholdError::AboveMax)) => {}
+            res => panic!("threshold above maximum accepted: {:?}", res),
+        }
+
+        pzem.set_addr(new_addr, NoTimeout).unwrap();
+        assert_eq!(pzem.get_addr(NoTimeout).unwrap(), new_addr as u16);
+
+        let device = pzem.release();
+        assert_eq!((device.addr, device.threshold), (new_addr, threshold));
+    }
+}
+
+#[test]
+fn reset_round_trip() {
+    let mut rng = Rng(0x1b87_3593);
+    for _ in 0..ITERATIONS {
+        let addr = rng.addr();
+        let mut pzem = verified(
+            Device {
+                addr,
+                ..Device::default()
+            },
+            Some(addr),
+        );
+
+        pzem.reset_energy(NoTimeout).unwrap();
+        assert!(pzem.release().energy_reset);
+    }
+}
+
+#[test]
+fn corrupted_frames_rejected() {
+    let mut rng = Rng(0x85eb_ca6b);
+    for _ in 0..ITERATIONS {
+        let addr = rng.addr();
+        let mut regs = [0; 10];
+        regs.iter_mut().for_each(|reg| *reg = rng.u16());
+
+        let device = Device {
+            addr,
+            regs,
+            corrupt: Some(rng.u8() as usize),
+            ..Device::default()
+        };
+        let mut pzem = Pzem::new(device, Some(addr)).unwrap();
+
+        let mut m = RawMeasurement::default();
+        match pzem.read_raw(&mut m, NoTimeout) {
+            Err(Error::CrcMismatch) | Err(Error::PzemError) => {}
+            res => panic!("corrupted frame accepted: {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn corrupted_byte_count() {
+    // Index of the byte count, 0x14, and the bit flipped: into 0x16, the count of a clone
+    // answering 11 registers, and into the odd 0x15.
+    for corrupt in [25 * 7 + 2, 25 * 6 + 2] {
+        let device = Device {
+            addr: 0x01,
+            corrupt: Some(corrupt),
+            ..Device::default()
+        };
+        let mut pzem = Pzem::new(device, Some(0x01)).unwrap();
+
+        let mut m = RawMeasurement::default();
+        let res = pzem.read_raw(&mut m, NoTimeout);
+        if corrupt % 8 == 1 {
+            assert!(matches!(res, Err(Error::CrcMismatch)), "{:?}", res);
+        } else {
+            assert!(matches!(res, Err(Error::PzemError)), "{:?}", res);
+        }
+    }
+}
+
+#[test]
+fn holding_registers() {
+    let device = Device {
+        addr: 0x07,
+        threshold: 1500,
+        ..Device::default()
+    };
+    let mut pzem = verified(device, Some(0x07));
+
+    let regs: [u16; 2] = pzem.read_holding_registers(0x0001, NoTimeout).unwrap();
+    assert_eq!(regs, [1500, 0x07]);
+}
+
+#[test]
+fn sniffed_measurements() {
+    let mut rng = Rng(0xbb67_ae85);
+    let mut line = VecDeque::new();
+    let mut expected = Vec::new();
+    for _ in 0..ITERATIONS {
+        let addr = rng.addr();
+        let mut regs = [0; 10];
+        regs.iter_mut().for_each(|reg| *reg = rng.u16());
+
+        // Noise between the exchanges.
+        for _ in 0..rng.next() % 3 {
+            line.push_back(rng.u8());
+        }
+
+        let mut req = vec![addr, 0x04, 0, 0, 0, 10];
+        push_crc(&mut req);
+        line.extend(&req);
+
+        let mut device = Device {
+            addr,
+            regs,
+            req,
+            ..Device::default()
+        };
+        device.respond();
+        line.extend(device.rx);
+        expected.push((addr, RawMeasurement::from_registers(&regs)));
+    }
+
+    let mut sniffer = Sniffer::new(Device {
+        rx: line,
+        ..Device::default()
+    });
+    let mut sniffed = Vec::new();
+    loop {
+        match sniffer.poll() {
+            Ok(Sniffed::Measurement { addr, measurement }) => sniffed.push((addr, measurement)),
+            Ok(Sniffed::Frame { function, .. }) => assert_eq!(function, 0x04),
+            Err(nb::Error::WouldBlock) => break,
+            Err(nb::Error::Other(e)) => match e {},
+        }
+    }
+    assert_eq!(sniffed, expected);
+}
+
+#[test]
+fn sniffer_queue_overflow() {
+    let mut line = VecDeque::new();
+    for addr in 1..=3 {
+        let mut req = vec![addr, 0x04, 0, 0, 0, 10];
+        push_crc(&mut req);
+        line.extend(req);
+    }
+
+    let mut sniffer = Sniffer::<_, 2>::with_queue(Device {
+        rx: line,
+        ..Device::default()
+    });
+    sniffer.fill().unwrap();
+    assert_eq!((sniffer.queued(), sniffer.overflows()), (2, 1));
+
+    let addrs: Vec<_> = core::iter::from_fn(|| sniffer.pop())
+        .map(|frame| match frame {
+            Sniffed::Frame { addr, .. } => addr,
+            frame => panic!("unexpected {:?}", frame),
+        })
+        .collect();
+    assert_eq!(addrs, [1, 2]);
+}
+
+#[cfg(feature = "test-support")]
+#[test]
+fn injected_faults_rejected() {
+    use pzem004t::{FaultInjector, FaultRates};
+
+    let mut rng = Rng(0x6a09_e667);
+    let addr = rng.addr();
+    let mut regs = [0; 10];
+    regs.iter_mut().for_each(|reg| *reg = rng.u16());
+
+    let rates = FaultRates {
+        drop_one_in: Some(500),
+        corrupt_one_in: Some(300),
+        duplicate_one_in: Some(5),
+        delay_one_in: Some(10),
+        delay_polls: 50,
+        seed: rng.next(),
+    };
+    let device = Device {
+        addr,
+        regs,
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(FaultInjector::new(device, rates), Some(addr)).unwrap();
+
+    let mut ok = 0;
+    for _ in 0..ITERATIONS {
+        let mut m = RawMeasurement::default();
+        match pzem.read_raw(&mut m, Some((&mut PollTimer(0), 100))) {
+            Ok(()) => {
+                assert_eq!(m, RawMeasurement::from_registers(&regs));
+                ok += 1;
+            }
+            Err(Error::TimedOut { .. }) | Err(Error::CrcMismatch) | Err(Error::PzemError) => {}
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    let counts = *pzem.release().counts();
+    assert!(counts.dropped > 0 && counts.corrupted > 0 && counts.duplicated > 0);
+    assert!(ok > ITERATIONS / 2, "only {} reads succeeded", ok);
+}
+
+#[test]
+fn cancelled_read_drains_stragglers() {
+    use pzem004t::Cancel;
+
+    static CANCEL: Cancel = Cancel::new();
+
+    // Sensor stalling mid-response, the rest of which arrives once cancelled.
+    struct Stalled {
+        polls: u32,
+        rx: VecDeque<u8>,
+    }
+
+    impl serial::Read<u8> for Stalled {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.polls += 1;
+            if self.polls == 100 {
+                CANCEL.cancel();
+                self.rx.extend([0; 5]);
+            }
+            self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+        }
+    }
+
+    impl serial::Write<u8> for Stalled {
+        type Error = Infallible;
+
+        fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let device = Stalled {
+        polls: 0,
+        rx: VecDeque::new(),
+    };
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap().with_cancel(&CANCEL);
+
+    let mut m = RawMeasurement::default();
+    match pzem.read_raw(&mut m, NoTimeout) {
+        Err(Error::Cancelled) => {}
+        res => panic!("cancelled read completed: {:?}", res),
+    }
+    assert!(pzem.release().rx.is_empty());
+}
+
+#[cfg(not(feature = "no-float"))]
+#[test]
+fn read_pzem_macro() {
+    let device = Device {
+        addr: 0x01,
+        regs: [2301, 0, 0, 0, 0, 0, 0, 500, 0, 0],
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap();
+    let mut tim = PollTimer(0);
+
+    let m = pzem004t::read_pzem!(pzem, tim, 100u32).unwrap();
+    assert_eq!((m.voltage, m.frequency), (230.1, 50.0));
+
+    let mut device = pzem.release();
+    device.corrupt = Some(3);
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap();
+    let mut failed = false;
+    assert!(pzem004t::read_pzem!(pzem, tim, 100u32, |_| failed = true).is_none());
+    assert!(failed);
+}
+
+#[test]
+fn addr_hook_on_set_addr() {
+    use pzem004t::AddrChange;
+    use std::sync::Mutex;
+
+    static CHANGES: Mutex<Vec<AddrChange>> = Mutex::new(Vec::new());
+
+    let device = Device {
+        addr: 0x01,
+        ..Device::default()
+    };
+    let mut pzem =
+        verified(device, Some(0x01)).with_addr_hook(|change| CHANGES.lock().unwrap().push(change));
+
+    pzem.set_addr(0x02, NoTimeout).unwrap();
+    pzem.set_addr(0x03, NoTimeout).unwrap();
+    assert_eq!(
+        *CHANGES.lock().unwrap(),
+        [
+            AddrChange {
+                old: 0x01,
+                new: 0x02
+            },
+            AddrChange {
+                old: 0x02,
+                new: 0x03
+            }
+        ]
+    );
+}
+
+#[test]
+fn set_addr_on_noisy_line() {
+    // Device behind a line picking up the given number of noise bytes.
+    struct Noisy(Device, Rc<Cell<u32>>);
+
+    impl serial::Read<u8> for Noisy {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            match self.1.get().checked_sub(1) {
+                Some(left) => {
+                    self.1.set(left);
+                    Ok(0x55)
+                }
+                None => self.0.read(),
+            }
+        }
+    }
+
+    impl serial::Write<u8> for Noisy {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.0.write(word)
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let device = Device {
+        addr: 0x01,
+        ..Device::default()
+    };
+    let noise = Rc::new(Cell::new(0));
+    let mut pzem = Pzem::new(Noisy(device, noise.clone()), Some(0x01))
+        .unwrap()
+        .with_noise_limit(Some(2))
+        .probe(NoTimeout)
+        .map_err(|(_, e)| e)
+        .unwrap();
+
+    // Never sent: not reported as a failed change.
+    noise.set(5);
+    match pzem.set_addr(0x02, NoTimeout) {
+        Err(Error::LineNoise) => {}
+        res => panic!("noise not reported: {:?}", res),
+    }
+    assert_eq!(pzem.release().0.addr, 0x01);
+}
+
+#[cfg(any(feature = "test-support", not(feature = "no-float")))]
+#[test]
+fn release_idle_discards_late_response() {
+    // Sensor answering after a number of polls.
+    struct Late {
+        device: Device,
+        delay: u32,
+    }
+
+    impl serial::Read<u8> for Late {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            if self.device.rx.is_empty() {
+                return Err(nb::Error::WouldBlock);
+            }
+            match self.delay.checked_sub(1) {
+                Some(left) => {
+                    self.delay = left;
+                    Err(nb::Error::WouldBlock)
+                }
+                None => self.device.read(),
+            }
+        }
+    }
+
+    impl serial::Write<u8> for Late {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.device.write(word)
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let late = Late {
+        device: Device {
+            addr: 0x01,
+            ..Device::default()
+        },
+        delay: 50,
+    };
+    let mut pzem = Pzem::new(late, Some(0x01)).unwrap();
+
+    let mut m = RawMeasurement::default();
+    match pzem.read_raw(&mut m, Some((&mut PollTimer(0), 10))) {
+        Err(Error::TimedOut { received: 0 }) => {}
+        res => panic!("late response received: {:?}", res),
+    }
+    assert!(pzem.is_pending());
+
+    let state = pzem.debug_state();
+    assert_eq!(state.last_error, Some(pzem004t::Event::TimedOut));
+    assert_eq!((state.last_rx, state.pending), (0, true));
+
+    let late = pzem
+        .release_idle(Some((&mut PollTimer(0), 100)))
+        .map_err(|(_, e)| e)
+        .unwrap();
+    assert!(late.device.rx.is_empty());
+}
+
+#[test]
+fn release_idle_on_streaming_bus() {
+    let mut pzem = Pzem::new(Babbler { silent: 100 }, Some(0x01)).unwrap();
+    let mut m = RawMeasurement::default();
+    match pzem.read_raw(&mut m, Some((&mut PollTimer(0), 10))) {
+        Err(Error::TimedOut { received: 0 }) => {}
+        res => panic!("response received: {:?}", res),
+    }
+
+    match pzem.release_idle(Some((&mut PollTimer(0), 100))) {
+        Err((pzem, Error::BusBusy)) => assert!(pzem.is_pending()),
+        Err((_, e)) => panic!("streaming bus released: {:?}", e),
+        Ok(_) => panic!("streaming bus released"),
+    }
+}
+
+#[test]
+fn drain_without_timeout_on_streaming_bus() {
+    let mut pzem = Pzem::new(Babbler { silent: 0 }, Some(0x01)).unwrap();
+    let mut m = RawMeasurement::default();
+    match pzem.read_raw(&mut m, NoTimeout) {
+        Err(Error::BusBusy) => {}
+        res => panic!("streaming bus drained: {:?}", res),
+    }
+}
+
+#[cfg(not(feature = "no-float"))]
+#[test]
+fn interleaved_scheduler() {
+    use pzem004t::{InterleavedScheduler, Poller};
+
+    let a = Device {
+        addr: 0x01,
+        regs: [2301, 0, 0, 0, 0, 0, 0, 500, 0, 0],
+        ..Device::default()
+    };
+    let b = Device {
+        addr: 0x02,
+        regs: [2190, 0, 0, 0, 0, 0, 0, 499, 0, 0],
+        ..Device::default()
+    };
+    let a = Poller::<_, 2>::new(a, &[0x01]).unwrap();
+    let b = Poller::<_, 2>::new(b, &[0x02]).unwrap();
+    let mut scheduler = InterleavedScheduler::new(a, b);
+    let mut tim = PollTimer(0);
+
+    let (ra, rb) = scheduler.poll(Some((&mut tim, 100u32)));
+    assert_eq!((ra.len(), rb.len()), (1, 1));
+    assert_eq!(ra[0].1.as_ref().unwrap().voltage, 230.1);
+    assert_eq!(rb[0].1.as_ref().unwrap().frequency, 49.9);
+
+    let (a, b) = scheduler.release();
+    assert!(a.release().rx.is_empty() && b.release().rx.is_empty());
+}
+
+#[test]
+fn journal_records_frames() {
+    use pzem004t::{Direction, Frame, Journal, VcdWriter};
+    use std::sync::Mutex;
+
+    static FRAMES: Mutex<Vec<(Direction, Vec<u8>)>> = Mutex::new(Vec::new());
+    struct Capture(VcdWriter<String>);
+    impl Journal for Capture {
+        fn record(&mut self, frame: Frame<'_>) {
+            FRAMES
+                .lock()
+                .unwrap()
+                .push((frame.direction, frame.bytes.to_vec()));
+            self.0.record(frame);
+        }
+    }
+
+    let capture = Box::leak(Box::new(Capture(VcdWriter::new(String::new(), 1000, 9600))));
+    let device = Device {
+        addr: 0x01,
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(device, Some(0x01))
+        .unwrap()
+        .with_journal(capture, || 0);
+
+    let mut m = RawMeasurement::default();
+    let mut frame = [0; READ_FRAME_LEN];
+    pzem.read_raw_with_frame(&mut m, &mut frame, NoTimeout)
+        .unwrap();
+
+    let frames = FRAMES.lock().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        frames[0],
+        (Direction::Tx, vec![1, 4, 0, 0, 0, 10, 0x70, 0x0d])
+    );
+    assert_eq!(frames[1], (Direction::Rx, frame.to_vec()));
+
+    // Start bit, the least significant bit set, stop bit: 104 µs per bit.
+    let mut vcd = VcdWriter::new(String::new(), 1000, 9600);
+    vcd.record(Frame {
+        tick: 0,
+        direction: Direction::Rx,
+        bytes: &[0x01],
+    });
+    let vcd = vcd.into_inner();
+    assert!(
+        vcd.ends_with("#0\n1t\n1r\n0r\n#104\n1r\n#208\n0r\n#937\n1r\n"),
+        "{}",
+        vcd
+    );
+}
+
+#[cfg(feature = "test-support")]
+#[test]
+fn replayed_capture() {
+    use pzem004t::{CaptureWriter, Replay, ReplayError};
+    use std::sync::Mutex;
+
+    static CAPTURE: Mutex<String> = Mutex::new(String::new());
+    struct Shared;
+    impl core::fmt::Write for Shared {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            CAPTURE.lock().unwrap().push_str(s);
+            Ok(())
+        }
+    }
+
+    let regs = [2301, 0, 0, 0, 0, 0, 0, 500, 0, 0];
+    let device = Device {
+        addr: 0x01,
+        regs,
+        threshold: 0x1234,
+        ..Device::default()
+    };
+    let journal = Box::leak(Box::new(CaptureWriter::new(Shared)));
+    let mut pzem = Pzem::new(device, Some(0x01))
+        .unwrap()
+        .with_journal(journal, || 7);
+    let mut m = RawMeasurement::default();
+    pzem.read_raw(&mut m, NoTimeout).unwrap();
+    let capture = CAPTURE.lock().unwrap().clone();
+    assert_eq!(capture.lines().next(), Some("7 tx 01 04 00 00 00 0a 70 0d"));
+
+    let replay = Replay::new(&capture);
+    let mut pzem = Pzem::new(&replay, Some(0x01)).unwrap();
+    let mut replayed = RawMeasurement::default();
+    pzem.read_raw(&mut replayed, Some((&mut replay.timer(), 0)))
+        .unwrap();
+    assert_eq!(replayed, m);
+    assert!(replay.is_finished());
+    assert_eq!(replay.tick(), 7);
+
+    // The driver diverging from the capture.
+    let replay = Replay::new(&capture);
+    let mut pzem = Pzem::new(&replay, Some(0x02)).unwrap();
+    assert!(matches!(
+        pzem.read_raw(&mut replayed, Some((&mut replay.timer(), 0))),
+        Err(Error::WriteError(ReplayError::Mismatch {
+            line: 1,
+            expected: 0x01,
+            written: 0x02
+        }))
+    ));
+
+    // Annotated capture of a truncated response.
+    let replay = Replay::new(
+        "# sensor powered off mid-response\n\
+         0 tx 01 04 00 00 00 0a 70 0d\n\
+         12 rx 01 04 14 08\n",
+    );
+    let mut pzem = Pzem::new(&replay, Some(0x01)).unwrap();
+    assert!(matches!(
+        pzem.read_raw(&mut replayed, Some((&mut replay.timer(), 0))),
+        Err(Error::TimedOut { .. })
+    ));
+    assert!(replay.is_finished());
+    assert_eq!((replay.line(), replay.tick()), (3, 12));
+
+    let replay = Replay::new("0 tx 01 04 xx\n");
+    let mut pzem = Pzem::new(&replay, Some(0x01)).unwrap();
+    assert!(matches!(
+        pzem.read_raw(&mut replayed, Some((&mut replay.timer(), 0))),
+        Err(Error::WriteError(ReplayError::Malformed { line: 1 }))
+    ));
+}
+
+#[test]
+fn adaptive_timeout() {
+    use pzem004t::{AdaptiveTimeout, Operation};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Advancing by a millisecond every call.
+    static MILLIS: AtomicU32 = AtomicU32::new(0);
+    fn millis() -> u32 {
+        MILLIS.fetch_add(1, Ordering::Relaxed)
+    }
+
+    let device = Device {
+        addr: 0x01,
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap();
+    let mut timeout = AdaptiveTimeout::new(millis, 10, 1000);
+    assert_eq!(timeout.current(Operation::Read), 1000);
+
+    let mut m = RawMeasurement::default();
+    for _ in 0..20 {
+        pzem.read_raw(&mut m, &mut timeout).unwrap();
+    }
+    // A poll per byte of the response.
+    let settled = timeout.current(Operation::Read);
+    assert!((25..100).contains(&settled), "{}", settled);
+    assert_eq!(timeout.current(Operation::ReadParam), 1000);
+
+    // The sensor going silent.
+    struct Silent;
+    impl serial::Read<u8> for Silent {
+        type Error = Infallible;
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+    impl serial::Write<u8> for Silent {
+        type Error = Infallible;
+        fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let mut pzem = Pzem::new(Silent, Some(0x01)).unwrap();
+    assert!(matches!(
+        pzem.read_raw(&mut m, &mut timeout),
+        Err(Error::TimedOut { received: 0 })
+    ));
+    assert_eq!(timeout.current(Operation::Read), 2 * settled);
+    for _ in 0..10 {
+        let _ = pzem.read_raw(&mut m, &mut timeout);
+    }
+    assert_eq!(timeout.current(Operation::Read), 1000);
+
+    timeout.reset();
+    assert_eq!(timeout.current(Operation::Read), 1000);
+}
+
+#[test]
+fn skipped_drain_resyncs() {
+    let regs = [2301, 0, 0, 0, 0, 0, 0, 500, 0, 0];
+    let device = Device {
+        addr: 0x01,
+        regs,
+        rx: vec![0x55, 0x01].into(),
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap().with_skip_drain(true);
+
+    let outcome = pzem.read_raw_outcome(NoTimeout).unwrap();
+    assert_eq!(outcome.measurement, RawMeasurement::from_registers(&regs));
+    assert_eq!((outcome.retries_used, outcome.resyncs), (0, 2));
+    assert_eq!(outcome.latency_ticks, None);
+    #[cfg(not(feature = "size-opt"))]
+    assert_eq!(pzem.stats().drained_bytes, 2);
+}
+
+#[test]
+fn wait_ready_skips_warm_up() {
+    use pzem004t::READY_INTERVAL;
+
+    // Delay adding up the time waited, in ms.
+    struct Delay(u32);
+
+    impl embedded_hal::blocking::delay::DelayMs<u16> for Delay {
+        fn delay_ms(&mut self, ms: u16) {
+            self.0 += ms as u32;
+        }
+    }
+
+    // Sensor reporting the voltages in turn, one per request.
+    struct WarmUp(Device, VecDeque<u16>);
+
+    impl serial::Read<u8> for WarmUp {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.0.read()
+        }
+    }
+
+    impl serial::Write<u8> for WarmUp {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            if self.0.req.is_empty() {
+                self.0.regs[0] = self.1.pop_front().unwrap_or(0);
+            }
+            self.0.write(word)
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    let device = Device {
+        addr: 0x01,
+        ..Device::default()
+    };
+    let voltages = vec![0, 3000, 2301, 2100, 2105].into();
+    let mut pzem = Pzem::new(WarmUp(device, voltages), Some(0x01)).unwrap();
+    let mut delay = Delay(0);
+    assert_eq!(
+        pzem.wait_ready(&mut delay, NoTimeout).unwrap().voltage,
+        2105
+    );
+    assert_eq!(delay.0, 4 * READY_INTERVAL as u32);
+
+    // Never settling.
+    let mut delay = Delay(0);
+    match pzem.wait_ready(&mut delay, NoTimeout) {
+        Err(Error::Implausible) => {}
+        res => panic!("garbage accepted: {:?}", res),
+    }
+    assert_eq!(delay.0, 1900);
+}
+
+#[test]
+fn register_table_round_trip() {
+    let mut rng = Rng(0x1b87_3593);
+    for _ in 0..ITERATIONS {
+        let m = RawMeasurement {
+            voltage: rng.u16(),
+            current: rng.next(),
+            power: rng.next(),
+            energy: rng.next(),
+            frequency: rng.u16(),
+            pf: rng.u16(),
+            alarm: rng.u8() & 1 != 0,
+        };
+
+        let regs = m.to_registers();
+        assert_eq!(RawMeasurement::from_registers(&regs), m);
+    }
+}
+
+#[test]
+fn register_table_with_parameters() {
+    use pzem004t::regs::{self, RegisterInfo};
+
+    // The parameters are skipped, wherever they lie.
+    let table = [
+        regs::VOLTAGE,
+        RegisterInfo {
+            addr: 0x0100,
+            ..regs::THRESHOLD
+        },
+        regs::ADDRESS,
+    ];
+    let m = regs::decode(&table, &[2301]);
+    assert_eq!(m.voltage, 2301);
+
+    let mut block = [0; 1];
+    regs::encode(&table, &m, &mut block);
+    assert_eq!(block, [2301]);
+}
+
+#[test]
+fn clone_extra_registers() {
+    let regs = [2301, 0x2839, 0, 0x1234, 0, 0x0567, 0, 500, 95, 0];
+    let device = Device {
+        addr: 0x01,
+        regs,
+        extra: vec![0xdead, 0xbeef],
+        ..Device::default()
+    };
+    let mut pzem = Pzem::new(device, Some(0x01)).unwrap();
+
+    let mut m = RawMeasurement::default();
+    let mut frame = [0u8; READ_FRAME_MAX];
+    pzem.read_raw_with_frame(&mut m, &mut frame, NoTimeout)
+        .unwrap();
+    assert_eq!(m, RawMeasurement::from_registers(&regs));
+    assert_eq!(frame[2], 24);
+    assert_eq!(decode::decode_frame(&frame), Some(m));
+    assert_eq!(decode::decode_response(&frame), Ok(m));
+
+    // Not fitting into the frame of a genuine sensor.
+    let mut frame = [0u8; READ_FRAME_LEN];
+    match pzem.read_raw_with_frame(&mut m, &mut frame, NoTimeout) {
+        Err(Error::PzemError) => {}
+        res => panic!("long response accepted: {:?}", res),
+    }
+}
+
+#[test]
+fn execute_commands() {
+    let regs = [2301, 0x2839, 0, 0x1234, 0, 0x0567, 0, 500, 95, 0];
+    let device = Device {
+        addr: 0x01,
+        threshold: 2300,
+        regs,
+        ..Device::default()
+    };
+    let mut pzem = verified(device, Some(0x01));
+    let mut resp = ResponseBuf::new();
+
+    let read = Command::ReadInput {
+        start: 0,
+        count: 10,
+    };
+    pzem.execute(read, &mut resp, NoTimeout).unwrap();
+    assert!(resp.registers().eq(regs.iter().copied()));
+    assert_eq!(resp.frame().len(), READ_FRAME_LEN);
+
+    let read = Command::ReadHolding { start: 1, count: 1 };
+    pzem.execute(read, &mut resp, NoTimeout).unwrap();
+    assert_eq!(resp.data(), 2300u16.to_be_bytes());
+
+    let write = Command::WriteHolding {
+        reg: 1,
+        value: 1000,
+    };
+    pzem.execute(write, &mut resp, NoTimeout).unwrap();
+    assert_eq!(resp.data(), [0, 1, 0x03, 0xe8]);
+    assert_eq!(pzem.get_threshold(NoTimeout).unwrap(), 1000);
+
+    pzem.execute(Command::ResetEnergy, &mut resp, NoTimeout)
+        .unwrap();
+    assert_eq!((resp.frame().len(), resp.data()), (4, &[][..]));
+
+    // Refused without a transaction.
+    let refused = [
+        Command::ReadInput { start: 0, count: 0 },
+        Command::ReadHolding {
+            start: 0,
+            count: 17,
+        },
+        Command::ReadHolding {
+            start: 0xffff,
+            count: 2,
+        },
+        Command::WriteHolding { reg: 2, value: 5 },
+    ];
+    for command in refused {
+        match pzem.execute(command, &mut resp, NoTimeout) {
+            Err(Error::InvalidCommand) => assert!(resp.frame().is_empty()),
+            res => panic!("{:?} not refused: {:?}", command, res),
+        }
+    }
+    let write = Command::WriteHolding { reg: 1, value: 0 };
+    match pzem.execute(write, &mut resp, NoTimeout) {
+        Err(Error::InvalidThreshold(ThresholdError::Zero)) => {}
+        res => panic!("zero threshold written: {:?}", res),
+    }
+}
+
+#[test]
+fn self_test_loopback() {
+    // Sensor behind a loopback switch, which echoes the bytes written or loses them if broken.
+    struct Looped {
+        device: Device,
+        on: Rc<Cell<bool>>,
+        echo: bool,
+    }
+
+    impl serial::Read<u8> for Looped {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.device.read()
+        }
+    }
+
+    impl serial::Write<u8> for Looped {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            match (self.on.get(), self.echo) {
+                (false, _) => self.device.write(word)?,
+                (true, true) => self.device.rx.push_back(word),
+                (true, false) => {}
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    for echo in [true, false] {
+        let on = Rc::new(Cell::new(false));
+        let looped = Looped {
+            device: Device {
+                addr: 0x01,
+                ..Device::default()
+            },
+            on: on.clone(),
+            echo,
+        };
+        let mut pzem = Pzem::new(looped, Some(0x01)).unwrap();
+
+        let mut hint = |state| on.set(state);
+        let report = pzem
+            .self_test(Some(&mut hint), Some((&mut PollTimer(0), 100)))
+            .unwrap();
+        assert_eq!(report.loopback, Some(echo));
+        assert_eq!(report.diagnosis, Diagnosis::Healthy);
+        assert_eq!(report.echoed_addr, Some(0x01));
+        assert_eq!(report.passed(), echo);
+    }
+}
@@ -0,0 +1,42 @@
+//! Serialization of the measurements to JSON.
+
+#![cfg(all(feature = "json", feature = "alloc", not(feature = "no-float")))]
+
+use pzem004t::Measurement;
+
+#[test]
+fn large_values() {
+    let m = Measurement {
+        power: 1e30,
+        energy: 1e30,
+        ..Measurement::default()
+    };
+
+    let s = m.to_json_string();
+    assert!(s.len() > 128);
+    assert!(s.contains("\"power\":1000000015047466219876688855040.0,"));
+    assert!(s.contains("\"energy\":1000000015047466219876688855040.000,"));
+
+    let mut small = heapless::String::<128>::new();
+    assert!(m.to_json(&mut small).is_err());
+}
+
+#[test]
+fn non_finite_values() {
+    let m = Measurement {
+        voltage: 230.1,
+        power: f32::INFINITY,
+        energy: f32::NEG_INFINITY,
+        pf: f32::NAN,
+        ..Measurement::default()
+    };
+
+    let mut s = heapless::String::<128>::new();
+    m.to_json(&mut s).unwrap();
+    assert_eq!(
+        s,
+        "{\"voltage\":230.1,\"current\":0.000,\"power\":null,\"energy\":null,\
+         \"frequency\":0.0,\"pf\":null,\"alarm\":false}"
+    );
+    assert_eq!(m.to_json_string(), s.as_str());
+}
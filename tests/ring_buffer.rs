@@ -0,0 +1,50 @@
+//! Single-threaded use of the lock-free ring buffer.
+
+use pzem004t::RingBuffer;
+
+#[test]
+fn full() {
+    let mut rb = RingBuffer::<4>::new();
+    let (mut producer, mut consumer) = rb.split();
+
+    assert_eq!(producer.push_slice(&[1, 2, 3, 4, 5]), 4);
+    assert!(producer.is_full());
+    assert!(!producer.push(6));
+    assert_eq!(consumer.len(), 4);
+
+    assert_eq!(consumer.pop(), Some(1));
+    assert!(!producer.is_full());
+    assert!(producer.push(6));
+    assert!(producer.is_full());
+
+    let drained: Vec<u8> = core::iter::from_fn(|| consumer.pop()).collect();
+    assert_eq!(drained, [2, 3, 4, 6]);
+    assert!(consumer.is_empty());
+    assert_eq!(consumer.pop(), None);
+}
+
+#[test]
+fn wrap_around() {
+    // Not a power of two, so that the slots would skip at an overflow of the indices.
+    let mut rb = RingBuffer::<3>::new();
+    let (mut producer, mut consumer) = rb.split();
+
+    let mut next = 0u8;
+    let mut expected = 0u8;
+    for round in 0..1000 {
+        // Alternating between a full buffer and a partly filled one.
+        let n = 1 + round % 3;
+        for _ in 0..n {
+            assert!(producer.push(next));
+            next = next.wrapping_add(1);
+        }
+        assert_eq!(producer.is_full(), n == 3);
+        assert_eq!(consumer.len(), n);
+
+        for _ in 0..n {
+            assert_eq!(consumer.pop(), Some(expected));
+            expected = expected.wrapping_add(1);
+        }
+        assert!(consumer.is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+//! Distribution of the measurements to the sinks.
+
+#![cfg(not(feature = "no-float"))]
+
+use pzem004t::{Broadcast, Measurement, MeasurementSink};
+
+#[test]
+fn broadcast_unsubscribe() {
+    let (mut a, mut b, mut c) = (0, 0, 0);
+    let mut fa = |_: &Measurement| a += 1;
+    let mut fb = |_: &Measurement| b += 1;
+    let mut fc = |_: &Measurement| c += 1;
+
+    {
+        let mut large = Broadcast::<4>::new();
+        let sa = large.subscribe(&mut fa).ok().unwrap();
+        let _ = large.subscribe(&mut fb).ok().unwrap();
+        let sc = large.subscribe(&mut fc).ok().unwrap();
+        large.push(&Measurement::default());
+
+        // Subscriptions of another broadcast: an empty slot, and one out of range.
+        let mut small = Broadcast::<2>::new();
+        assert!(small.unsubscribe(sa).is_none());
+        assert!(small.unsubscribe(sc).is_none());
+
+        assert_eq!(large.len(), 3);
+        large.push(&Measurement::default());
+    }
+    assert_eq!((a, b, c), (2, 2, 2));
+}
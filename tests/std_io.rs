@@ -0,0 +1,87 @@
+//! Runs the driver over in-memory `std::io` pipes.
+
+#![cfg(feature = "std")]
+
+use std::io::{self, Cursor, Read, Write};
+
+use pzem004t::{modbus_crc, Error, NoTimeout, Pzem, RawMeasurement, StdIo};
+
+// Receives from the cursor, collecting what is sent.
+struct Pipe {
+    rx: Cursor<Vec<u8>>,
+    tx: Vec<u8>,
+}
+
+impl Pipe {
+    fn new(rx: &[u8]) -> Self {
+        Self {
+            rx: Cursor::new(rx.to_vec()),
+            tx: Vec::new(),
+        }
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.rx.read(buf)
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn response(regs: &[u16; 10]) -> Vec<u8> {
+    let mut frame = vec![0x01, 0x04, 20];
+    for reg in regs {
+        frame.extend_from_slice(&reg.to_be_bytes());
+    }
+    let crc = modbus_crc(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+#[test]
+fn cursor_pipe() {
+    let regs = [2301, 1234, 0, 2839, 0, 12345, 0, 500, 95, 0];
+    let pipe = Pipe::new(&response(&regs));
+    let mut pzem = Pzem::new(StdIo::new(pipe).with_idle_eof(true), Some(0x01))
+        .unwrap()
+        .with_skip_drain(true);
+
+    let mut m = RawMeasurement::default();
+    pzem.read_raw(&mut m, NoTimeout).unwrap();
+    assert_eq!(m, RawMeasurement::from_registers(&regs));
+
+    let pipe = pzem.release().into_inner();
+    assert_eq!(pipe.tx, [0x01, 0x04, 0x00, 0x00, 0x00, 0x0a, 0x70, 0x0d]);
+}
+
+#[test]
+fn end_of_stream() {
+    // The peer gone: waiting indefinitely fails instead of spinning.
+    let mut pzem = Pzem::new(StdIo::new(Pipe::new(&[])), Some(0x01)).unwrap();
+
+    let mut m = RawMeasurement::default();
+    match pzem.read_raw(&mut m, NoTimeout) {
+        Err(Error::ReadError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        res => panic!("read past the end of the stream: {:?}", res),
+    }
+
+    // Truncated response.
+    let regs = [0; 10];
+    let pipe = Pipe::new(&response(&regs)[..12]);
+    let mut pzem = Pzem::new(StdIo::new(pipe), Some(0x01))
+        .unwrap()
+        .with_skip_drain(true);
+    match pzem.read_raw(&mut m, NoTimeout) {
+        Err(Error::ReadError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        res => panic!("truncated response accepted: {:?}", res),
+    }
+}
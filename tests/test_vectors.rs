@@ -0,0 +1,155 @@
+//! Checks the driver against the canonical frames of the `test_vectors` module.
+
+#![cfg(feature = "test-support")]
+
+use core::convert::Infallible;
+use std::collections::VecDeque;
+
+use embedded_hal::serial;
+use pzem004t::test_vectors::{self, get_addr, get_threshold, read, reset, set_threshold};
+use pzem004t::{decode, modbus_crc, Error, Exception, NoTimeout, Pzem, RawMeasurement, Verified};
+
+type Exchange = (&'static [u8], &'static [u8]);
+
+// Sensor expecting the scripted requests in order, answering each with its canned response.
+struct Scripted {
+    script: VecDeque<Exchange>,
+    req: Vec<u8>,
+    rx: VecDeque<u8>,
+}
+
+impl Scripted {
+    fn new(script: &[Exchange]) -> Self {
+        Self {
+            script: script.iter().copied().collect(),
+            req: Vec::new(),
+            rx: VecDeque::new(),
+        }
+    }
+}
+
+impl serial::Read<u8> for Scripted {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for Scripted {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.req.push(word);
+        let (request, response) = *self.script.front().expect("unexpected request");
+        if self.req.len() == request.len() {
+            assert_eq!(self.req, request);
+            self.req.clear();
+            self.rx.extend(response);
+            self.script.pop_front();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn unverified(script: &[Exchange]) -> Pzem<Scripted> {
+    Pzem::new(Scripted::new(script), Some(test_vectors::ADDR)).unwrap()
+}
+
+fn verified(script: &[Exchange]) -> Pzem<Scripted, Verified> {
+    let mut full = vec![(&get_addr::REQUEST[..], &get_addr::RESPONSE[..])];
+    full.extend_from_slice(script);
+
+    unverified(&full)
+        .probe(NoTimeout)
+        .map_err(|(_, e)| e)
+        .unwrap()
+}
+
+#[test]
+fn read() {
+    let mut pzem = unverified(&[(&read::REQUEST, &read::RESPONSE)]);
+
+    let mut m = RawMeasurement::default();
+    pzem.read_raw(&mut m, NoTimeout).unwrap();
+    assert_eq!(m, read::MEASUREMENT);
+    assert_eq!(
+        decode::decode_frame(&read::RESPONSE),
+        Some(read::MEASUREMENT)
+    );
+    assert_eq!(
+        decode::decode_response(&read::RESPONSE),
+        Ok(read::MEASUREMENT)
+    );
+}
+
+#[test]
+fn get_threshold() {
+    let mut pzem = unverified(&[(&get_threshold::REQUEST, &get_threshold::RESPONSE)]);
+
+    assert_eq!(
+        pzem.get_threshold(NoTimeout).unwrap(),
+        get_threshold::THRESHOLD
+    );
+}
+
+#[test]
+fn set_threshold() {
+    let mut pzem = verified(&[(&set_threshold::REQUEST, &set_threshold::RESPONSE)]);
+
+    pzem.set_threshold(set_threshold::THRESHOLD, NoTimeout)
+        .unwrap();
+    assert!(pzem.release().script.is_empty());
+}
+
+#[test]
+fn reset() {
+    let mut pzem = verified(&[
+        (&reset::REQUEST, &reset::RESPONSE),
+        (&reset::REQUEST, &reset::EXCEPTION),
+    ]);
+
+    pzem.reset_energy(NoTimeout).unwrap();
+    match pzem.reset_energy(NoTimeout) {
+        Err(Error::Exception(Exception::SlaveError)) => {}
+        res => panic!("exception not reported: {:?}", res),
+    }
+}
+
+#[test]
+fn golden_crcs() {
+    let frames: [&[u8]; 9] = [
+        &read::REQUEST,
+        &read::RESPONSE,
+        &get_threshold::REQUEST,
+        &get_threshold::RESPONSE,
+        &get_addr::REQUEST,
+        &get_addr::RESPONSE,
+        &set_threshold::REQUEST,
+        &test_vectors::write_addr::REQUEST,
+        &reset::EXCEPTION,
+    ];
+    for frame in frames {
+        let (body, crc) = frame.split_at(frame.len() - 2);
+        assert_eq!(modbus_crc(body).to_le_bytes(), crc, "{:02x?}", frame);
+    }
+}
+
+#[test]
+fn frame_sizes() {
+    use pzem004t::{
+        PARAM_RESPONSE_LEN, READ_FRAME_LEN, READ_FRAME_MAX, READ_REG_MAX, READ_RESPONSE_LEN,
+        REG_COUNT, REQUEST_LEN,
+    };
+
+    assert_eq!(REQUEST_LEN, 8);
+    assert_eq!(READ_RESPONSE_LEN, 3 + 2 * REG_COUNT as usize + 2);
+    assert_eq!(READ_FRAME_LEN, READ_RESPONSE_LEN);
+    assert_eq!(READ_FRAME_MAX, 3 + 2 * READ_REG_MAX + 2);
+    assert_eq!(PARAM_RESPONSE_LEN, 7);
+    assert_eq!(read::RESPONSE[2] as usize, 2 * REG_COUNT as usize);
+}
@@ -0,0 +1,100 @@
+//! Static assertions of the types which may cross the task boundaries: a change making
+//! any of them `Send` or `Sync` (or not) fails to compile.
+
+#![allow(dead_code)]
+
+use core::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_hal::serial;
+use pzem004t::{Cancel, EventLog, Pzem, RingBuffer, Sniffer, Verified};
+
+// Serial peripheral owned by a single task.
+struct Uart;
+
+impl serial::Read<u8> for Uart {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for Uart {
+    type Error = Infallible;
+
+    fn write(&mut self, _: u8) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// Serial peripheral bound to the thread which created it.
+type LocalUart = Rc<Uart>;
+
+fn assert_send<T: Send>() {}
+
+fn assert_sync<T: Sync>() {}
+
+// The call of `item` is ambiguous, failing to compile, if `T` implements the trait, as
+// both impls apply.
+macro_rules! assert_not_impl {
+    ($t:ty: $tr:path) => {{
+        trait Ambiguous<A> {
+            fn item() {}
+        }
+        impl<T: ?Sized> Ambiguous<()> for T {}
+        impl<T: ?Sized + $tr> Ambiguous<u8> for T {}
+        let _ = <$t as Ambiguous<_>>::item;
+    }};
+}
+
+#[test]
+fn driver() {
+    assert_send::<Pzem<Uart>>();
+    assert_send::<Pzem<Uart, Verified>>();
+    assert_not_impl!(Pzem<Uart>: Sync);
+    assert_not_impl!(Pzem<LocalUart>: Send);
+}
+
+#[test]
+fn shared_state() {
+    assert_sync::<Cancel>();
+    assert_send::<EventLog<16>>();
+    assert_sync::<EventLog<16>>();
+    assert_sync::<RingBuffer<64>>();
+    assert_send::<pzem004t::Producer<'static, 64>>();
+    assert_send::<pzem004t::Consumer<'static, 64>>();
+    assert_send::<Sniffer<Uart>>();
+}
+
+#[cfg(not(feature = "no-float"))]
+#[test]
+fn bus() {
+    use pzem004t::{InterleavedScheduler, NoTimeout, Poller, ScanIter};
+
+    assert_send::<Poller<Uart, 4>>();
+    assert_not_impl!(Poller<Uart, 4>: Sync);
+    assert_send::<InterleavedScheduler<Uart, Uart, 4>>();
+    assert_send::<ScanIter<'static, Uart, Verified, NoTimeout>>();
+
+    #[cfg(feature = "alloc")]
+    assert_send::<pzem004t::DynPoller<Uart>>();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn shared_bus() {
+    use pzem004t::SharedBus;
+    use std::sync::Mutex;
+
+    assert_sync::<SharedBus<Mutex<Pzem<Uart>>>>();
+    assert_not_impl!(SharedBus<Mutex<Pzem<LocalUart>>>: Sync);
+    assert_send::<pzem004t::PzemEmulator<std::net::TcpStream>>();
+
+    #[cfg(feature = "critical-section")]
+    assert_sync::<SharedBus<critical_section::Mutex<core::cell::RefCell<Pzem<Uart>>>>>();
+}
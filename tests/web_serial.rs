@@ -0,0 +1,160 @@
+//! Checks the Web Serial driver against the canonical frames of the `test_vectors` module.
+
+#![cfg(all(feature = "async", feature = "test-support"))]
+
+use core::convert::Infallible;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use pzem004t::test_vectors::{self, get_addr, read, reset};
+use pzem004t::{modbus_crc, AsyncPort, Error, Exception, WebSerial};
+
+// Port answering the expected request with the canned response, a few bytes per read.
+struct Port {
+    request: &'static [u8],
+    response: &'static [u8],
+    rx: VecDeque<u8>,
+}
+
+impl AsyncPort for Port {
+    type Error = Infallible;
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+        assert_eq!(bytes, self.request);
+        self.rx.extend(self.response);
+        Ok(())
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+        let n = buf.len().min(self.rx.len()).min(3);
+        for b in buf[..n].iter_mut() {
+            *b = self.rx.pop_front().unwrap();
+        }
+        Ok(n)
+    }
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+// The port never suspends, so a single poll completes the future.
+fn block_on<F: Future>(f: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    match pin!(f).poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(out) => out,
+        Poll::Pending => panic!("port suspended"),
+    }
+}
+
+fn web_serial(request: &'static [u8], response: &'static [u8]) -> WebSerial<Port> {
+    let port = Port {
+        request,
+        response,
+        rx: VecDeque::new(),
+    };
+    WebSerial::new(port, Some(test_vectors::ADDR)).unwrap()
+}
+
+#[test]
+fn read() {
+    let mut pzem = web_serial(&read::REQUEST, &read::RESPONSE);
+    assert_eq!(block_on(pzem.read_raw()).unwrap(), read::MEASUREMENT);
+}
+
+#[test]
+fn get_addr() {
+    let mut pzem = web_serial(&get_addr::REQUEST, &get_addr::RESPONSE);
+    assert_eq!(
+        block_on(pzem.get_addr()).unwrap(),
+        test_vectors::ADDR as u16
+    );
+}
+
+#[test]
+fn failed_reads() {
+    const CRC: u16 = modbus_crc(&[0x01, 0x84, 0x04]);
+    const EXCEPTION: [u8; 5] = [0x01, 0x84, 0x04, CRC as u8, (CRC >> 8) as u8];
+
+    let mut pzem = web_serial(&read::REQUEST, &EXCEPTION);
+    match block_on(pzem.read_raw()) {
+        Err(Error::Exception(Exception::SlaveError)) => {}
+        res => panic!("exception not reported: {:?}", res),
+    }
+
+    // The exception to the reset doesn't answer the read.
+    let mut pzem = web_serial(&read::REQUEST, &reset::EXCEPTION);
+    match block_on(pzem.read_raw()) {
+        Err(Error::PzemError) => {}
+        res => panic!("mismatched header accepted: {:?}", res),
+    }
+
+    let mut pzem = web_serial(&read::REQUEST, &read::RESPONSE[..10]);
+    match block_on(pzem.read_raw()) {
+        Err(Error::TimedOut { received: 10 }) => {}
+        res => panic!("truncated response accepted: {:?}", res),
+    }
+}
+
+#[test]
+fn blocking_adapter() {
+    use embedded_hal::blocking::delay::DelayUs;
+    use pzem004t::BlockingAdapter;
+
+    // Future suspending once, as if waiting for the UART.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+            if core::mem::replace(&mut self.0, true) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    // Port suspending before every read.
+    struct Suspending(Port);
+
+    impl AsyncPort for Suspending {
+        type Error = Infallible;
+
+        async fn write(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+            self.0.write(bytes).await
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+            YieldOnce(false).await;
+            self.0.read(buf).await
+        }
+    }
+
+    struct Delay(u32);
+
+    impl DelayUs<u32> for Delay {
+        fn delay_us(&mut self, us: u32) {
+            self.0 += us;
+        }
+    }
+
+    let port = Port {
+        request: &read::REQUEST,
+        response: &read::RESPONSE,
+        rx: VecDeque::new(),
+    };
+    let pzem = WebSerial::new(Suspending(port), Some(test_vectors::ADDR)).unwrap();
+    let mut pzem = BlockingAdapter::new(pzem, Delay(0)).with_interval(1000);
+    assert_eq!(pzem.read_raw().unwrap(), read::MEASUREMENT);
+
+    // A poll per 3 bytes read.
+    let (_, delay) = pzem.release();
+    assert_eq!(delay.0, 1000 * read::RESPONSE.len().div_ceil(3) as u32);
+}